@@ -1,40 +1,63 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 fn print_usage() {
-    eprintln!("Usage: blinker-cli scan <DIR> <DB_PATH>");
+    eprintln!("Usage:");
+    eprintln!("  blinker-cli scan <DIR> <DB_PATH> [--verify]");
+    eprintln!("  blinker-cli continue <DB_PATH> [--all]");
+    eprintln!("  blinker-cli backfill <DB_PATH>");
+    eprintln!("  blinker-cli prune <DB_PATH> [--include-annotated]");
+    eprintln!("  blinker-cli duplicates <DB_PATH>");
+    eprintln!("  blinker-cli lookup <DB_PATH> <ISBN|DOI|UUID>");
+    eprintln!("  blinker-cli authors <DB_PATH>");
+    eprintln!("  blinker-cli author <DB_PATH> <NAME>");
+    eprintln!("  blinker-cli roots <DB_PATH>");
+    eprintln!("  blinker-cli add-root <DB_PATH> <DIR> [--include PAT]... [--exclude PAT]... [--max-depth N] [--follow-symlinks]");
+    eprintln!("  blinker-cli remove-root <DB_PATH> <ROOT_ID>");
+    eprintln!("  blinker-cli enable-root <DB_PATH> <ROOT_ID>");
+    eprintln!("  blinker-cli disable-root <DB_PATH> <ROOT_ID>");
+    eprintln!("  blinker-cli rescan <DB_PATH> [--verify]");
+    eprintln!("  blinker-cli thumbnails <DB_PATH> <CACHE_DIR> [--size PX]");
+    eprintln!("  blinker-cli export <DB_PATH> <ARCHIVE>");
+    eprintln!("  blinker-cli import <DB_PATH> <ARCHIVE> [--remap FROM=TO]...");
+    eprintln!("  blinker-cli import-calibre <DB_PATH> <CALIBRE_LIBRARY>");
 }
 
-#[tokio::main]
-async fn main() {
-    tracing_subscriber::fmt::init();
-
-    let mut args = std::env::args().skip(1).collect::<Vec<_>>();
-    if args.len() < 3 || args[0].as_str() != "scan" {
-        print_usage();
-        std::process::exit(2);
-    }
-
-    let dir = PathBuf::from(args.remove(1));
-    let db_path = PathBuf::from(args.remove(1));
-
-    println!("Blinker CLI — scanning {:?} -> {:?}", dir, db_path);
-
-    let db = match blinker_core_library::LibraryDatabase::new(&db_path) {
+fn open_db(db_path: &Path) -> blinker_core_library::LibraryDatabase {
+    match blinker_core_library::LibraryDatabase::new(db_path) {
         Ok(db) => db,
         Err(e) => {
             eprintln!("Failed to open DB: {}", e);
             std::process::exit(1);
         }
-    };
+    }
+}
 
-    let scanner = blinker_core_library::LibraryScanner::new();
-    let paths = vec![dir.as_path()];
-    match scanner.scan_paths(&db, &paths) {
+/// Scanner printing progress to stderr and stopping at the next file on Ctrl-C.
+/// With `verify`, unchanged files are re-hashed too.
+fn interactive_scanner(verify: bool) -> blinker_core_library::LibraryScanner {
+    let token = blinker_core_library::CancelToken::new();
+    let on_interrupt = token.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            eprintln!("\nCancelling scan...");
+            on_interrupt.cancel();
+        }
+    });
+    blinker_core_library::LibraryScanner::new()
+        .with_cancel(token)
+        .with_verify(verify)
+        .with_progress(|p| eprint!("\r  {}/{} files", p.processed, p.discovered))
+}
+
+fn print_report(result: blinker_core_common::Result<blinker_core_library::ScanReport>) {
+    eprintln!();
+    match result {
         Ok(report) => {
-            println!("Scan complete:");
+            println!("{}", if report.cancelled { "Scan cancelled:" } else { "Scan complete:" });
             println!("  total:   {}", report.total);
             println!("  new:     {}", report.new);
             println!("  updated: {}", report.updated);
+            println!("  missing: {}", report.missing);
             if !report.errors.is_empty() {
                 println!("  errors ({}):", report.errors.len());
                 for e in report.errors.iter().take(10) { println!("    - {}", e); }
@@ -47,3 +70,414 @@ async fn main() {
         }
     }
 }
+
+fn scan(dir: PathBuf, db_path: PathBuf, verify: bool) {
+    println!("Blinker CLI — scanning {:?} -> {:?}", dir, db_path);
+
+    let db = open_db(&db_path);
+    let scanner = interactive_scanner(verify);
+    let paths = vec![dir.as_path()];
+    print_report(scanner.scan_paths(&db, &paths));
+}
+
+/// List documents found at more than one path.
+fn duplicates(db_path: PathBuf) {
+    use blinker_core_library::LocationStore;
+
+    let db = open_db(&db_path);
+    match db.duplicates() {
+        Ok(dups) if dups.is_empty() => println!("No duplicates."),
+        Ok(dups) => {
+            for d in dups {
+                println!("{} ({} copies)", d.item.metadata.title, d.locations.len());
+                for loc in d.locations {
+                    let primary = if loc.path == d.item.file_path { "  [primary]" } else { "" };
+                    let missing = if loc.missing_since.is_some() { "  [missing]" } else { "" };
+                    println!("    {}{}{}", loc.path.display(), primary, missing);
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!("Query error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// List documents carrying an ISBN, DOI or UUID.
+fn lookup(db_path: PathBuf, identifier: &str) {
+    use blinker_core_library::LibraryStore;
+
+    let Some((scheme, value)) = blinker_core_library::identifiers::recognize(None, identifier) else {
+        eprintln!("Not an ISBN, DOI or UUID: {}", identifier);
+        std::process::exit(2);
+    };
+    let db = open_db(&db_path);
+    match db.find_by_identifier(&scheme, &value) {
+        Ok(items) if items.is_empty() => println!("No documents with {} {}.", scheme, value),
+        Ok(items) => {
+            for item in items {
+                println!("{}  {}", item.metadata.title, item.file_path.display());
+            }
+        }
+        Err(e) => {
+            eprintln!("Query error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// List everyone credited on the library's items, by sort name.
+fn authors(db_path: PathBuf) {
+    use blinker_core_library::ContributorStore;
+
+    let db = open_db(&db_path);
+    match db.list_contributors(None) {
+        Ok(people) => {
+            for p in people {
+                println!("{}  ({} items)", p.sort_name, p.items);
+            }
+        }
+        Err(e) => {
+            eprintln!("Query error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// List the works of a person, matching variant spellings of their name.
+fn works_by(db_path: PathBuf, name: &str) {
+    use blinker_core_library::ContributorStore;
+
+    let db = open_db(&db_path);
+    match db.works_by(name) {
+        Ok(items) if items.is_empty() => println!("No documents by {}.", name),
+        Ok(items) => {
+            for item in items {
+                let credits: Vec<String> =
+                    item.metadata.contributors.iter().map(|c| format!("{} ({})", c.name, c.role.as_str())).collect();
+                println!("{}  [{}]  {}", item.metadata.title, credits.join(", "), item.file_path.display());
+            }
+        }
+        Err(e) => {
+            eprintln!("Query error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Scan every enabled stored root with its rules.
+fn rescan(db_path: PathBuf, verify: bool) {
+    let db = open_db(&db_path);
+    print_report(interactive_scanner(verify).rescan_all(&db));
+}
+
+/// Render missing cover thumbnails for every item, then trim the cache.
+fn thumbnails(db_path: PathBuf, cache_dir: PathBuf, size: Option<&str>) {
+    use blinker_core_library::LibraryStore;
+
+    let mut cache = blinker_core_library::ThumbnailCache::new(cache_dir);
+    if let Some(size) = size {
+        match size.parse() {
+            Ok(px) => cache = cache.with_max_edge(px),
+            Err(_) => {
+                eprintln!("Invalid size: {}", size);
+                std::process::exit(2);
+            }
+        }
+    }
+    let db = open_db(&db_path);
+    let items = match db.query(&blinker_core_library::LibraryQuery::default()) {
+        Ok(items) => items,
+        Err(e) => {
+            eprintln!("Query error: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let (mut cached, mut without, mut failed) = (0, 0, 0);
+    for item in items.iter().filter(|i| i.missing_since.is_none()) {
+        match cache.get_or_create(item) {
+            Ok(Some(_)) => cached += 1,
+            Ok(None) => without += 1,
+            Err(e) => {
+                failed += 1;
+                eprintln!("  {}: {}", item.file_path.display(), e);
+            }
+        }
+    }
+    let evicted = cache.evict().unwrap_or_else(|e| {
+        eprintln!("Eviction failed: {}", e);
+        0
+    });
+    println!("Thumbnails: {} cached, {} without cover, {} failed, {} evicted", cached, without, failed, evicted);
+}
+
+fn list_roots(db_path: PathBuf) {
+    use blinker_core_library::LibraryRootStore;
+
+    let db = open_db(&db_path);
+    match db.list_roots() {
+        Ok(roots) if roots.is_empty() => println!("No library roots."),
+        Ok(roots) => {
+            for r in roots {
+                println!("{}  {}{}", r.id, r.path.display(), if r.enabled { "" } else { "  (disabled)" });
+                if !r.rules.include.is_empty() { println!("    include: {}", r.rules.include.join(", ")); }
+                if !r.rules.exclude.is_empty() { println!("    exclude: {}", r.rules.exclude.join(", ")); }
+                if let Some(d) = r.rules.max_depth { println!("    max depth: {}", d); }
+                if r.rules.follow_symlinks { println!("    follows symlinks"); }
+            }
+        }
+        Err(e) => {
+            eprintln!("Query error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn add_root(db_path: PathBuf, dir: PathBuf, opts: &[&str]) {
+    use blinker_core_library::LibraryRootStore;
+
+    let mut rules = blinker_core_library::ScanRules::default();
+    let mut it = opts.iter();
+    while let Some(opt) = it.next() {
+        match *opt {
+            "--follow-symlinks" => rules.follow_symlinks = true,
+            "--include" | "--exclude" | "--max-depth" => {
+                let Some(value) = it.next() else {
+                    print_usage();
+                    std::process::exit(2);
+                };
+                match *opt {
+                    "--include" => rules.include.push(value.to_string()),
+                    "--exclude" => rules.exclude.push(value.to_string()),
+                    _ => match value.parse() {
+                        Ok(n) => rules.max_depth = Some(n),
+                        Err(_) => {
+                            eprintln!("Invalid max depth: {}", value);
+                            std::process::exit(2);
+                        }
+                    },
+                }
+            }
+            _ => {
+                print_usage();
+                std::process::exit(2);
+            }
+        }
+    }
+
+    let db = open_db(&db_path);
+    match db.add_root(&dir, rules) {
+        Ok(root) => println!("Added root {} ({})", root.path.display(), root.id),
+        Err(e) => {
+            eprintln!("Add root error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn remove_root(db_path: PathBuf, id: &str) {
+    use blinker_core_library::LibraryRootStore;
+
+    let db = open_db(&db_path);
+    if let Err(e) = db.remove_root(id) {
+        eprintln!("Remove root error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+fn set_root_enabled(db_path: PathBuf, id: &str, enabled: bool) {
+    use blinker_core_library::LibraryRootStore;
+
+    let db = open_db(&db_path);
+    let result = db
+        .get_root(id)
+        .and_then(|r| r.ok_or_else(|| blinker_core_common::BlinkerError::NotFound(format!("root {}", id))))
+        .and_then(|r| db.update_root(id, r.rules, enabled));
+    if let Err(e) = result {
+        eprintln!("Update root error: {}", e);
+        std::process::exit(1);
+    }
+}
+
+/// List items to resume, most recently opened first.
+fn continue_reading(db_path: PathBuf, all: bool) {
+    use blinker_core_library::ReadingStateStore;
+
+    let db = open_db(&db_path);
+    let entries = if all { db.recently_opened(20) } else { db.in_progress(20) };
+    match entries {
+        Ok(entries) if entries.is_empty() => println!("Nothing to continue."),
+        Ok(entries) => {
+            for e in entries {
+                println!(
+                    "{:>3.0}%  p.{}/{}  {}  ({})",
+                    e.state.progress * 100.0,
+                    e.state.current_page,
+                    e.state.total_pages,
+                    e.item.metadata.title,
+                    e.item.file_path.display(),
+                );
+            }
+        }
+        Err(e) => {
+            eprintln!("Query error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Re-extract full metadata for items indexed by older versions.
+fn backfill(db_path: PathBuf) {
+    let db = open_db(&db_path);
+    match db.backfill_metadata() {
+        Ok(n) => println!("Backfilled metadata for {} items", n),
+        Err(e) => {
+            eprintln!("Backfill error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Delete entries whose files were found missing by a previous scan.
+fn prune(db_path: PathBuf, include_annotated: bool) {
+    use blinker_core_library::LibraryStore;
+
+    let db = open_db(&db_path);
+    match db.prune_missing(include_annotated) {
+        Ok(rep) => {
+            println!("Removed {} missing items", rep.removed);
+            if rep.kept > 0 {
+                println!("Kept {} with annotations or reading state (use --include-annotated)", rep.kept);
+            }
+        }
+        Err(e) => {
+            eprintln!("Prune error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Write the library's user data to a zip archive.
+fn export(db_path: PathBuf, archive: PathBuf) {
+    use blinker_core_library::ArchiveStore;
+
+    let db = open_db(&db_path);
+    match db.export_archive(&archive) {
+        Ok(rep) => println!(
+            "Exported {} items, {} annotations and {} collections to {}",
+            rep.items, rep.annotations, rep.collections, archive.display()
+        ),
+        Err(e) => {
+            eprintln!("Export error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Merge an exported archive, remapping path prefixes of items not indexed yet.
+fn import(db_path: PathBuf, archive: PathBuf, opts: &[&str]) {
+    use blinker_core_library::{ArchiveStore, PathRemap};
+
+    let mut remaps = vec![];
+    let mut it = opts.iter();
+    while let Some(opt) = it.next() {
+        let remap = match (*opt, it.next()) {
+            ("--remap", Some(value)) => value.split_once('='),
+            _ => None,
+        };
+        let Some((from, to)) = remap else {
+            print_usage();
+            std::process::exit(2);
+        };
+        remaps.push(PathRemap::new(from, to));
+    }
+
+    let db = open_db(&db_path);
+    match db.import_archive(&archive, &remaps) {
+        Ok(rep) => {
+            println!(
+                "Matched {} items, indexed {}; imported {} annotations and {} reading states; created {} tags and {} collections",
+                rep.matched, rep.indexed, rep.annotations, rep.reading_states, rep.tags_created, rep.collections_created
+            );
+            if !rep.unmatched.is_empty() {
+                println!("Not found ({}):", rep.unmatched.len());
+                for path in &rep.unmatched {
+                    println!("  {}", path);
+                }
+            }
+        }
+        Err(e) => {
+            eprintln!("Import error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Index a Calibre library's books in place with Calibre's metadata.
+fn import_calibre(db_path: PathBuf, library: PathBuf) {
+    use blinker_core_library::CalibreImport;
+
+    let db = open_db(&db_path);
+    match db.import_calibre(&library) {
+        Ok(rep) => {
+            println!("Imported {} books: {} new items, {} already indexed", rep.books, rep.created, rep.existing);
+            for (format, n) in &rep.unsupported_formats {
+                println!("  Unsupported format {}: {} files", format, n);
+            }
+            for (field, n) in &rep.unmapped_fields {
+                println!("  Not imported: {} on {} books", field, n);
+            }
+            for title in &rep.skipped_books {
+                println!("  Skipped (no usable file): {}", title);
+            }
+            for path in &rep.missing_files {
+                println!("  Missing: {}", path.display());
+            }
+            for err in &rep.errors {
+                eprintln!("  {}", err);
+            }
+        }
+        Err(e) => {
+            eprintln!("Calibre import error: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    tracing_subscriber::fmt::init();
+
+    let args = std::env::args().skip(1).collect::<Vec<_>>();
+    match args.iter().map(String::as_str).collect::<Vec<_>>().as_slice() {
+        ["scan", dir, db_path] => scan(PathBuf::from(dir), PathBuf::from(db_path), false),
+        ["scan", dir, db_path, "--verify"] => scan(PathBuf::from(dir), PathBuf::from(db_path), true),
+        ["continue", db_path] => continue_reading(PathBuf::from(db_path), false),
+        ["continue", db_path, "--all"] => continue_reading(PathBuf::from(db_path), true),
+        ["backfill", db_path] => backfill(PathBuf::from(db_path)),
+        ["prune", db_path] => prune(PathBuf::from(db_path), false),
+        ["prune", db_path, "--include-annotated"] => prune(PathBuf::from(db_path), true),
+        ["duplicates", db_path] => duplicates(PathBuf::from(db_path)),
+        ["lookup", db_path, identifier] => lookup(PathBuf::from(db_path), identifier),
+        ["authors", db_path] => authors(PathBuf::from(db_path)),
+        ["author", db_path, name] => works_by(PathBuf::from(db_path), name),
+        ["roots", db_path] => list_roots(PathBuf::from(db_path)),
+        ["add-root", db_path, dir, opts @ ..] => add_root(PathBuf::from(db_path), PathBuf::from(dir), opts),
+        ["remove-root", db_path, id] => remove_root(PathBuf::from(db_path), id),
+        ["enable-root", db_path, id] => set_root_enabled(PathBuf::from(db_path), id, true),
+        ["disable-root", db_path, id] => set_root_enabled(PathBuf::from(db_path), id, false),
+        ["rescan", db_path] => rescan(PathBuf::from(db_path), false),
+        ["rescan", db_path, "--verify"] => rescan(PathBuf::from(db_path), true),
+        ["thumbnails", db_path, dir] => thumbnails(PathBuf::from(db_path), PathBuf::from(dir), None),
+        ["thumbnails", db_path, dir, "--size", px] => thumbnails(PathBuf::from(db_path), PathBuf::from(dir), Some(px)),
+        ["export", db_path, archive] => export(PathBuf::from(db_path), PathBuf::from(archive)),
+        ["import-calibre", db_path, library] => import_calibre(PathBuf::from(db_path), PathBuf::from(library)),
+        ["import", db_path, archive, opts @ ..] => import(PathBuf::from(db_path), PathBuf::from(archive), opts),
+        _ => {
+            print_usage();
+            std::process::exit(2);
+        }
+    }
+}
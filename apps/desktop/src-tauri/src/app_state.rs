@@ -1,23 +1,33 @@
 use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::collections::HashMap;
+use blinker_core_library::{CancelToken, LibraryWatcher, ThumbnailCache};
 use blinker_core_render::AnyRenderer;
 
 pub struct ReaderSession {
     pub renderer: AnyRenderer,
     pub item_id: String,
+    pub total_pages: usize,
 }
 
 pub struct AppState {
     pub db_path: PathBuf,
     pub sessions: Arc<Mutex<HashMap<String, ReaderSession>>>,
+    pub watcher: Mutex<Option<LibraryWatcher>>,
+    /// Cancels the scan started last, if any.
+    pub scan_cancel: Mutex<Option<CancelToken>>,
+    /// Cover thumbnails, filled while scanning and on request.
+    pub thumbnails: ThumbnailCache,
 }
 
 impl AppState {
-    pub fn new(db_path: PathBuf) -> Self {
+    pub fn new(db_path: PathBuf, thumbnails: ThumbnailCache) -> Self {
         Self {
             db_path,
+            thumbnails,
             sessions: Arc::new(Mutex::new(HashMap::new())),
+            watcher: Mutex::new(None),
+            scan_cancel: Mutex::new(None),
         }
     }
 }
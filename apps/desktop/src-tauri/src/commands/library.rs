@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
 use tauri::State;
-use blinker_core_library::LibraryStore;
+use blinker_core_library::{
+    ArchiveStore, CalibreImport, CalibreReport, CancelToken, Collection, CollectionStore, ContributorStore, ContributorSummary, ExportReport, Identifier, IdentifierStore, ImportReport, ItemLocation, LibraryQuery, LibraryRoot, LibraryRootStore, LibraryStore, LocationStore, MetadataField, PathRemap, ReadingState, ReadingStateStore, ScanRules, SeriesStore, SeriesSummary, Tag,
+};
+use blinker_core_common::types::{Contributor, ContributorRole};
 use crate::app_state::AppState;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -8,7 +11,22 @@ pub struct ScanReport {
     pub total: usize,
     pub new: usize,
     pub updated: usize,
+    pub missing: usize,
     pub errors: Vec<String>,
+    pub cancelled: bool,
+}
+
+impl From<blinker_core_library::ScanReport> for ScanReport {
+    fn from(rep: blinker_core_library::ScanReport) -> Self {
+        Self {
+            total: rep.total,
+            new: rep.new,
+            updated: rep.updated,
+            missing: rep.missing,
+            errors: rep.errors,
+            cancelled: rep.cancelled,
+        }
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -17,18 +35,76 @@ pub struct LibraryItem {
     pub path: String,
     pub title: String,
     pub author: Option<String>,
+    pub publisher: Option<String>,
+    pub subject: Option<String>,
+    pub language: Option<String>,
+    pub page_count: Option<usize>,
+    pub series: Option<String>,
+    pub series_index: Option<f64>,
+    pub right_to_left: bool,
+    /// Everyone credited, in the order the file lists them.
+    pub contributors: Vec<Contributor>,
     pub file_type: String,
     pub hash: String,
     pub tags: Vec<String>,
+    pub missing_since: Option<i64>,
+    pub score: Option<f64>,
+}
+
+impl From<blinker_core_library::LibraryItem> for LibraryItem {
+    fn from(it: blinker_core_library::LibraryItem) -> Self {
+        Self {
+            id: it.id,
+            path: it.file_path.to_string_lossy().to_string(),
+            title: it.metadata.title,
+            author: it.metadata.author,
+            publisher: it.metadata.publisher,
+            subject: it.metadata.subject,
+            language: it.metadata.language,
+            page_count: it.metadata.page_count,
+            series: it.metadata.series,
+            series_index: it.metadata.series_index,
+            right_to_left: it.metadata.right_to_left,
+            contributors: it.metadata.contributors,
+            file_type: it.file_type,
+            hash: it.file_hash,
+            tags: it.tags,
+            missing_since: it.missing_since,
+            score: it.score,
+        }
+    }
+}
+
+/// Scanner emitting `scan-progress` events, stoppable with `cancel_scan`.
+/// With `verify`, unchanged files are re-hashed too.
+fn ui_scanner(app: tauri::AppHandle, state: &AppState, verify: bool) -> blinker_core_library::LibraryScanner {
+    use tauri::Manager;
+
+    let token = CancelToken::new();
+    *state.scan_cancel.lock().unwrap() = Some(token.clone());
+    blinker_core_library::LibraryScanner::new()
+        .with_cancel(token)
+        .with_verify(verify)
+        .with_thumbnails(state.thumbnails.clone())
+        .with_progress(move |progress| {
+            if let Err(e) = app.emit_all("scan-progress", progress) {
+                tracing::warn!("Failed to emit scan progress: {}", e);
+            }
+        })
 }
 
 #[tauri::command]
-pub async fn scan_library(state: State<'_, AppState>, paths: Vec<String>) -> Result<ScanReport, String> {
+pub async fn scan_library(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    paths: Vec<String>,
+    verify: Option<bool>,
+) -> Result<ScanReport, String> {
     tracing::info!("Scanning library paths: {:?}", paths);
     let db_path = state.db_path.clone();
+    let scanner = ui_scanner(app, &state, verify.unwrap_or(false));
     let rep = tauri::async_runtime::spawn_blocking(move || {
         let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
-        let scanner = blinker_core_library::LibraryScanner::new();
         let pbufs: Vec<std::path::PathBuf> = paths.into_iter().map(Into::into).collect();
         let prefs: Vec<&std::path::Path> = pbufs.iter().map(|p| p.as_path()).collect();
         scanner.scan_paths(&db, &prefs)
@@ -36,7 +112,94 @@ pub async fn scan_library(state: State<'_, AppState>, paths: Vec<String>) -> Res
     .await
     .map_err(|e| e.to_string())?
     .map_err(|e| e.to_string())?;
-    Ok(ScanReport { total: rep.total, new: rep.new, updated: rep.updated, errors: rep.errors })
+    Ok(rep.into())
+}
+
+/// Scan every enabled stored root with its own rules.
+#[tauri::command]
+pub async fn rescan_all(
+    app: tauri::AppHandle,
+    state: State<'_, AppState>,
+    verify: Option<bool>,
+) -> Result<ScanReport, String> {
+    tracing::info!("Rescanning all library roots");
+    let db_path = state.db_path.clone();
+    let scanner = ui_scanner(app, &state, verify.unwrap_or(false));
+    let rep = tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        scanner.rescan_all(&db)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+    Ok(rep.into())
+}
+
+/// Stop the running scan; it returns a report with `cancelled` set.
+#[tauri::command]
+pub async fn cancel_scan(state: State<'_, AppState>) -> Result<(), String> {
+    if let Some(token) = state.scan_cancel.lock().unwrap().take() {
+        tracing::info!("Cancelling library scan");
+        token.cancel();
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn list_roots(state: State<'_, AppState>) -> Result<Vec<LibraryRoot>, String> {
+    let db_path = state.db_path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.list_roots()
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn add_root(state: State<'_, AppState>, path: String, rules: Option<ScanRules>) -> Result<LibraryRoot, String> {
+    tracing::info!("Adding library root {:?}", path);
+    let db_path = state.db_path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.add_root(std::path::Path::new(&path), rules.unwrap_or_default())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn update_root(
+    state: State<'_, AppState>,
+    id: String,
+    rules: ScanRules,
+    enabled: bool,
+) -> Result<LibraryRoot, String> {
+    tracing::info!("Updating library root {}", id);
+    let db_path = state.db_path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.update_root(&id, rules, enabled)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// Forget a root; items already indexed from it stay in the library.
+#[tauri::command]
+pub async fn remove_root(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    tracing::info!("Removing library root {}", id);
+    let db_path = state.db_path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.remove_root(&id)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
@@ -51,24 +214,619 @@ pub async fn query_library(state: State<'_, AppState>, filters: serde_json::Valu
     if let Some(types) = filters.get("file_types").and_then(|v| v.as_array()) {
         q.file_types = Some(types.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect());
     }
+    if let Some(tags) = filters.get("tags").and_then(|v| v.as_array()) {
+        q.tags = Some(tags.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect());
+    }
+    if let Some(mode) = filters.get("tag_match") {
+        q.tag_match = serde_json::from_value(mode.clone()).map_err(|e| e.to_string())?;
+    }
+    if let Some(languages) = filters.get("languages").and_then(|v| v.as_array()) {
+        q.languages = Some(languages.iter().filter_map(|v| v.as_str().map(|s| s.to_string())).collect());
+    }
+    if let Some(v) = filters.get("size") { q.size = serde_json::from_value(v.clone()).map_err(|e| e.to_string())?; }
+    if let Some(v) = filters.get("page_count") { q.page_count = serde_json::from_value(v.clone()).map_err(|e| e.to_string())?; }
+    if let Some(v) = filters.get("added") { q.added = serde_json::from_value(v.clone()).map_err(|e| e.to_string())?; }
+    if let Some(v) = filters.get("last_opened") { q.last_opened = serde_json::from_value(v.clone()).map_err(|e| e.to_string())?; }
+    if let Some(series) = filters.get("series").and_then(|v| v.as_str()) { q.series = Some(series.to_string()); }
+    if let Some(author) = filters.get("author").and_then(|v| v.as_str()) { q.author = Some(author.to_string()); }
+    if let Some(v) = filters.get("read_status") { q.read_status = serde_json::from_value(v.clone()).map_err(|e| e.to_string())?; }
+    if let Some(v) = filters.get("sort") { q.sort = serde_json::from_value(v.clone()).map_err(|e| e.to_string())?; }
+    if let Some(v) = filters.get("direction") { q.direction = serde_json::from_value(v.clone()).map_err(|e| e.to_string())?; }
+    if let Some(offset) = filters.get("offset").and_then(|v| v.as_u64()) { q.offset = offset as usize; }
 
     let items = db.query(&q).map_err(|e| e.to_string())?;
-    let out = items.into_iter().map(|it| LibraryItem {
-        id: it.id,
-        path: it.file_path.to_string_lossy().to_string(),
-        title: it.metadata.title,
-        author: it.metadata.author,
-        file_type: it.file_type,
-        hash: it.file_hash,
-        tags: it.tags,
-    }).collect();
-    Ok(out)
+    Ok(items.into_iter().map(Into::into).collect())
 }
 
+/// Watch `paths` for changes, replacing any previous watcher. Applied changes
+/// are emitted to the UI as `library-changed` events.
 #[tauri::command]
-pub async fn update_metadata(id: String, fields: serde_json::Value) -> Result<(), String> {
-    // TODO: Implement metadata update
-    tracing::info!("Updating metadata for {}: {:?}", id, fields);
+pub async fn watch_library(app: tauri::AppHandle, state: State<'_, AppState>, paths: Vec<String>) -> Result<(), String> {
+    use tauri::Manager;
 
+    tracing::info!("Watching library paths: {:?}", paths);
+    let db = blinker_core_library::LibraryDatabase::new(&state.db_path).map_err(|e| e.to_string())?;
+    let roots: Vec<std::path::PathBuf> = paths.into_iter().map(Into::into).collect();
+    // Stop the old watcher first so no change is applied twice; other
+    // connections writing meanwhile wait on the database's busy timeout
+    state.watcher.lock().unwrap().take();
+    let watcher = blinker_core_library::LibraryWatcher::spawn(
+        db,
+        &roots,
+        blinker_core_library::watcher::DEFAULT_DEBOUNCE,
+        move |change| {
+            if let Err(e) = app.emit_all("library-changed", &change) {
+                tracing::warn!("Failed to emit library change: {}", e);
+            }
+        },
+    )
+    .map_err(|e| e.to_string())?;
+    *state.watcher.lock().unwrap() = Some(watcher);
     Ok(())
 }
+
+#[tauri::command]
+pub async fn stop_watching(state: State<'_, AppState>) -> Result<(), String> {
+    tracing::info!("Stopping library watcher");
+    state.watcher.lock().unwrap().take();
+    Ok(())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PruneReport {
+    pub removed: usize,
+    pub kept: usize,
+}
+
+#[tauri::command]
+pub async fn list_missing(state: State<'_, AppState>) -> Result<Vec<LibraryItem>, String> {
+    let db_path = state.db_path.clone();
+    let items = tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.missing_items()
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+    Ok(items.into_iter().map(Into::into).collect())
+}
+
+/// Remove missing items; those with annotations or reading state are only
+/// removed when `include_annotated` is true.
+#[tauri::command]
+pub async fn prune_missing(state: State<'_, AppState>, include_annotated: bool) -> Result<PruneReport, String> {
+    tracing::info!("Pruning missing items (include_annotated: {})", include_annotated);
+    let db_path = state.db_path.clone();
+    let rep = tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.prune_missing(include_annotated)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+    Ok(PruneReport { removed: rep.removed, kept: rep.kept })
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DuplicateItem {
+    pub item: LibraryItem,
+    pub locations: Vec<ItemLocation>,
+}
+
+#[tauri::command]
+pub async fn item_locations(state: State<'_, AppState>, id: String) -> Result<Vec<ItemLocation>, String> {
+    let db_path = state.db_path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.item_locations(&id)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// Documents found at more than one path, most copies first.
+#[tauri::command]
+pub async fn list_duplicates(state: State<'_, AppState>) -> Result<Vec<DuplicateItem>, String> {
+    let db_path = state.db_path.clone();
+    let dups = tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.duplicates()
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+    Ok(dups.into_iter().map(|d| DuplicateItem { item: d.item.into(), locations: d.locations }).collect())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ContentSearchHit {
+    pub item: LibraryItem,
+    pub score: f64,
+    pub matches: Vec<blinker_core_library::ContentMatch>,
+}
+
+#[tauri::command]
+pub async fn search_library_content(
+    state: State<'_, AppState>,
+    text: String,
+    limit: Option<usize>,
+) -> Result<Vec<ContentSearchHit>, String> {
+    tracing::info!("Searching library contents: {}", text);
+    let db_path = state.db_path.clone();
+    let hits = tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.search_content(&text, limit.unwrap_or(20))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+    Ok(hits.into_iter().map(|h| ContentSearchHit {
+        item: h.item.into(),
+        score: h.score,
+        matches: h.matches,
+    }).collect())
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ReadingEntry {
+    pub item: LibraryItem,
+    pub state: ReadingState,
+}
+
+/// Items to offer for resuming: unfinished ones, or every opened item when `all` is set.
+#[tauri::command]
+pub async fn continue_reading(
+    state: State<'_, AppState>,
+    limit: Option<usize>,
+    all: Option<bool>,
+) -> Result<Vec<ReadingEntry>, String> {
+    let db_path = state.db_path.clone();
+    let entries = tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        let limit = limit.unwrap_or(20);
+        if all.unwrap_or(false) {
+            db.recently_opened(limit)
+        } else {
+            db.in_progress(limit)
+        }
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+    Ok(entries.into_iter().map(|e| ReadingEntry { item: e.item.into(), state: e.state }).collect())
+}
+
+/// Mark an item read to the end, e.g. a single-page or flow document whose
+/// position alone never completes it.
+#[tauri::command]
+pub async fn mark_finished(state: State<'_, AppState>, id: String) -> Result<ReadingState, String> {
+    let db_path = state.db_path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.mark_finished(&id)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// Apply user edits. `fields` maps field names (title, author, publisher,
+/// subject, language, series, series_index) to a string, or null to blank
+/// the field. A `series_index` must be a number, such as `"2"` or `"1.5"`.
+#[tauri::command]
+pub async fn update_metadata(
+    state: State<'_, AppState>,
+    id: String,
+    fields: serde_json::Value,
+) -> Result<(), String> {
+    tracing::info!("Updating metadata for {}: {:?}", id, fields);
+
+    let obj = fields.as_object().ok_or("fields must be an object")?;
+    let mut changes = Vec::with_capacity(obj.len());
+    for (key, value) in obj {
+        let field = MetadataField::from_column(key)
+            .ok_or_else(|| format!("Unknown metadata field: {}", key))?;
+        let value = match value {
+            serde_json::Value::Null => None,
+            serde_json::Value::String(s) => Some(s.clone()),
+            other => return Err(format!("Invalid value for {}: {}", key, other)),
+        };
+        changes.push((field, value));
+    }
+
+    let db_path = state.db_path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.update_metadata(&id, &changes)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn revert_metadata(
+    state: State<'_, AppState>,
+    id: String,
+    fields: Vec<MetadataField>,
+) -> Result<(), String> {
+    tracing::info!("Reverting metadata for {}: {:?}", id, fields);
+    let db_path = state.db_path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.revert_metadata(&id, &fields)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn list_tags(state: State<'_, AppState>) -> Result<Vec<Tag>, String> {
+    let db_path = state.db_path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.list_tags()
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn create_tag(state: State<'_, AppState>, name: String, color: Option<String>) -> Result<Tag, String> {
+    tracing::info!("Creating tag {:?}", name);
+    let db_path = state.db_path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.create_tag(&name, color.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn rename_tag(state: State<'_, AppState>, id: String, name: String) -> Result<(), String> {
+    tracing::info!("Renaming tag {} to {:?}", id, name);
+    let db_path = state.db_path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.rename_tag(&id, &name)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_tag_color(state: State<'_, AppState>, id: String, color: Option<String>) -> Result<(), String> {
+    let db_path = state.db_path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.set_tag_color(&id, color.as_deref())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_tag(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    tracing::info!("Deleting tag {}", id);
+    let db_path = state.db_path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.delete_tag(&id)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_item_tag(
+    state: State<'_, AppState>,
+    item_id: String,
+    tag_id: String,
+    assigned: bool,
+) -> Result<(), String> {
+    tracing::info!("Setting tag {} on {}: {}", tag_id, item_id, assigned);
+    let db_path = state.db_path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        if assigned {
+            db.assign_tag(&item_id, &tag_id)
+        } else {
+            db.unassign_tag(&item_id, &tag_id)
+        }
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn list_collections(state: State<'_, AppState>) -> Result<Vec<Collection>, String> {
+    let db_path = state.db_path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.list_collections()
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// Create a collection: a smart one when `query` is given, otherwise an
+/// empty manual one.
+#[tauri::command]
+pub async fn create_collection(
+    state: State<'_, AppState>,
+    name: String,
+    query: Option<LibraryQuery>,
+) -> Result<Collection, String> {
+    tracing::info!("Creating collection {:?}", name);
+    let db_path = state.db_path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        match query {
+            Some(query) => db.create_smart_collection(&name, &query),
+            None => db.create_collection(&name),
+        }
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn rename_collection(state: State<'_, AppState>, id: String, name: String) -> Result<(), String> {
+    tracing::info!("Renaming collection {} to {:?}", id, name);
+    let db_path = state.db_path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.rename_collection(&id, &name)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn set_collection_query(state: State<'_, AppState>, id: String, query: LibraryQuery) -> Result<(), String> {
+    let db_path = state.db_path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.set_collection_query(&id, &query)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn delete_collection(state: State<'_, AppState>, id: String) -> Result<(), String> {
+    tracing::info!("Deleting collection {}", id);
+    let db_path = state.db_path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.delete_collection(&id)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn collection_items(state: State<'_, AppState>, id: String) -> Result<Vec<LibraryItem>, String> {
+    let db_path = state.db_path.clone();
+    let items = tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.collection_items(&id)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+    Ok(items.into_iter().map(Into::into).collect())
+}
+
+/// Add an item to a manual collection at `position`, or at the end; an item
+/// already in it is moved.
+#[tauri::command]
+pub async fn add_to_collection(
+    state: State<'_, AppState>,
+    id: String,
+    item_id: String,
+    position: Option<usize>,
+) -> Result<(), String> {
+    let db_path = state.db_path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.add_to_collection(&id, &item_id, position)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn remove_from_collection(state: State<'_, AppState>, id: String, item_id: String) -> Result<(), String> {
+    let db_path = state.db_path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.remove_from_collection(&id, &item_id)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn list_series(state: State<'_, AppState>) -> Result<Vec<SeriesSummary>, String> {
+    let db_path = state.db_path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.list_series()
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn series_items(state: State<'_, AppState>, name: String) -> Result<Vec<LibraryItem>, String> {
+    let db_path = state.db_path.clone();
+    let items = tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.series_items(&name)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+    Ok(items.into_iter().map(Into::into).collect())
+}
+
+/// People credited on the library's items, by sort name; with `role`,
+/// only those credited in that role.
+#[tauri::command]
+pub async fn list_contributors(
+    state: State<'_, AppState>,
+    role: Option<ContributorRole>,
+) -> Result<Vec<ContributorSummary>, String> {
+    let db_path = state.db_path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.list_contributors(role)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// Items crediting a person, however their name is spelled in each file.
+#[tauri::command]
+pub async fn works_by(state: State<'_, AppState>, name: String) -> Result<Vec<LibraryItem>, String> {
+    let db_path = state.db_path.clone();
+    let items = tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.works_by(&name)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+    Ok(items.into_iter().map(Into::into).collect())
+}
+
+/// The volume after `id` in its series, for offering "read next".
+#[tauri::command]
+pub async fn next_in_series(state: State<'_, AppState>, id: String) -> Result<Option<LibraryItem>, String> {
+    let db_path = state.db_path.clone();
+    let item = tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.next_in_series(&id)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+    Ok(item.map(Into::into))
+}
+
+/// Path of an item's cover thumbnail, rendering it if it is not cached yet.
+/// `None` for documents without a cover.
+#[tauri::command]
+pub async fn item_thumbnail(state: State<'_, AppState>, id: String) -> Result<Option<String>, String> {
+    let db_path = state.db_path.clone();
+    let thumbnails = state.thumbnails.clone();
+    let path = tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        let item = db
+            .get_item(&id)?
+            .ok_or_else(|| blinker_core_common::BlinkerError::NotFound(format!("item {}", id)))?;
+        thumbnails.get_or_create(&item)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+    Ok(path.map(|p| p.to_string_lossy().to_string()))
+}
+
+/// Write tags, collections, reading state and annotations to a zip archive.
+#[tauri::command]
+pub async fn export_library(state: State<'_, AppState>, path: String) -> Result<ExportReport, String> {
+    tracing::info!("Exporting library to {}", path);
+    let db_path = state.db_path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.export_archive(std::path::Path::new(&path))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// Merge an exported archive. Items are matched by file hash; `remaps`
+/// rewrite the recorded paths of items not in this library yet.
+#[tauri::command]
+pub async fn import_library(state: State<'_, AppState>, path: String, remaps: Option<Vec<PathRemap>>) -> Result<ImportReport, String> {
+    tracing::info!("Importing library from {}", path);
+    let db_path = state.db_path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.import_archive(std::path::Path::new(&path), &remaps.unwrap_or_default())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// Index the books of a Calibre library in place with Calibre's metadata.
+#[tauri::command]
+pub async fn import_calibre(state: State<'_, AppState>, path: String) -> Result<CalibreReport, String> {
+    tracing::info!("Importing Calibre library {}", path);
+    let db_path = state.db_path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.import_calibre(std::path::Path::new(&path))
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub async fn item_identifiers(state: State<'_, AppState>, id: String) -> Result<Vec<Identifier>, String> {
+    let db_path = state.db_path.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.item_identifiers(&id)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
+/// Documents carrying an identifier. Without `scheme` the identifier is
+/// recognised from its form (ISBN, DOI, UUID, `urn:` prefixes).
+#[tauri::command]
+pub async fn find_by_identifier(
+    state: State<'_, AppState>,
+    identifier: String,
+    scheme: Option<String>,
+) -> Result<Vec<LibraryItem>, String> {
+    let (scheme, value) = match scheme {
+        Some(scheme) => (scheme, identifier),
+        None => blinker_core_library::identifiers::recognize(None, &identifier)
+            .ok_or_else(|| format!("not an ISBN, DOI or UUID: {}", identifier))?,
+    };
+    let db_path = state.db_path.clone();
+    let items = tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.find_by_identifier(&scheme, &value)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())?;
+    Ok(items.into_iter().map(Into::into).collect())
+}
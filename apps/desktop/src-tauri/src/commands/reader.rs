@@ -1,6 +1,6 @@
 use serde::{Deserialize, Serialize};
 use tauri::State;
-use blinker_core_library::LibraryStore;
+use blinker_core_library::{LibraryStore, ReadingStateStore};
 use blinker_core_render::AnyRenderer;
 use crate::app_state::{AppState, ReaderSession};
 
@@ -40,6 +40,9 @@ pub async fn open_document(id: String, state: State<'_, AppState>) -> Result<Rea
         let item = db.get_item(&id)
             .map_err(|e| e.to_string())?
             .ok_or_else(|| format!("Document not found: {}", id))?;
+        if !item.file_path.is_file() {
+            return Err(format!("Document file is missing: {}", item.file_path.display()));
+        }
 
         // Open the renderer
         let renderer = AnyRenderer::open_for(&item.file_path)
@@ -49,12 +52,20 @@ pub async fn open_document(id: String, state: State<'_, AppState>) -> Result<Rea
         let total_pages = renderer.page_count()
             .map_err(|e| e.to_string())?;
 
-        Ok::<_, String>((renderer, item.id, total_pages))
+        // Resume where the reader left off
+        let last_page = db.reading_state(&item.id)
+            .map_err(|e| e.to_string())?
+            .map(|s| s.current_page)
+            .unwrap_or(1);
+        let state = db.record_position(&item.id, last_page, total_pages)
+            .map_err(|e| e.to_string())?;
+
+        Ok::<_, String>((renderer, item.id, total_pages, state.current_page))
     })
     .await
     .map_err(|e| e.to_string())??;
 
-    let (renderer, item_id, total_pages) = result;
+    let (renderer, item_id, total_pages, current_page) = result;
 
     // Create a new session
     let session_id = uuid::Uuid::new_v4().to_string();
@@ -62,6 +73,7 @@ pub async fn open_document(id: String, state: State<'_, AppState>) -> Result<Rea
     let session = ReaderSession {
         renderer,
         item_id: item_id.clone(),
+        total_pages,
     };
 
     // Store the session
@@ -73,11 +85,39 @@ pub async fn open_document(id: String, state: State<'_, AppState>) -> Result<Rea
     Ok(ReaderSessionResponse {
         session_id,
         document_id: item_id,
-        current_page: 1,
+        current_page,
         total_pages,
     })
 }
 
+#[tauri::command]
+pub async fn update_reading_progress(
+    session_id: String,
+    page: usize,
+    elapsed_secs: u64,
+    state: State<'_, AppState>,
+) -> Result<(), String> {
+    let (item_id, total_pages) = {
+        let sessions = state.sessions.lock().unwrap();
+        let session = sessions.get(&session_id)
+            .ok_or_else(|| format!("Session not found: {}", session_id))?;
+        (session.item_id.clone(), session.total_pages)
+    };
+    let db_path = state.db_path.clone();
+
+    tauri::async_runtime::spawn_blocking(move || {
+        let db = blinker_core_library::LibraryDatabase::new(&db_path)?;
+        db.record_position(&item_id, page, total_pages)?;
+        if elapsed_secs > 0 {
+            db.add_reading_time(&item_id, elapsed_secs)?;
+        }
+        Ok::<_, blinker_core_common::BlinkerError>(())
+    })
+    .await
+    .map_err(|e| e.to_string())?
+    .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
 pub async fn render_page(
     session_id: String,
@@ -24,16 +24,59 @@ fn main() {
             let db_path = app_dir.join("blinker.db");
             // Ensure DB is initialized once
             let _ = blinker_core_library::LibraryDatabase::new(&db_path);
-            app.manage(app_state::AppState::new(db_path));
+            let thumbnails = blinker_core_library::ThumbnailCache::new(app_dir.join("thumbnails"));
+            app.manage(app_state::AppState::new(db_path, thumbnails));
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             commands::library::scan_library,
+            commands::library::rescan_all,
+            commands::library::cancel_scan,
+            commands::library::list_roots,
+            commands::library::add_root,
+            commands::library::update_root,
+            commands::library::remove_root,
             commands::library::query_library,
+            commands::library::watch_library,
+            commands::library::stop_watching,
+            commands::library::search_library_content,
+            commands::library::continue_reading,
+            commands::library::mark_finished,
+            commands::library::list_missing,
+            commands::library::prune_missing,
+            commands::library::item_locations,
+            commands::library::list_duplicates,
             commands::library::update_metadata,
+            commands::library::revert_metadata,
+            commands::library::list_tags,
+            commands::library::create_tag,
+            commands::library::rename_tag,
+            commands::library::set_tag_color,
+            commands::library::delete_tag,
+            commands::library::set_item_tag,
+            commands::library::list_collections,
+            commands::library::create_collection,
+            commands::library::rename_collection,
+            commands::library::set_collection_query,
+            commands::library::delete_collection,
+            commands::library::collection_items,
+            commands::library::add_to_collection,
+            commands::library::remove_from_collection,
+            commands::library::list_series,
+            commands::library::series_items,
+            commands::library::next_in_series,
+            commands::library::list_contributors,
+            commands::library::works_by,
+            commands::library::item_thumbnail,
+            commands::library::export_library,
+            commands::library::import_library,
+            commands::library::import_calibre,
+            commands::library::item_identifiers,
+            commands::library::find_by_identifier,
             commands::reader::open_document,
             commands::reader::render_page,
             commands::reader::search_document,
+            commands::reader::update_reading_progress,
             commands::reader::close_session,
             commands::annotations::add_annotation,
             commands::annotations::list_annotations,
@@ -5,18 +5,197 @@ export interface LibraryItem {
   path: string;
   title: string;
   author?: string;
+  publisher?: string;
+  subject?: string;
+  language?: string;
+  page_count?: number;
+  series?: string;
+  series_index?: number;
+  /** Pages read right to left (manga). */
+  right_to_left: boolean;
+  /** Everyone credited, in the order the file lists them. */
+  contributors: Contributor[];
   file_type: string;
   hash: string;
   tags: string[];
+  missing_since?: number;
+  score?: number;
+}
+
+/** Inclusive bounds; an omitted end is open. */
+export interface Bounds {
+  min?: number;
+  max?: number;
+}
+
+/** Filters accepted by `query_library`. */
+export interface LibraryFilters {
+  text?: string;
+  file_types?: string[];
+  tags?: string[];
+  tag_match?: "all" | "any";
+  languages?: string[];
+  size?: Bounds;
+  page_count?: Bounds;
+  added?: Bounds;
+  last_opened?: Bounds;
+  read_status?: "unread" | "in_progress" | "finished";
+  series?: string;
+  /** A person in any role; variant spellings of their name match too. */
+  author?: string;
+  sort?: "relevance" | "title" | "author" | "added" | "last_opened" | "size" | "page_count" | "series";
+  direction?: "asc" | "desc";
+  offset?: number;
+  /** Omit for every match. */
+  limit?: number;
+}
+
+export type ContributorRole = "author" | "editor" | "translator" | "illustrator";
+
+export interface Contributor {
+  name: string;
+  role: ContributorRole;
+  /** e.g. "Asimov, Isaac". */
+  sort_name?: string;
+}
+
+export interface ContributorSummary {
+  id: string;
+  name: string;
+  sort_name: string;
+  items: number;
+}
+
+export interface SeriesSummary {
+  name: string;
+  items: number;
+}
+
+export interface Collection {
+  id: string;
+  name: string;
+  /** Set for smart collections. */
+  query?: LibraryFilters;
+  created_at: number;
+  modified_at: number;
+}
+
+/** Rewrites archived paths starting with `from` to start with `to`. */
+export interface PathRemap {
+  from: string;
+  to: string;
+}
+
+export interface ExportReport {
+  items: number;
+  annotations: number;
+  collections: number;
+}
+
+export interface ImportReport {
+  matched: number;
+  indexed: number;
+  /** Archived paths whose files were not found; their data was skipped. */
+  unmatched: string[];
+  annotations: number;
+  reading_states: number;
+  tags_created: number;
+  collections_created: number;
+}
+
+export interface CalibreReport {
+  books: number;
+  created: number;
+  existing: number;
+  /** Formats that cannot be opened, with file counts. */
+  unsupported_formats: Record<string, number>;
+  missing_files: string[];
+  skipped_books: string[];
+  /** Calibre fields not imported (custom columns as `#label`), with book counts. */
+  unmapped_fields: Record<string, number>;
+  errors: string[];
+}
+
+export interface Identifier {
+  /** `isbn13`, `doi`, `uuid`, or a scheme from an import such as `amazon`. */
+  scheme: string;
+  value: string;
+  /** `file`, `text`, or the import it came from, e.g. `calibre`. */
+  source: string;
+}
+
+export interface ItemLocation {
+  path: string;
+  file_size: number;
+  missing_since?: number;
+  added_at: number;
+  seen_at: number;
+}
+
+export interface DuplicateItem {
+  item: LibraryItem;
+  locations: ItemLocation[];
+}
+
+export interface Tag {
+  id: string;
+  name: string;
+  color?: string;
+  created_at: number;
+}
+
+export interface ContentMatch {
+  page: number;
+  snippet: string;
+  highlights: [number, number][];
+}
+
+export interface ContentSearchHit {
+  item: LibraryItem;
+  score: number;
+  matches: ContentMatch[];
 }
 
 export interface ScanReport {
   total: number;
   new: number;
   updated: number;
+  missing: number;
   errors: string[];
+  cancelled: boolean;
 }
 
+/** Payload of the `scan-progress` event emitted while scanning. */
+export interface ScanProgress {
+  discovered: number;
+  processed: number;
+  current?: string;
+}
+
+export interface ScanRules {
+  include: string[];
+  exclude: string[];
+  max_depth?: number;
+  follow_symlinks: boolean;
+}
+
+export interface LibraryRoot {
+  id: string;
+  path: string;
+  rules: ScanRules;
+  enabled: boolean;
+  created_at: number;
+  last_scanned_at?: number;
+}
+
+/** Payload of the `library-changed` event emitted while watching roots. */
+export type LibraryChange =
+  | { kind: "added"; id: string; path: string }
+  | { kind: "updated"; id: string; path: string }
+  | { kind: "missing"; path: string; count: number }
+  | { kind: "scanned"; path: string; new: number; updated: number }
+  | { kind: "error"; path: string; message: string };
+
 export interface ReaderSession {
   id: string;
   document_id: string;
@@ -30,6 +209,21 @@ export interface SearchMatch {
   position: [number, number];
 }
 
+export interface ReadingState {
+  item_id: string;
+  current_page: number;
+  total_pages: number;
+  /** 0 to 1. Single-page and flow documents stay at 0 until marked finished. */
+  progress: number;
+  last_opened: number;
+  reading_time: number;
+}
+
+export interface ReadingEntry {
+  item: LibraryItem;
+  state: ReadingState;
+}
+
 export interface Annotation {
   id: string;
   item_id: string;
@@ -1,9 +1,9 @@
-/// Annotation management: highlights, notes, bookmarks.
-///
-/// This crate handles:
-/// - Creating and storing annotations
-/// - Exporting to JSON/Markdown
-/// - Future: write-back to PDF annotations
+//! Annotation management: highlights, notes, bookmarks.
+//!
+//! This crate handles:
+//! - Creating and storing annotations
+//! - Exporting to JSON/Markdown
+//! - Future: write-back to PDF annotations
 
 use blinker_core_common::{BlinkerError, Result};
 use rusqlite::{Connection, params};
@@ -32,11 +32,11 @@ pub enum AnnotationKind {
 }
 
 impl AnnotationKind {
-    fn to_string(&self) -> String {
+    fn as_str(&self) -> &'static str {
         match self {
-            Self::Highlight => "highlight".to_string(),
-            Self::Note => "note".to_string(),
-            Self::Bookmark => "bookmark".to_string(),
+            Self::Highlight => "highlight",
+            Self::Note => "note",
+            Self::Bookmark => "bookmark",
         }
     }
 
@@ -95,7 +95,7 @@ impl AnnotationManager {
                 annotation.range.1,
                 annotation.range.2,
                 annotation.range.3,
-                annotation.kind.to_string(),
+                annotation.kind.as_str(),
                 annotation.text,
                 annotation.color,
                 annotation.created_at,
@@ -168,7 +168,7 @@ impl AnnotationManager {
 
         for annotation in annotations {
             markdown.push_str(&format!("## Page {}\n", annotation.page));
-            markdown.push_str(&format!("**Type:** {}\n", annotation.kind.to_string()));
+            markdown.push_str(&format!("**Type:** {}\n", annotation.kind.as_str()));
             markdown.push_str(&format!("**Text:** {}\n", annotation.text));
             markdown.push_str(&format!("**Color:** {}\n", annotation.color));
             markdown.push_str("\n---\n\n");
@@ -1,4 +1,4 @@
-/// Common types, utilities, and error definitions shared across all Blinker crates.
+//! Common types, utilities, and error definitions shared across all Blinker crates.
 
 pub mod error;
 pub mod types;
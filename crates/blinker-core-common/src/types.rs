@@ -47,6 +47,103 @@ pub struct Metadata {
     pub created_at: Option<i64>,
     pub modified_at: Option<i64>,
     pub page_count: Option<usize>,
+    pub series: Option<String>,
+    /// Position within `series`; fractional for in-between volumes like 1.5.
+    pub series_index: Option<f64>,
+    /// Pages are read right to left, as in most manga.
+    #[serde(default)]
+    pub right_to_left: bool,
+    /// Everyone credited, in the order given. `author` is the display form
+    /// of the ones with the `Author` role.
+    #[serde(default)]
+    pub contributors: Vec<Contributor>,
+}
+
+impl Metadata {
+    /// Set the author display string and replace the `Author` contributors
+    /// with the names it lists, keeping other roles.
+    pub fn set_author(&mut self, author: Option<String>) {
+        let mut contributors = author
+            .as_deref()
+            .map(|a| Contributor::split(a, ContributorRole::Author))
+            .unwrap_or_default();
+        contributors.extend(self.contributors.drain(..).filter(|c| c.role != ContributorRole::Author));
+        self.contributors = contributors;
+        self.author = author;
+    }
+
+    /// Names of the `Author` contributors joined with ` & `, or `None`.
+    pub fn author_display(contributors: &[Contributor]) -> Option<String> {
+        let authors: Vec<&str> = contributors
+            .iter()
+            .filter(|c| c.role == ContributorRole::Author)
+            .map(|c| c.name.as_str())
+            .collect();
+        (!authors.is_empty()).then(|| authors.join(" & "))
+    }
+}
+
+/// What a person did for a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContributorRole {
+    Author,
+    Editor,
+    Translator,
+    Illustrator,
+}
+
+impl ContributorRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Author => "author",
+            Self::Editor => "editor",
+            Self::Translator => "translator",
+            Self::Illustrator => "illustrator",
+        }
+    }
+
+    /// Role from its name, a MARC relator code (`aut`, `edt`, `trl`, `ill`)
+    /// or a comic credit (`Writer`, `Penciller`, ...). `None` for roles not
+    /// modelled, such as `Letterer`.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "author" | "aut" | "writer" | "wri" | "creator" | "cre" => Some(Self::Author),
+            "editor" | "edt" => Some(Self::Editor),
+            "translator" | "trl" => Some(Self::Translator),
+            "illustrator" | "ill" | "art" | "artist" | "penciller" | "inker" | "colorist" | "coverartist" => {
+                Some(Self::Illustrator)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// A person credited on a document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Contributor {
+    pub name: String,
+    pub role: ContributorRole,
+    /// Name as sorted, e.g. `Asimov, Isaac`. `None` when the source does not
+    /// give one; the library then derives it from `name`.
+    pub sort_name: Option<String>,
+}
+
+impl Contributor {
+    pub fn new(name: &str, role: ContributorRole) -> Self {
+        Self { name: name.trim().to_string(), role, sort_name: None }
+    }
+
+    /// Contributors listed in one string, separated by ` & ` or `;`.
+    pub fn split(names: &str, role: ContributorRole) -> Vec<Self> {
+        names
+            .split(" & ")
+            .flat_map(|n| n.split(';'))
+            .map(str::trim)
+            .filter(|n| !n.is_empty())
+            .map(|n| Self::new(n, role))
+            .collect()
+    }
 }
 
 impl Default for Metadata {
@@ -60,6 +157,10 @@ impl Default for Metadata {
             created_at: None,
             modified_at: None,
             page_count: None,
+            series: None,
+            series_index: None,
+            right_to_left: false,
+            contributors: vec![],
         }
     }
 }
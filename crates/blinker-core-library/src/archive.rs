@@ -0,0 +1,608 @@
+//! Whole-library export and import.
+//!
+//! An archive is a zip holding one JSON document, [`ARCHIVE_ENTRY`], with
+//! every item's metadata, user overrides, tags, identifiers, contributors,
+//! reading state and annotations, plus tags and collections. Items are keyed by `file_hash`,
+//! so importing on another machine reattaches user data to the same files
+//! wherever they now live; [`PathRemap`] rewrites the recorded paths for
+//! items the target library has not indexed yet.
+
+use blinker_core_common::{types::{Contributor, Metadata}, BlinkerError, Result};
+use rusqlite::{params, OptionalExtension};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::collections::hash_map::{Entry, HashMap};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::{
+    CollectionStore, Identifier, IdentifierStore, LibraryDatabase, LibraryQuery, LibraryStore, LocationStore,
+    MetadataField,
+};
+
+/// Name of the JSON document inside an archive.
+pub const ARCHIVE_ENTRY: &str = "blinker-library.json";
+/// Version written by this build; newer archives are refused.
+/// 2 added identifiers and contributors, 3 the time of each override.
+pub const ARCHIVE_VERSION: u32 = 3;
+
+/// Upper bound on the uncompressed JSON document, to refuse zip bombs early.
+/// It is parsed as it is read, so only the decoded archive is held in memory.
+const MAX_ARCHIVE_BYTES: u64 = 256 * 1024 * 1024;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LibraryArchive {
+    version: u32,
+    exported_at: i64,
+    tags: Vec<ArchivedTag>,
+    items: Vec<ArchivedItem>,
+    collections: Vec<ArchivedCollection>,
+}
+
+/// Just the version, read before the rest so newer archives are refused
+/// with a clear error rather than whatever their new fields cause.
+#[derive(Debug, Deserialize)]
+struct ArchiveHeader {
+    version: Option<u64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ArchivedTag {
+    name: String,
+    color: Option<String>,
+}
+
+/// Override values by field name.
+type Overrides = HashMap<String, Option<String>>;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ArchivedItem {
+    file_hash: String,
+    file_path: String,
+    /// Other paths the file was found at.
+    #[serde(default)]
+    locations: Vec<String>,
+    file_type: String,
+    file_size: u64,
+    metadata: Metadata,
+    /// User edits by field name; a `null` value blanks the field.
+    #[serde(default)]
+    overrides: Overrides,
+    /// When each override was made. Older archives lack it; their
+    /// overrides count as made at `exported_at`.
+    #[serde(default)]
+    overridden_at: HashMap<String, i64>,
+    #[serde(default)]
+    tags: Vec<String>,
+    /// Every identifier with its source, so imported ones (e.g. from
+    /// Calibre) survive a move to another library.
+    #[serde(default)]
+    identifiers: Vec<Identifier>,
+    /// Everyone credited, in order, with roles and the sort names in use.
+    #[serde(default)]
+    contributors: Vec<Contributor>,
+    reading_state: Option<ArchivedReadingState>,
+    #[serde(default)]
+    annotations: Vec<ArchivedAnnotation>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ArchivedReadingState {
+    current_page: usize,
+    total_pages: usize,
+    progress: f64,
+    last_opened: i64,
+    reading_time: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ArchivedAnnotation {
+    id: String,
+    page: usize,
+    range: (f64, f64, f64, f64),
+    kind: String,
+    text: String,
+    color: String,
+    created_at: i64,
+    modified_at: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ArchivedCollection {
+    name: String,
+    /// Query of a smart collection; `None` for manual ones.
+    query: Option<LibraryQuery>,
+    /// File hashes of a manual collection's members, in order.
+    #[serde(default)]
+    items: Vec<String>,
+}
+
+/// Decode the archive's JSON document as it is decompressed.
+fn read_entry<T: DeserializeOwned>(zip: &mut zip::ZipArchive<std::fs::File>, path: &Path) -> Result<T> {
+    let entry = zip
+        .by_name(ARCHIVE_ENTRY)
+        .map_err(|e| BlinkerError::Parsing(format!("{:?} is not a library archive: {}", path, e)))?;
+    serde_json::from_reader(std::io::BufReader::new(entry.take(MAX_ARCHIVE_BYTES)))
+        .map_err(|e| BlinkerError::Parsing(format!("read archive: {}", e)))
+}
+
+/// Rewrites paths recorded under `from` to live under `to`. Both `/` and `\`
+/// separate components in `from`, so archives made on Windows remap onto
+/// Unix paths and back.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PathRemap {
+    pub from: String,
+    pub to: PathBuf,
+}
+
+impl PathRemap {
+    pub fn new(from: impl Into<String>, to: impl Into<PathBuf>) -> Self {
+        Self { from: from.into(), to: to.into() }
+    }
+
+    /// `path` moved under `to`, or `None` when it is not under `from`.
+    pub fn apply(&self, path: &str) -> Option<PathBuf> {
+        let components = |p: &str| -> Vec<String> {
+            p.split(['/', '\\']).filter(|c| !c.is_empty()).map(str::to_string).collect()
+        };
+        let from = components(&self.from);
+        let path = components(path);
+        if from.is_empty() || !path.starts_with(&from) {
+            return None;
+        }
+        Some(path[from.len()..].iter().fold(self.to.clone(), |acc, c| acc.join(c)))
+    }
+}
+
+/// Result of [`ArchiveStore::export_archive`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ExportReport {
+    pub items: usize,
+    pub annotations: usize,
+    pub collections: usize,
+}
+
+/// Result of [`ArchiveStore::import_archive`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ImportReport {
+    /// Archived items already in the library.
+    pub matched: usize,
+    /// Archived items indexed from their remapped paths.
+    pub indexed: usize,
+    /// Recorded paths of archived items neither in the library nor found on
+    /// disk with the same content; their user data was not imported.
+    pub unmatched: Vec<String>,
+    pub annotations: usize,
+    pub reading_states: usize,
+    pub tags_created: usize,
+    pub collections_created: usize,
+}
+
+/// Export and import of a library's user data.
+pub trait ArchiveStore {
+    /// Write every item, tag and collection to a zip archive at `path`.
+    fn export_archive(&self, path: &Path) -> Result<ExportReport>;
+    /// Merge an archive into this library. Items are matched by file hash;
+    /// ones not indexed yet are looked for at their recorded paths after
+    /// applying the first matching remap. Importing twice changes nothing:
+    /// annotations are keyed by id, newer reading state and overrides win,
+    /// and existing tags and collections are reused by name.
+    fn import_archive(&self, path: &Path, remaps: &[PathRemap]) -> Result<ImportReport>;
+}
+
+impl LibraryDatabase {
+    /// Overrides of an item by field, and when each was made.
+    fn archived_overrides(&self, item_id: &str) -> Result<(Overrides, HashMap<String, i64>)> {
+        let mut stmt = self.conn
+            .prepare("SELECT field, value, updated_at FROM metadata_override WHERE item_id = ?1")
+            .map_err(|e| BlinkerError::Database(format!("prepare overrides: {}", e)))?;
+        let rows = stmt
+            .query_map(params![item_id], |row| Ok((row.get::<_, String>(0)?, row.get(1)?, row.get(2)?)))
+            .map_err(|e| BlinkerError::Database(format!("read overrides: {}", e)))?
+            .collect::<rusqlite::Result<Vec<(String, Option<String>, i64)>>>()
+            .map_err(|e| BlinkerError::Database(format!("row: {}", e)))?;
+        let times = rows.iter().map(|(field, _, at)| (field.clone(), *at)).collect();
+        Ok((rows.into_iter().map(|(field, value, _)| (field, value)).collect(), times))
+    }
+
+    fn archived_reading_state(&self, item_id: &str) -> Result<Option<ArchivedReadingState>> {
+        self.conn
+            .query_row(
+                "SELECT current_page, total_pages, progress, last_opened, reading_time
+                 FROM reading_state WHERE item_id = ?1",
+                params![item_id],
+                |row| {
+                    let current_page: i64 = row.get(0)?;
+                    let total_pages: i64 = row.get(1)?;
+                    let reading_time: i64 = row.get(4)?;
+                    Ok(ArchivedReadingState {
+                        current_page: current_page as usize,
+                        total_pages: total_pages as usize,
+                        progress: row.get(2)?,
+                        last_opened: row.get(3)?,
+                        reading_time: reading_time as u64,
+                    })
+                },
+            )
+            .optional()
+            .map_err(|e| BlinkerError::Database(format!("get reading state: {}", e)))
+    }
+
+    fn archived_annotations(&self, item_id: &str) -> Result<Vec<ArchivedAnnotation>> {
+        let mut stmt = self.conn
+            .prepare(
+                "SELECT id, page, range_x, range_y, range_width, range_height, kind, text, color, created_at, modified_at
+                 FROM annotation WHERE item_id = ?1 ORDER BY page, created_at",
+            )
+            .map_err(|e| BlinkerError::Database(format!("prepare annotations: {}", e)))?;
+        let annotations = stmt
+            .query_map(params![item_id], |row| {
+                let page: i64 = row.get(1)?;
+                Ok(ArchivedAnnotation {
+                    id: row.get(0)?,
+                    page: page as usize,
+                    range: (row.get(2)?, row.get(3)?, row.get(4)?, row.get(5)?),
+                    kind: row.get(6)?,
+                    text: row.get(7)?,
+                    color: row.get(8)?,
+                    created_at: row.get(9)?,
+                    modified_at: row.get(10)?,
+                })
+            })
+            .map_err(|e| BlinkerError::Database(format!("read annotations: {}", e)))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| BlinkerError::Database(format!("row: {}", e)))?;
+        Ok(annotations)
+    }
+
+    fn collection_hashes(&self, collection_id: &str) -> Result<Vec<String>> {
+        let mut stmt = self.conn
+            .prepare(
+                "SELECT li.file_hash FROM collection_item ci JOIN library_item li ON li.id = ci.item_id
+                 WHERE ci.collection_id = ?1 ORDER BY ci.position",
+            )
+            .map_err(|e| BlinkerError::Database(format!("prepare collection items: {}", e)))?;
+        let hashes = stmt
+            .query_map(params![collection_id], |row| row.get(0))
+            .map_err(|e| BlinkerError::Database(format!("collection items: {}", e)))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| BlinkerError::Database(format!("row: {}", e)))?;
+        Ok(hashes)
+    }
+
+    fn item_id_for_hash(&self, file_hash: &str) -> Result<Option<String>> {
+        self.conn
+            .query_row(
+                "SELECT id FROM library_item WHERE file_hash = ?1 ORDER BY missing_since IS NOT NULL LIMIT 1",
+                params![file_hash],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| BlinkerError::Database(format!("find item by hash: {}", e)))
+    }
+
+    /// Index the first existing remapped location of an archived item.
+    /// Returns the item id when its content still matches the archive.
+    fn index_archived(&self, item: &ArchivedItem, remaps: &[PathRemap]) -> Result<Option<String>> {
+        let candidates = std::iter::once(&item.file_path)
+            .chain(&item.locations)
+            .map(|p| remaps.iter().find_map(|r| r.apply(p)).unwrap_or_else(|| PathBuf::from(p)));
+        for path in candidates {
+            if !path.is_file() {
+                continue;
+            }
+            let outcome = self.add_or_update_path(&path)?;
+            let indexed = self.get_item(outcome.id())?;
+            if indexed.is_some_and(|i| i.file_hash == item.file_hash) {
+                return Ok(Some(outcome.id().to_string()));
+            }
+            tracing::warn!("{:?} no longer matches the archived file; its user data is not imported", path);
+        }
+        Ok(None)
+    }
+
+    fn import_user_data(&self, item_id: &str, item: &ArchivedItem, exported_at: i64, tag_ids: &HashMap<String, String>, report: &mut ImportReport) -> Result<()> {
+        // The newer edit of a field wins, whichever side it was made on
+        for (name, value) in &item.overrides {
+            let updated_at = item.overridden_at.get(name).copied().unwrap_or(exported_at);
+            match MetadataField::from_column(name) {
+                Some(field) => {
+                    self.write_override_if_newer(item_id, field, value.as_deref(), updated_at)?;
+                }
+                None => tracing::warn!("Ignoring archived override for unknown field {:?}", name),
+            }
+        }
+
+        for name in &item.tags {
+            if let Some(tag_id) = tag_ids.get(&name.trim().to_ascii_lowercase()) {
+                self.assign_tag(item_id, tag_id)?;
+            }
+        }
+
+        for identifier in &item.identifiers {
+            self.add_identifier(item_id, &identifier.scheme, &identifier.value, &identifier.source)?;
+        }
+
+        // Archived credits carry sort names the author override alone does
+        // not; they apply while they still name the item's authors
+        if !item.contributors.is_empty() {
+            let author = self.get_item(item_id)?.and_then(|i| i.metadata.author);
+            if Metadata::author_display(&item.contributors) == author {
+                self.replace_contributors(item_id, &item.contributors, None)?;
+            }
+        }
+
+        if let Some(state) = &item.reading_state {
+            // Keep whichever side was read more recently; reading time only grows
+            let n = self.conn
+                .execute(
+                    "INSERT INTO reading_state (id, item_id, current_page, total_pages, progress, last_opened, reading_time)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                     ON CONFLICT(item_id) DO UPDATE SET
+                        current_page = CASE WHEN excluded.last_opened > last_opened THEN excluded.current_page ELSE current_page END,
+                        total_pages = CASE WHEN excluded.last_opened > last_opened THEN excluded.total_pages ELSE total_pages END,
+                        progress = CASE WHEN excluded.last_opened > last_opened THEN excluded.progress ELSE progress END,
+                        last_opened = MAX(last_opened, excluded.last_opened),
+                        reading_time = MAX(reading_time, excluded.reading_time)
+                     WHERE excluded.last_opened > last_opened OR excluded.reading_time > reading_time",
+                    params![
+                        uuid::Uuid::new_v4().to_string(),
+                        item_id,
+                        state.current_page as i64,
+                        state.total_pages as i64,
+                        state.progress,
+                        state.last_opened,
+                        state.reading_time as i64,
+                    ],
+                )
+                .map_err(|e| BlinkerError::Database(format!("import reading state: {}", e)))?;
+            report.reading_states += n;
+        }
+
+        for a in &item.annotations {
+            report.annotations += self.conn
+                .execute(
+                    "INSERT INTO annotation (
+                        id, item_id, page, range_x, range_y, range_width, range_height,
+                        kind, text, color, created_at, modified_at
+                     ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)
+                     ON CONFLICT(id) DO NOTHING",
+                    params![
+                        a.id, item_id, a.page as i64, a.range.0, a.range.1, a.range.2, a.range.3,
+                        a.kind, a.text, a.color, a.created_at, a.modified_at,
+                    ],
+                )
+                .map_err(|e| BlinkerError::Database(format!("import annotation: {}", e)))?;
+        }
+        Ok(())
+    }
+
+    fn import_collection(&self, archived: &ArchivedCollection, item_ids: &HashMap<&str, String>, report: &mut ImportReport) -> Result<()> {
+        let existing = self.list_collections()?
+            .into_iter()
+            .find(|c| c.name.eq_ignore_ascii_case(archived.name.trim()));
+        let collection = match (existing, &archived.query) {
+            (Some(c), _) => c,
+            (None, Some(query)) => {
+                report.collections_created += 1;
+                self.create_smart_collection(&archived.name, query)?
+            }
+            (None, None) => {
+                report.collections_created += 1;
+                self.create_collection(&archived.name)?
+            }
+        };
+        if collection.is_smart() {
+            return Ok(());
+        }
+        // Members missing here go after the ones already present, in archive order
+        for hash in &archived.items {
+            let Some(item_id) = item_ids.get(hash.as_str()) else {
+                continue;
+            };
+            self.conn
+                .execute(
+                    "INSERT INTO collection_item (collection_id, item_id, position, added_at)
+                     SELECT ?1, ?2, COUNT(*), ?3 FROM collection_item WHERE collection_id = ?1
+                     ON CONFLICT(collection_id, item_id) DO NOTHING",
+                    params![collection.id, item_id, Self::now_secs()],
+                )
+                .map_err(|e| BlinkerError::Database(format!("import collection item: {}", e)))?;
+        }
+        Ok(())
+    }
+}
+
+impl ArchiveStore for LibraryDatabase {
+    fn export_archive(&self, path: &Path) -> Result<ExportReport> {
+        let mut report = ExportReport::default();
+
+        let tags = self.list_tags()?
+            .into_iter()
+            .map(|t| ArchivedTag { name: t.name, color: t.color })
+            .collect();
+
+        let mut items = vec![];
+        for item in self.query(&LibraryQuery::default())? {
+            let locations = self.item_locations(&item.id)?
+                .into_iter()
+                .filter(|l| l.path != item.file_path)
+                .map(|l| l.path.to_string_lossy().to_string())
+                .collect();
+            let annotations = self.archived_annotations(&item.id)?;
+            report.annotations += annotations.len();
+            let identifiers = self.item_identifiers(&item.id)?;
+            let (overrides, overridden_at) = self.archived_overrides(&item.id)?;
+            items.push(ArchivedItem {
+                file_hash: item.file_hash,
+                file_path: item.file_path.to_string_lossy().to_string(),
+                locations,
+                file_type: item.file_type,
+                file_size: item.file_size,
+                overrides,
+                overridden_at,
+                reading_state: self.archived_reading_state(&item.id)?,
+                contributors: item.metadata.contributors.clone(),
+                metadata: item.metadata,
+                tags: item.tags,
+                identifiers,
+                annotations,
+            });
+        }
+        report.items = items.len();
+
+        let mut collections = vec![];
+        for c in self.list_collections()? {
+            let items = if c.is_smart() { vec![] } else { self.collection_hashes(&c.id)? };
+            collections.push(ArchivedCollection { name: c.name, query: c.query, items });
+        }
+        report.collections = collections.len();
+
+        let archive = LibraryArchive { version: ARCHIVE_VERSION, exported_at: Self::now_secs(), tags, items, collections };
+        let json = serde_json::to_vec_pretty(&archive)
+            .map_err(|e| BlinkerError::Parsing(format!("encode archive: {}", e)))?;
+
+        let file = std::fs::File::create(path)?;
+        let mut zip = zip::ZipWriter::new(file);
+        zip.start_file(ARCHIVE_ENTRY, zip::write::SimpleFileOptions::default())
+            .map_err(|e| BlinkerError::Parsing(format!("write archive: {}", e)))?;
+        zip.write_all(&json)?;
+        zip.finish()
+            .map_err(|e| BlinkerError::Parsing(format!("write archive: {}", e)))?;
+        Ok(report)
+    }
+
+    fn import_archive(&self, path: &Path, remaps: &[PathRemap]) -> Result<ImportReport> {
+        let file = std::fs::File::open(path)?;
+        let mut zip = zip::ZipArchive::new(file)
+            .map_err(|e| BlinkerError::Parsing(format!("open archive {:?}: {}", path, e)))?;
+        let header: ArchiveHeader = read_entry(&mut zip, path)?;
+        match header.version {
+            Some(v) if v <= ARCHIVE_VERSION as u64 => {}
+            Some(v) => return Err(BlinkerError::Parsing(format!("archive version {} is newer than this build supports ({})", v, ARCHIVE_VERSION))),
+            None => return Err(BlinkerError::Parsing("archive has no version".into())),
+        }
+        let archive: LibraryArchive = read_entry(&mut zip, path)?;
+
+        let mut report = ImportReport::default();
+
+        // Indexing commits on its own, so locate every item before the user data transaction
+        let mut item_ids: HashMap<&str, String> = HashMap::new();
+        for item in &archive.items {
+            if let Some(id) = self.item_id_for_hash(&item.file_hash)? {
+                report.matched += 1;
+                item_ids.insert(&item.file_hash, id);
+            } else if let Some(id) = self.index_archived(item, remaps)? {
+                report.indexed += 1;
+                item_ids.insert(&item.file_hash, id);
+            } else {
+                report.unmatched.push(item.file_path.clone());
+            }
+        }
+
+        let tx = self
+            .write_transaction()
+            .map_err(|e| BlinkerError::Database(format!("begin import: {}", e)))?;
+
+        let mut tag_ids: HashMap<String, String> = self.list_tags()?
+            .into_iter()
+            .map(|t| (t.name.to_ascii_lowercase(), t.id))
+            .collect();
+        let tag_names = archive.tags.iter()
+            .map(|t| (t.name.as_str(), t.color.as_deref()))
+            .chain(archive.items.iter().flat_map(|i| i.tags.iter().map(|t| (t.as_str(), None))));
+        for (name, color) in tag_names {
+            if let Entry::Vacant(slot) = tag_ids.entry(name.trim().to_ascii_lowercase()) {
+                slot.insert(self.create_tag(name, color)?.id);
+                report.tags_created += 1;
+            }
+        }
+
+        for item in &archive.items {
+            if let Some(item_id) = item_ids.get(item.file_hash.as_str()) {
+                self.import_user_data(item_id, item, archive.exported_at, &tag_ids, &mut report)?;
+            }
+        }
+        for collection in &archive.collections {
+            self.import_collection(collection, &item_ids, &mut report)?;
+        }
+
+        tx.commit()
+            .map_err(|e| BlinkerError::Database(format!("commit import: {}", e)))?;
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{add_file, library_with};
+    use blinker_core_common::types::ContributorRole;
+
+    #[test]
+    fn remap_moves_paths_under_the_prefix() {
+        let remap = PathRemap::new("/old/Books", "/home/me/Books");
+        assert_eq!(remap.apply("/old/Books/sf/dune.epub"), Some(PathBuf::from("/home/me/Books/sf/dune.epub")));
+        assert_eq!(remap.apply("/old/Books/"), Some(PathBuf::from("/home/me/Books")));
+        assert_eq!(remap.apply("/old/Bookshelf/dune.epub"), None);
+        assert_eq!(remap.apply("/elsewhere/dune.epub"), None);
+    }
+
+    #[test]
+    fn remap_accepts_either_separator() {
+        let remap = PathRemap::new("C:\\Books\\", "/home/me/Books");
+        assert_eq!(remap.apply("C:\\Books\\sf\\dune.epub"), Some(PathBuf::from("/home/me/Books/sf/dune.epub")));
+        assert_eq!(remap.apply("C:/Books/sf/dune.epub"), Some(PathBuf::from("/home/me/Books/sf/dune.epub")));
+
+        let back = PathRemap::new("/home/me/Books", "D:\\Books");
+        assert_eq!(back.apply("/home/me/Books/dune.epub"), Some(PathBuf::from("D:\\Books").join("dune.epub")));
+    }
+
+    #[test]
+    fn empty_remap_matches_nothing() {
+        assert_eq!(PathRemap::new("/", "/home/me").apply("/old/dune.epub"), None);
+        assert_eq!(PathRemap::new("", "/home/me").apply("/old/dune.epub"), None);
+    }
+
+    #[test]
+    fn identifiers_and_contributors_survive_a_round_trip() {
+        let (dir, source, _) = library_with(&[]);
+        let id = add_file(&source, &dir.path().join("note.md"), "---\nauthor: Isaac Asimov\n---\nText");
+        let archive = dir.path().join("library.zip");
+        source.add_identifier(&id, "isbn", "0-553-29335-4", "calibre").unwrap();
+        let mut asimov = Contributor::new("Isaac Asimov", ContributorRole::Author);
+        asimov.sort_name = Some("Asimov, I.".into());
+        source.replace_contributors(&id, &[asimov.clone()], None).unwrap();
+        source.export_archive(&archive).unwrap();
+
+        let target = LibraryDatabase::new(&dir.path().join("target.db")).unwrap();
+        target.import_archive(&archive, &[]).unwrap();
+        let id = target.item_id_for_hash(&source.get_item(&id).unwrap().unwrap().file_hash).unwrap().unwrap();
+        let identifiers = target.item_identifiers(&id).unwrap();
+        assert_eq!(
+            identifiers,
+            [Identifier { scheme: "isbn13".into(), value: "9780553293357".into(), source: "calibre".into() }]
+        );
+        let item = target.get_item(&id).unwrap().unwrap();
+        assert_eq!(item.metadata.contributors, [asimov]);
+    }
+
+    #[test]
+    fn newer_override_wins_on_import() {
+        let (dir, source, ids) = library_with(&["note"]);
+        let id = &ids[0];
+        let archive = dir.path().join("library.zip");
+        source.write_override(id, MetadataField::Title, Some("Archived title"), 100).unwrap();
+        source.write_override(id, MetadataField::Subject, Some("Archived subject"), 300).unwrap();
+        source.export_archive(&archive).unwrap();
+
+        let target = LibraryDatabase::new(&dir.path().join("target.db")).unwrap();
+        let id = target.add_or_update_path(&dir.path().join("note.md")).unwrap().id().to_string();
+        target.write_override(&id, MetadataField::Title, Some("Local title"), 200).unwrap();
+        target.write_override(&id, MetadataField::Subject, Some("Local subject"), 200).unwrap();
+        target.import_archive(&archive, &[]).unwrap();
+
+        let item = target.get_item(&id).unwrap().unwrap();
+        assert_eq!(item.metadata.title, "Local title");
+        assert_eq!(item.metadata.subject.as_deref(), Some("Archived subject"));
+    }
+}
@@ -0,0 +1,456 @@
+//! Import of a Calibre library: its `metadata.db` and the book folders next
+//! to it.
+//!
+//! Book files are indexed where they are, one item per supported format.
+//! Calibre's metadata is recorded as overrides dated at the book's
+//! `last_modified`, so it wins over what extraction finds in the files just
+//! as if it had been edited here, and loses to edits made here since.
+
+use blinker_core_common::types::{Contributor, ContributorRole, DocumentFormat, Metadata};
+use blinker_core_common::{BlinkerError, Result};
+use rusqlite::{params, Connection, OpenFlags};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use crate::{AddOutcome, LibraryDatabase, LibraryStore, MetadataField};
+
+/// Source recorded for identifiers taken from Calibre.
+const SOURCE: &str = "calibre";
+
+/// Result of [`CalibreImport::import_calibre`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CalibreReport {
+    /// Books listed in `metadata.db`.
+    pub books: usize,
+    /// Items created for book files.
+    pub created: usize,
+    /// Book files that were already in the library.
+    pub existing: usize,
+    /// Formats that cannot be opened here, with how many files have them.
+    pub unsupported_formats: BTreeMap<String, usize>,
+    /// Files listed in `metadata.db` but not found on disk.
+    pub missing_files: Vec<PathBuf>,
+    /// Titles of books without any file that could be imported.
+    pub skipped_books: Vec<String>,
+    /// Calibre fields with no counterpart here (custom columns as `#label`),
+    /// with how many books set them.
+    pub unmapped_fields: BTreeMap<String, usize>,
+    /// Files that could not be indexed.
+    pub errors: Vec<String>,
+}
+
+/// Import from Calibre.
+pub trait CalibreImport {
+    /// Import the Calibre library at `library`, either its folder or its
+    /// `metadata.db`. Files are not copied. Importing again only refreshes
+    /// metadata of books changed in Calibre after their fields were last
+    /// edited here, and adds books new since.
+    fn import_calibre(&self, library: &Path) -> Result<CalibreReport>;
+}
+
+struct CalibreBook {
+    id: i64,
+    title: String,
+    path: String,
+    uuid: Option<String>,
+    isbn: Option<String>,
+    series_index: Option<f64>,
+    /// `last_modified` in seconds since the epoch; 0 when unreadable, so
+    /// such a book never overrides an edit.
+    last_modified: i64,
+}
+
+fn calibre_err(e: rusqlite::Error) -> BlinkerError {
+    BlinkerError::Database(format!("read calibre library: {}", e))
+}
+
+fn has_table(conn: &Connection, name: &str) -> Result<bool> {
+    conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?1)",
+        params![name],
+        |row| row.get(0),
+    )
+    .map_err(calibre_err)
+}
+
+/// Rows of `(book, a, b)` grouped by book, in query order.
+fn grouped(conn: &Connection, sql: &str) -> Result<HashMap<i64, Vec<(String, String)>>> {
+    let mut stmt = conn.prepare(sql).map_err(calibre_err)?;
+    let rows = stmt
+        .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<String>>(2)?)))
+        .map_err(calibre_err)?;
+    let mut out: HashMap<i64, Vec<(String, String)>> = HashMap::new();
+    for row in rows {
+        let (book, a, b) = row.map_err(calibre_err)?;
+        out.entry(book).or_default().push((a, b.unwrap_or_default()));
+    }
+    Ok(out)
+}
+
+/// Names linked to each book through a Calibre link table, e.g. authors.
+fn linked(conn: &Connection, sql: &str) -> Result<HashMap<i64, Vec<String>>> {
+    Ok(grouped(conn, sql)?
+        .into_iter()
+        .map(|(book, rows)| (book, rows.into_iter().map(|(name, _)| name).collect()))
+        .collect())
+}
+
+fn count(conn: &Connection, sql: &str) -> Result<usize> {
+    let n: i64 = conn.query_row(sql, [], |row| row.get(0)).map_err(calibre_err)?;
+    Ok(n as usize)
+}
+
+/// Fields Calibre may hold that have no counterpart here.
+fn unmapped_fields(conn: &Connection) -> Result<BTreeMap<String, usize>> {
+    let mut checks: Vec<(String, String)> = vec![
+        ("comments".into(), "SELECT COUNT(*) FROM comments WHERE trim(text) != ''".into()),
+        ("pubdate".into(), "SELECT COUNT(*) FROM books WHERE pubdate > '0101-01-02'".into()),
+        (
+            "rating".into(),
+            "SELECT COUNT(*) FROM books_ratings_link l JOIN ratings r ON r.id = l.rating WHERE r.rating > 0".into(),
+        ),
+    ];
+    if has_table(conn, "custom_columns")? {
+        let mut stmt = conn.prepare("SELECT id, label, normalized FROM custom_columns").map_err(calibre_err)?;
+        let columns = stmt
+            .query_map([], |row| Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, bool>(2)?)))
+            .map_err(calibre_err)?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(calibre_err)?;
+        for (id, label, normalized) in columns {
+            let table = if normalized { format!("books_custom_column_{}_link", id) } else { format!("custom_column_{}", id) };
+            checks.push((format!("#{}", label), format!("SELECT COUNT(DISTINCT book) FROM {}", table)));
+        }
+    }
+
+    let mut fields = BTreeMap::new();
+    for (field, sql) in checks {
+        match count(conn, &sql) {
+            Ok(0) => {}
+            Ok(n) => {
+                fields.insert(field, n);
+            }
+            // Older libraries lack some tables; nothing to map then
+            Err(e) => tracing::debug!("Skipping unmapped field check {}: {}", field, e),
+        }
+    }
+    Ok(fields)
+}
+
+/// ISO 639-1 code for the ISO 639-2 codes Calibre stores, so language
+/// filters such as `en` match. Codes without a two-letter form are kept.
+fn language_code(code: &str) -> String {
+    let code = code.trim().to_ascii_lowercase();
+    let short = match code.as_str() {
+        "ara" => "ar",
+        "ces" | "cze" => "cs",
+        "dan" => "da",
+        "deu" | "ger" => "de",
+        "ell" | "gre" => "el",
+        "eng" => "en",
+        "spa" => "es",
+        "fin" => "fi",
+        "fra" | "fre" => "fr",
+        "heb" => "he",
+        "hin" => "hi",
+        "hun" => "hu",
+        "ita" => "it",
+        "jpn" => "ja",
+        "kor" => "ko",
+        "nld" | "dut" => "nl",
+        "nor" => "no",
+        "pol" => "pl",
+        "por" => "pt",
+        "rus" => "ru",
+        "swe" => "sv",
+        "tur" => "tr",
+        "ukr" => "uk",
+        "zho" | "chi" => "zh",
+        _ => return code,
+    };
+    short.to_string()
+}
+
+fn read_books(conn: &Connection) -> Result<Vec<CalibreBook>> {
+    let mut stmt = conn
+        .prepare(
+            "SELECT id, title, path, uuid, isbn, series_index, CAST(strftime('%s', last_modified) AS INTEGER)
+             FROM books ORDER BY id",
+        )
+        .map_err(calibre_err)?;
+    let books = stmt
+        .query_map([], |row| {
+            Ok(CalibreBook {
+                id: row.get(0)?,
+                title: row.get(1)?,
+                path: row.get(2)?,
+                uuid: row.get(3)?,
+                isbn: row.get(4)?,
+                series_index: row.get(5)?,
+                last_modified: row.get::<_, Option<i64>>(6)?.unwrap_or(0),
+            })
+        })
+        .map_err(calibre_err)?
+        .collect::<rusqlite::Result<Vec<_>>>()
+        .map_err(calibre_err)?;
+    Ok(books)
+}
+
+impl CalibreImport for LibraryDatabase {
+    fn import_calibre(&self, library: &Path) -> Result<CalibreReport> {
+        let db_file = if library.is_file() { library.to_path_buf() } else { library.join("metadata.db") };
+        let root = db_file.parent().unwrap_or(Path::new(".")).to_path_buf();
+        if !db_file.is_file() {
+            return Err(BlinkerError::NotFound(format!("Calibre library at {:?}", library)));
+        }
+        let conn = Connection::open_with_flags(&db_file, OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX)
+            .map_err(|e| BlinkerError::Database(format!("open {:?}: {}", db_file, e)))?;
+
+        let books = read_books(&conn)?;
+        let authors = grouped(&conn, "SELECT l.book, a.name, a.sort FROM books_authors_link l JOIN authors a ON a.id = l.author ORDER BY l.id")?;
+        let tags = linked(&conn, "SELECT l.book, t.name, NULL FROM books_tags_link l JOIN tags t ON t.id = l.tag ORDER BY t.name")?;
+        let series = linked(&conn, "SELECT l.book, s.name, NULL FROM books_series_link l JOIN series s ON s.id = l.series")?;
+        let publishers = linked(&conn, "SELECT l.book, p.name, NULL FROM books_publishers_link l JOIN publishers p ON p.id = l.publisher")?;
+        let languages = if has_table(&conn, "books_languages_link")? {
+            linked(&conn, "SELECT l.book, g.lang_code, NULL FROM books_languages_link l JOIN languages g ON g.id = l.lang_code ORDER BY l.item_order")?
+        } else {
+            HashMap::new()
+        };
+        let identifiers = if has_table(&conn, "identifiers")? {
+            grouped(&conn, "SELECT book, type, val FROM identifiers")?
+        } else {
+            HashMap::new()
+        };
+        let files = grouped(&conn, "SELECT book, format, name FROM data ORDER BY id")?;
+
+        let mut report = CalibreReport {
+            books: books.len(),
+            unmapped_fields: unmapped_fields(&conn)?,
+            ..Default::default()
+        };
+        let mut tag_ids: HashMap<String, String> = self.list_tags()?
+            .into_iter()
+            .map(|t| (t.name.to_ascii_lowercase(), t.id))
+            .collect();
+
+        for book in &books {
+            // Indexing commits on its own, so files come before the metadata transaction
+            let mut item_ids = vec![];
+            for (format, name) in files.get(&book.id).into_iter().flatten() {
+                if DocumentFormat::from_extension(format).is_none() {
+                    *report.unsupported_formats.entry(format.to_ascii_uppercase()).or_default() += 1;
+                    continue;
+                }
+                let path = root.join(&book.path).join(format!("{}.{}", name, format.to_ascii_lowercase()));
+                if !path.is_file() {
+                    report.missing_files.push(path);
+                    continue;
+                }
+                match self.add_or_update_path(&path) {
+                    Ok(AddOutcome::Created { id }) => {
+                        report.created += 1;
+                        item_ids.push(id);
+                    }
+                    Ok(outcome) => {
+                        report.existing += 1;
+                        item_ids.push(outcome.id().to_string());
+                    }
+                    Err(e) => report.errors.push(format!("{}: {}", path.display(), e)),
+                }
+            }
+            if item_ids.is_empty() {
+                report.skipped_books.push(book.title.clone());
+                continue;
+            }
+
+            // Calibre keeps commas out of author names by storing them as '|'
+            let book_authors: Vec<Contributor> = authors
+                .get(&book.id)
+                .into_iter()
+                .flatten()
+                .filter(|(name, _)| name != "Unknown")
+                .map(|(name, sort)| Contributor {
+                    sort_name: Some(sort.replace('|', ",")).filter(|s| !s.trim().is_empty()),
+                    ..Contributor::new(&name.replace('|', ","), ContributorRole::Author)
+                })
+                .collect();
+            let author = Metadata::author_display(&book_authors);
+            let first = |map: &HashMap<i64, Vec<String>>| map.get(&book.id).and_then(|v| v.first()).cloned();
+            let series_name = first(&series);
+            let mut changes = vec![
+                (MetadataField::Title, Some(book.title.clone())),
+                (MetadataField::Author, author),
+                (MetadataField::Publisher, first(&publishers)),
+                (MetadataField::Language, first(&languages).map(|c| language_code(&c))),
+            ];
+            if series_name.is_some() {
+                changes.push((MetadataField::Series, series_name));
+                changes.push((MetadataField::SeriesIndex, book.series_index.map(|i| i.to_string())));
+            }
+
+            let tx = self
+                .write_transaction()
+                .map_err(|e| BlinkerError::Database(format!("begin calibre import: {}", e)))?;
+            for item_id in &item_ids {
+                let mut author_written = false;
+                for (field, value) in &changes {
+                    if value.is_some() {
+                        let written = self.write_override_if_newer(item_id, *field, value.as_deref(), book.last_modified)?;
+                        author_written |= written && *field == MetadataField::Author;
+                    }
+                }
+                if author_written {
+                    // Same people as the override just recorded, with Calibre's sort names
+                    self.replace_contributors(item_id, &book_authors, Some(ContributorRole::Author))?;
+                }
+                for name in tags.get(&book.id).into_iter().flatten() {
+                    let key = name.trim().to_ascii_lowercase();
+                    let tag_id = match tag_ids.get(&key) {
+                        Some(id) => id.clone(),
+                        None => {
+                            let tag = self.create_tag(name, None)?;
+                            tag_ids.insert(key, tag.id.clone());
+                            tag.id
+                        }
+                    };
+                    self.assign_tag(item_id, &tag_id)?;
+                }
+                for (scheme, value) in identifiers.get(&book.id).into_iter().flatten() {
+                    self.add_identifier(item_id, scheme, value, SOURCE)?;
+                }
+                if let Some(isbn) = &book.isbn {
+                    self.add_identifier(item_id, "isbn", isbn, SOURCE)?;
+                }
+                if let Some(uuid) = &book.uuid {
+                    self.add_identifier(item_id, "uuid", uuid, SOURCE)?;
+                }
+            }
+            tx.commit()
+                .map_err(|e| BlinkerError::Database(format!("commit calibre import: {}", e)))?;
+        }
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::library_with;
+    use crate::{IdentifierStore, LibraryQuery};
+
+    /// A Calibre library with the tables the import reads: one book with a
+    /// text file, and one with only a MOBI.
+    fn calibre_library(root: &Path) {
+        let conn = Connection::open(root.join("metadata.db")).unwrap();
+        conn.execute_batch(
+            "CREATE TABLE books (id INTEGER PRIMARY KEY, title TEXT, path TEXT, uuid TEXT, isbn TEXT,
+                                 series_index REAL, pubdate TIMESTAMP, last_modified TIMESTAMP);
+             CREATE TABLE authors (id INTEGER PRIMARY KEY, name TEXT, sort TEXT);
+             CREATE TABLE books_authors_link (id INTEGER PRIMARY KEY, book INTEGER, author INTEGER);
+             CREATE TABLE tags (id INTEGER PRIMARY KEY, name TEXT);
+             CREATE TABLE books_tags_link (id INTEGER PRIMARY KEY, book INTEGER, tag INTEGER);
+             CREATE TABLE series (id INTEGER PRIMARY KEY, name TEXT);
+             CREATE TABLE books_series_link (id INTEGER PRIMARY KEY, book INTEGER, series INTEGER);
+             CREATE TABLE publishers (id INTEGER PRIMARY KEY, name TEXT);
+             CREATE TABLE books_publishers_link (id INTEGER PRIMARY KEY, book INTEGER, publisher INTEGER);
+             CREATE TABLE languages (id INTEGER PRIMARY KEY, lang_code TEXT);
+             CREATE TABLE books_languages_link (id INTEGER PRIMARY KEY, book INTEGER, lang_code INTEGER, item_order INTEGER);
+             CREATE TABLE identifiers (id INTEGER PRIMARY KEY, book INTEGER, type TEXT, val TEXT);
+             CREATE TABLE data (id INTEGER PRIMARY KEY, book INTEGER, format TEXT, name TEXT);
+             CREATE TABLE comments (id INTEGER PRIMARY KEY, book INTEGER, text TEXT);
+             CREATE TABLE ratings (id INTEGER PRIMARY KEY, rating INTEGER);
+             CREATE TABLE books_ratings_link (id INTEGER PRIMARY KEY, book INTEGER, rating INTEGER);
+             CREATE TABLE custom_columns (id INTEGER PRIMARY KEY, label TEXT, normalized BOOL);
+             CREATE TABLE custom_column_1 (id INTEGER PRIMARY KEY, book INTEGER, value TEXT);
+
+             INSERT INTO books VALUES
+                (1, 'Why We Can''t Wait', 'Martin Luther King, Jr_/Why We Can''t Wait (1)',
+                 'a1b2c3d4-0000-4000-8000-000000000001', '', 2, '0101-01-01 00:00:00+00:00',
+                 '2021-03-04 12:00:00.123456+00:00'),
+                (2, 'Only Kindle', 'Someone/Only Kindle (2)', NULL, NULL, 1, '0101-01-01 00:00:00+00:00',
+                 '2021-03-04 12:00:00+00:00');
+             INSERT INTO authors VALUES (1, 'Martin Luther King| Jr.', 'King| Martin Luther| Jr.');
+             INSERT INTO books_authors_link VALUES (1, 1, 1);
+             INSERT INTO tags VALUES (1, 'History');
+             INSERT INTO books_tags_link VALUES (1, 1, 1);
+             INSERT INTO series VALUES (1, 'Speeches');
+             INSERT INTO books_series_link VALUES (1, 1, 1);
+             INSERT INTO languages VALUES (1, 'eng');
+             INSERT INTO books_languages_link VALUES (1, 1, 1, 0);
+             INSERT INTO identifiers VALUES (1, 1, 'isbn', '0-553-29335-4');
+             INSERT INTO data VALUES (1, 1, 'TXT', 'Why We Can''t Wait - Martin Luther King, Jr_'),
+                                     (2, 2, 'MOBI', 'Only Kindle - Someone');
+             INSERT INTO custom_columns VALUES (1, 'read_date', 0);
+             INSERT INTO custom_column_1 VALUES (1, 1, '2020-01-01');",
+        )
+        .unwrap();
+        let folder = root.join("Martin Luther King, Jr_/Why We Can't Wait (1)");
+        std::fs::create_dir_all(&folder).unwrap();
+        std::fs::write(folder.join("Why We Can't Wait - Martin Luther King, Jr_.txt"), "Text").unwrap();
+    }
+
+    #[test]
+    fn imports_books_with_their_metadata() {
+        let (dir, db, _) = library_with(&[]);
+        let calibre = dir.path().join("Calibre Library");
+        std::fs::create_dir(&calibre).unwrap();
+        calibre_library(&calibre);
+
+        let report = db.import_calibre(&calibre).unwrap();
+        assert_eq!((report.books, report.created, report.existing), (2, 1, 0));
+        assert_eq!(report.unsupported_formats, BTreeMap::from([("MOBI".to_string(), 1)]));
+        assert_eq!(report.skipped_books, ["Only Kindle"]);
+        assert_eq!(report.unmapped_fields, BTreeMap::from([("#read_date".to_string(), 1)]));
+        assert!(report.missing_files.is_empty() && report.errors.is_empty());
+
+        let items = db.query(&LibraryQuery::default()).unwrap();
+        assert_eq!(items.len(), 1);
+        let item = &items[0];
+        assert_eq!(item.metadata.title, "Why We Can't Wait");
+        assert_eq!(item.metadata.author.as_deref(), Some("Martin Luther King, Jr."));
+        assert_eq!(item.metadata.contributors[0].sort_name.as_deref(), Some("King, Martin Luther, Jr."));
+        assert_eq!(item.metadata.language.as_deref(), Some("en"));
+        assert_eq!(item.metadata.series.as_deref(), Some("Speeches"));
+        assert_eq!(item.metadata.series_index, Some(2.0));
+        assert_eq!(item.tags, ["History"]);
+        let identifiers: Vec<(String, String)> = db.item_identifiers(&item.id).unwrap()
+            .into_iter()
+            .map(|i| (i.scheme, i.value))
+            .collect();
+        assert_eq!(
+            identifiers,
+            [
+                ("isbn13".to_string(), "9780553293357".to_string()),
+                ("uuid".to_string(), "a1b2c3d4-0000-4000-8000-000000000001".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn reimport_keeps_newer_edits() {
+        let (dir, db, _) = library_with(&[]);
+        let calibre = dir.path().join("Calibre Library");
+        std::fs::create_dir(&calibre).unwrap();
+        calibre_library(&calibre);
+        db.import_calibre(&calibre).unwrap();
+        let id = db.query(&LibraryQuery::default()).unwrap().remove(0).id;
+        db.update_metadata(&id, &[(MetadataField::Title, Some("Edited here".into()))]).unwrap();
+
+        let report = db.import_calibre(&calibre).unwrap();
+        assert_eq!((report.created, report.existing), (0, 1));
+        assert_eq!(db.get_item(&id).unwrap().unwrap().metadata.title, "Edited here");
+
+        // A later change in Calibre wins again
+        let conn = Connection::open(calibre.join("metadata.db")).unwrap();
+        conn.execute(
+            "UPDATE books SET title = 'Edited in Calibre', last_modified = datetime(?1, 'unixepoch') WHERE id = 1",
+            params![LibraryDatabase::now_secs() + 60],
+        )
+        .unwrap();
+        db.import_calibre(&calibre).unwrap();
+        let item = db.get_item(&id).unwrap().unwrap();
+        assert_eq!(item.metadata.title, "Edited in Calibre");
+        assert_eq!(item.metadata.author.as_deref(), Some("Martin Luther King, Jr."));
+    }
+}
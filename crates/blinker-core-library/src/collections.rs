@@ -0,0 +1,359 @@
+//! User collections: manual ones hold an ordered list of items, smart ones a
+//! stored [`LibraryQuery`] evaluated each time they are read.
+
+use blinker_core_common::{BlinkerError, Result};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::{LibraryDatabase, LibraryItem, LibraryQuery, LibraryStore};
+
+/// A named group of items.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Collection {
+    pub id: String,
+    pub name: String,
+    /// Query defining a smart collection; `None` for manual ones.
+    pub query: Option<LibraryQuery>,
+    pub created_at: i64,
+    pub modified_at: i64,
+}
+
+impl Collection {
+    pub fn is_smart(&self) -> bool {
+        self.query.is_some()
+    }
+}
+
+/// Persistence of collections and their members.
+pub trait CollectionStore {
+    /// Collections ordered by name.
+    fn list_collections(&self) -> Result<Vec<Collection>>;
+    fn get_collection(&self, id: &str) -> Result<Option<Collection>>;
+    /// Create an empty manual collection.
+    fn create_collection(&self, name: &str) -> Result<Collection>;
+    fn create_smart_collection(&self, name: &str, query: &LibraryQuery) -> Result<Collection>;
+    fn rename_collection(&self, id: &str, name: &str) -> Result<()>;
+    /// Replace the query of a smart collection.
+    fn set_collection_query(&self, id: &str, query: &LibraryQuery) -> Result<()>;
+    /// Delete a collection. Its items stay in the library.
+    fn delete_collection(&self, id: &str) -> Result<()>;
+
+    /// Items of a collection: manual ones in their stored order, smart ones
+    /// as returned by their query.
+    fn collection_items(&self, id: &str) -> Result<Vec<LibraryItem>>;
+    /// Insert an item into a manual collection at `position` (0-based), or at
+    /// the end when `None`. An item already present is moved there.
+    fn add_to_collection(&self, id: &str, item_id: &str, position: Option<usize>) -> Result<()>;
+    fn remove_from_collection(&self, id: &str, item_id: &str) -> Result<()>;
+    /// Manual collections containing an item, ordered by name.
+    fn item_collections(&self, item_id: &str) -> Result<Vec<Collection>>;
+}
+
+const COLLECTION_COLUMNS: &str = "id, name, query, created_at, modified_at";
+
+impl LibraryDatabase {
+    fn collection_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Collection> {
+        let query: Option<String> = row.get(2)?;
+        let query = query
+            .map(|json| serde_json::from_str(&json))
+            .transpose()
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(2, rusqlite::types::Type::Text, Box::new(e)))?;
+        Ok(Collection {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            query,
+            created_at: row.get(3)?,
+            modified_at: row.get(4)?,
+        })
+    }
+
+    fn query_json(query: &LibraryQuery) -> Result<String> {
+        serde_json::to_string(query).map_err(|e| BlinkerError::Parsing(format!("encode query: {}", e)))
+    }
+
+    /// Collection names are unique ignoring ASCII case, like tag names.
+    fn checked_collection_name(&self, name: &str, except_id: Option<&str>) -> Result<String> {
+        let name = name.trim();
+        if name.is_empty() {
+            return Err(BlinkerError::Parsing("collection name is empty".into()));
+        }
+        let taken: bool = self.conn
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM collection WHERE lower(name) = lower(?1) AND id IS NOT ?2)",
+                params![name, except_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| BlinkerError::Database(format!("check collection name: {}", e)))?;
+        if taken {
+            return Err(BlinkerError::Database(format!("collection already exists: {}", name)));
+        }
+        Ok(name.to_string())
+    }
+
+    fn insert_collection(&self, name: &str, query: Option<&LibraryQuery>) -> Result<Collection> {
+        let name = self.checked_collection_name(name, None)?;
+        let id = uuid::Uuid::new_v4().to_string();
+        self.conn
+            .execute(
+                "INSERT INTO collection (id, name, query, created_at, modified_at) VALUES (?1, ?2, ?3, ?4, ?4)",
+                params![id, name, query.map(Self::query_json).transpose()?, Self::now_secs()],
+            )
+            .map_err(|e| BlinkerError::Database(format!("create collection: {}", e)))?;
+        self.get_collection(&id)?
+            .ok_or_else(|| BlinkerError::NotFound(format!("collection {}", id)))
+    }
+
+    fn manual_collection(&self, id: &str) -> Result<Collection> {
+        let collection = self.get_collection(id)?
+            .ok_or_else(|| BlinkerError::NotFound(format!("collection {}", id)))?;
+        if collection.is_smart() {
+            return Err(BlinkerError::Parsing(format!("{} is a smart collection; its items come from its query", collection.name)));
+        }
+        Ok(collection)
+    }
+
+    /// Delete a member and close the gap it leaves. Returns whether it was present.
+    pub(crate) fn remove_member(&self, id: &str, item_id: &str) -> Result<bool> {
+        let position: Option<i64> = self.conn
+            .query_row(
+                "DELETE FROM collection_item WHERE collection_id = ?1 AND item_id = ?2 RETURNING position",
+                params![id, item_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| BlinkerError::Database(format!("remove from collection: {}", e)))?;
+        let Some(position) = position else {
+            return Ok(false);
+        };
+        self.conn
+            .execute(
+                "UPDATE collection_item SET position = position - 1 WHERE collection_id = ?1 AND position > ?2",
+                params![id, position],
+            )
+            .map_err(|e| BlinkerError::Database(format!("shift collection items: {}", e)))?;
+        Ok(true)
+    }
+
+    fn touch_collection(&self, id: &str) -> Result<()> {
+        self.conn
+            .execute("UPDATE collection SET modified_at = ?2 WHERE id = ?1", params![id, Self::now_secs()])
+            .map_err(|e| BlinkerError::Database(format!("touch collection: {}", e)))?;
+        Ok(())
+    }
+}
+
+impl CollectionStore for LibraryDatabase {
+    fn list_collections(&self) -> Result<Vec<Collection>> {
+        let sql = format!("SELECT {} FROM collection ORDER BY name COLLATE NOCASE", COLLECTION_COLUMNS);
+        let mut stmt = self.conn
+            .prepare(&sql)
+            .map_err(|e| BlinkerError::Database(format!("prepare collections: {}", e)))?;
+        let collections = stmt
+            .query_map([], Self::collection_from_row)
+            .map_err(|e| BlinkerError::Database(format!("list collections: {}", e)))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| BlinkerError::Database(format!("row: {}", e)))?;
+        Ok(collections)
+    }
+
+    fn get_collection(&self, id: &str) -> Result<Option<Collection>> {
+        let sql = format!("SELECT {} FROM collection WHERE id = ?1", COLLECTION_COLUMNS);
+        self.conn
+            .query_row(&sql, params![id], Self::collection_from_row)
+            .optional()
+            .map_err(|e| BlinkerError::Database(format!("get collection: {}", e)))
+    }
+
+    fn create_collection(&self, name: &str) -> Result<Collection> {
+        self.insert_collection(name, None)
+    }
+
+    fn create_smart_collection(&self, name: &str, query: &LibraryQuery) -> Result<Collection> {
+        self.insert_collection(name, Some(query))
+    }
+
+    fn rename_collection(&self, id: &str, name: &str) -> Result<()> {
+        let name = self.checked_collection_name(name, Some(id))?;
+        let n = self.conn
+            .execute(
+                "UPDATE collection SET name = ?2, modified_at = ?3 WHERE id = ?1",
+                params![id, name, Self::now_secs()],
+            )
+            .map_err(|e| BlinkerError::Database(format!("rename collection: {}", e)))?;
+        if n == 0 {
+            return Err(BlinkerError::NotFound(format!("collection {}", id)));
+        }
+        Ok(())
+    }
+
+    fn set_collection_query(&self, id: &str, query: &LibraryQuery) -> Result<()> {
+        let n = self.conn
+            .execute(
+                "UPDATE collection SET query = ?2, modified_at = ?3 WHERE id = ?1 AND query IS NOT NULL",
+                params![id, Self::query_json(query)?, Self::now_secs()],
+            )
+            .map_err(|e| BlinkerError::Database(format!("set collection query: {}", e)))?;
+        if n == 0 {
+            return match self.get_collection(id)? {
+                Some(c) => Err(BlinkerError::Parsing(format!("{} is a manual collection", c.name))),
+                None => Err(BlinkerError::NotFound(format!("collection {}", id))),
+            };
+        }
+        Ok(())
+    }
+
+    fn delete_collection(&self, id: &str) -> Result<()> {
+        let n = self.conn
+            .execute("DELETE FROM collection WHERE id = ?1", params![id])
+            .map_err(|e| BlinkerError::Database(format!("delete collection: {}", e)))?;
+        if n == 0 {
+            return Err(BlinkerError::NotFound(format!("collection {}", id)));
+        }
+        Ok(())
+    }
+
+    fn collection_items(&self, id: &str) -> Result<Vec<LibraryItem>> {
+        let collection = self.get_collection(id)?
+            .ok_or_else(|| BlinkerError::NotFound(format!("collection {}", id)))?;
+        if let Some(query) = &collection.query {
+            return self.query(query);
+        }
+
+        let mut stmt = self.conn
+            .prepare("SELECT item_id FROM collection_item WHERE collection_id = ?1 ORDER BY position")
+            .map_err(|e| BlinkerError::Database(format!("prepare collection items: {}", e)))?;
+        let ids = stmt
+            .query_map(params![id], |row| row.get::<_, String>(0))
+            .map_err(|e| BlinkerError::Database(format!("collection items: {}", e)))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| BlinkerError::Database(format!("row: {}", e)))?;
+
+        let mut out = Vec::with_capacity(ids.len());
+        for item_id in ids {
+            if let Some(item) = self.get_item(&item_id)? {
+                out.push(item);
+            }
+        }
+        Ok(out)
+    }
+
+    fn add_to_collection(&self, id: &str, item_id: &str, position: Option<usize>) -> Result<()> {
+        self.manual_collection(id)?;
+        if self.get_item(item_id)?.is_none() {
+            return Err(BlinkerError::NotFound(format!("item {}", item_id)));
+        }
+
+        let tx = self
+            .write_transaction()
+            .map_err(|e| BlinkerError::Database(format!("begin collection update: {}", e)))?;
+        // Take the item out first so moving within the collection reuses the same path
+        self.remove_member(id, item_id)?;
+        let len: i64 = tx
+            .query_row("SELECT COUNT(*) FROM collection_item WHERE collection_id = ?1", params![id], |row| row.get(0))
+            .map_err(|e| BlinkerError::Database(format!("count collection items: {}", e)))?;
+        let position = position.map_or(len, |p| (p as i64).min(len));
+        tx.execute(
+            "UPDATE collection_item SET position = position + 1 WHERE collection_id = ?1 AND position >= ?2",
+            params![id, position],
+        )
+        .map_err(|e| BlinkerError::Database(format!("shift collection items: {}", e)))?;
+        tx.execute(
+            "INSERT INTO collection_item (collection_id, item_id, position, added_at) VALUES (?1, ?2, ?3, ?4)",
+            params![id, item_id, position, Self::now_secs()],
+        )
+        .map_err(|e| BlinkerError::Database(format!("add to collection: {}", e)))?;
+        self.touch_collection(id)?;
+        tx.commit().map_err(|e| BlinkerError::Database(format!("commit collection update: {}", e)))?;
+        Ok(())
+    }
+
+    fn remove_from_collection(&self, id: &str, item_id: &str) -> Result<()> {
+        self.manual_collection(id)?;
+        let tx = self
+            .write_transaction()
+            .map_err(|e| BlinkerError::Database(format!("begin collection update: {}", e)))?;
+        if !self.remove_member(id, item_id)? {
+            return Err(BlinkerError::NotFound(format!("item {} in collection {}", item_id, id)));
+        }
+        self.touch_collection(id)?;
+        tx.commit().map_err(|e| BlinkerError::Database(format!("commit collection update: {}", e)))?;
+        Ok(())
+    }
+
+    fn item_collections(&self, item_id: &str) -> Result<Vec<Collection>> {
+        let sql = format!(
+            "SELECT {} FROM collection WHERE id IN (SELECT collection_id FROM collection_item WHERE item_id = ?1)
+             ORDER BY name COLLATE NOCASE",
+            COLLECTION_COLUMNS
+        );
+        let mut stmt = self.conn
+            .prepare(&sql)
+            .map_err(|e| BlinkerError::Database(format!("prepare item collections: {}", e)))?;
+        let collections = stmt
+            .query_map(params![item_id], Self::collection_from_row)
+            .map_err(|e| BlinkerError::Database(format!("item collections: {}", e)))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| BlinkerError::Database(format!("row: {}", e)))?;
+        Ok(collections)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::library_with;
+
+    fn titles(db: &LibraryDatabase, id: &str) -> Vec<String> {
+        db.collection_items(id).unwrap().into_iter().map(|i| i.metadata.title).collect()
+    }
+
+    fn positions(db: &LibraryDatabase, id: &str) -> Vec<i64> {
+        let mut stmt = db.conn
+            .prepare("SELECT position FROM collection_item WHERE collection_id = ?1 ORDER BY position")
+            .unwrap();
+        stmt.query_map(params![id], |row| row.get(0)).unwrap().collect::<rusqlite::Result<_>>().unwrap()
+    }
+
+    #[test]
+    fn items_are_inserted_and_moved_in_place() {
+        let (_dir, db, ids) = library_with(&["a", "b", "c", "d"]);
+        let c = db.create_collection("Reading list").unwrap();
+        for id in &ids[..3] {
+            db.add_to_collection(&c.id, id, None).unwrap();
+        }
+        assert_eq!(titles(&db, &c.id), ["a", "b", "c"]);
+
+        db.add_to_collection(&c.id, &ids[3], Some(0)).unwrap();
+        assert_eq!(titles(&db, &c.id), ["d", "a", "b", "c"]);
+
+        // Moving an item already present does not duplicate it
+        db.add_to_collection(&c.id, &ids[2], Some(1)).unwrap();
+        assert_eq!(titles(&db, &c.id), ["d", "c", "a", "b"]);
+        db.add_to_collection(&c.id, &ids[3], None).unwrap();
+        assert_eq!(titles(&db, &c.id), ["c", "a", "b", "d"]);
+        db.add_to_collection(&c.id, &ids[0], Some(99)).unwrap();
+        assert_eq!(titles(&db, &c.id), ["c", "b", "d", "a"]);
+        assert_eq!(positions(&db, &c.id), [0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn removing_closes_the_gap() {
+        let (_dir, db, ids) = library_with(&["a", "b", "c"]);
+        let c = db.create_collection("Reading list").unwrap();
+        for id in &ids {
+            db.add_to_collection(&c.id, id, None).unwrap();
+        }
+        db.remove_from_collection(&c.id, &ids[1]).unwrap();
+        assert_eq!(titles(&db, &c.id), ["a", "c"]);
+        assert_eq!(positions(&db, &c.id), [0, 1]);
+        assert!(db.remove_from_collection(&c.id, &ids[1]).is_err());
+    }
+
+    #[test]
+    fn smart_collections_take_no_members() {
+        let (_dir, db, ids) = library_with(&["a"]);
+        let c = db.create_smart_collection("Everything", &LibraryQuery::default()).unwrap();
+        assert!(db.add_to_collection(&c.id, &ids[0], None).is_err());
+        assert!(db.add_to_collection("no-such-collection", &ids[0], None).is_err());
+    }
+}
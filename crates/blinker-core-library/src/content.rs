@@ -0,0 +1,183 @@
+//! Library-wide full-text index of document contents.
+//!
+//! Page text is extracted through the renderers in `blinker-core-render` and
+//! stored in `content_page`/`content_fts`. An item is re-extracted only when
+//! its `file_hash` differs from the one recorded in `content_index_state`.
+
+use blinker_core_common::{types::DocumentFormat, BlinkerError, Result};
+use blinker_core_render::{format, AnyRenderer, PageText};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::Path;
+
+use crate::identifiers::{TEXT_PAGES, TEXT_SOURCE};
+use crate::{LibraryDatabase, LibraryItem, LibraryStore};
+
+/// Maximum number of matching pages reported per item.
+const MAX_MATCHES_PER_ITEM: usize = 10;
+
+/// Marks the start of a highlighted span in FTS5 snippets.
+const HIGHLIGHT_START: char = '\u{1}';
+/// Marks the end of a highlighted span in FTS5 snippets.
+const HIGHLIGHT_END: char = '\u{2}';
+
+/// One page of a document matching a content search.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentMatch {
+    pub page: usize,
+    /// Text surrounding the match.
+    pub snippet: String,
+    /// Byte ranges within `snippet` that matched the query.
+    pub highlights: Vec<(usize, usize)>,
+}
+
+/// An item whose contents match a content search.
+#[derive(Debug, Clone)]
+pub struct ContentHit {
+    pub item: LibraryItem,
+    /// Relevance of the best matching page (higher is better).
+    pub score: f64,
+    /// Matching pages in page order.
+    pub matches: Vec<ContentMatch>,
+}
+
+impl LibraryDatabase {
+    /// Re-extract and index the text of `path` unless `file_hash` is already
+    /// indexed. `pages` is used instead of extracting when given.
+    pub(crate) fn refresh_content_index(
+        &self,
+        item_id: &str,
+        file_hash: &str,
+        path: &Path,
+        pages: Option<Result<Vec<PageText>>>,
+    ) -> Result<()> {
+        let indexed: Option<String> = self.conn
+            .query_row(
+                "SELECT file_hash FROM content_index_state WHERE item_id = ?1",
+                params![item_id],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| BlinkerError::Database(format!("read content state: {}", e)))?;
+        if indexed.as_deref() == Some(file_hash) {
+            return Ok(());
+        }
+
+        let pages = match pages {
+            Some(pages) => pages?,
+            None => AnyRenderer::open_for(path)?.extract_text()?,
+        };
+        tracing::debug!("Indexing {} pages of text for {:?}", pages.len(), path);
+
+        let tx = self
+            .write_transaction()
+            .map_err(|e| BlinkerError::Database(format!("begin content index: {}", e)))?;
+        tx.execute("DELETE FROM content_page WHERE item_id = ?1", params![item_id])
+            .map_err(|e| BlinkerError::Database(format!("clear content: {}", e)))?;
+        {
+            let mut insert = tx
+                .prepare("INSERT INTO content_page (item_id, page, body) VALUES (?1, ?2, ?3)")
+                .map_err(|e| BlinkerError::Database(format!("prepare content insert: {}", e)))?;
+            for page in &pages {
+                insert
+                    .execute(params![item_id, page.page as i64, page.text])
+                    .map_err(|e| BlinkerError::Database(format!("insert content: {}", e)))?;
+            }
+        }
+        // PDFs carry no structured identifiers; look for printed ones
+        if format::detect(path).ok() == Some(DocumentFormat::Pdf) {
+            let front: Vec<&str> = pages.iter().take(TEXT_PAGES).map(|p| p.text.as_str()).collect();
+            let identifiers = crate::identifiers::find_in_text(&front.join("\n"));
+            self.replace_identifiers(item_id, TEXT_SOURCE, &identifiers)?;
+        }
+        tx.execute(
+            "INSERT INTO content_index_state (item_id, file_hash, indexed_at) VALUES (?1, ?2, ?3)
+             ON CONFLICT(item_id) DO UPDATE SET file_hash = excluded.file_hash, indexed_at = excluded.indexed_at",
+            params![item_id, file_hash, Self::now_secs()],
+        )
+        .map_err(|e| BlinkerError::Database(format!("record content state: {}", e)))?;
+        tx.commit()
+            .map_err(|e| BlinkerError::Database(format!("commit content index: {}", e)))?;
+        Ok(())
+    }
+
+    /// File hashes whose text is already in the content index.
+    pub(crate) fn content_indexed_hashes_impl(&self) -> Result<HashSet<String>> {
+        let mut stmt = self.conn
+            .prepare("SELECT file_hash FROM content_index_state")
+            .map_err(|e| BlinkerError::Database(format!("prepare content state: {}", e)))?;
+        let hashes = stmt
+            .query_map([], |row| row.get(0))
+            .map_err(|e| BlinkerError::Database(format!("read content state: {}", e)))?
+            .collect::<rusqlite::Result<HashSet<String>>>()
+            .map_err(|e| BlinkerError::Database(format!("row: {}", e)))?;
+        Ok(hashes)
+    }
+
+    pub(crate) fn search_content_impl(&self, text: &str, limit: usize) -> Result<Vec<ContentHit>> {
+        let Some(expr) = crate::search::match_expression_for(text, &[]) else {
+            return Ok(vec![]);
+        };
+
+        // Rank items by their best page first, then fetch snippets per item.
+        let mut stmt = self.conn
+            .prepare(
+                "SELECT item_id, min(rank) AS best FROM (
+                    SELECT p.item_id AS item_id, content_fts.rank AS rank
+                    FROM content_fts JOIN content_page p ON p.id = content_fts.rowid
+                    WHERE content_fts MATCH ?1
+                 ) GROUP BY item_id ORDER BY best ASC LIMIT ?2",
+            )
+            .map_err(|e| BlinkerError::Database(format!("prepare content search: {}", e)))?;
+        let ranked = stmt
+            .query_map(params![expr, limit as i64], |row| Ok((row.get::<_, String>(0)?, row.get::<_, f64>(1)?)))
+            .map_err(|e| BlinkerError::Database(format!("content search: {}", e)))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| BlinkerError::Database(format!("row: {}", e)))?;
+
+        let mut pages = self.conn
+            .prepare(
+                "SELECT p.page, snippet(content_fts, 0, char(1), char(2), '…', 16)
+                 FROM content_fts JOIN content_page p ON p.id = content_fts.rowid
+                 WHERE content_fts MATCH ?1 AND p.item_id = ?2
+                 ORDER BY p.page ASC LIMIT ?3",
+            )
+            .map_err(|e| BlinkerError::Database(format!("prepare content snippets: {}", e)))?;
+
+        let mut hits = Vec::with_capacity(ranked.len());
+        for (item_id, rank) in ranked {
+            let Some(item) = self.get_item(&item_id)? else { continue };
+            let matches = pages
+                .query_map(params![expr, item_id, MAX_MATCHES_PER_ITEM as i64], |row| {
+                    let page: i64 = row.get(0)?;
+                    let raw: String = row.get(1)?;
+                    Ok(Self::content_match(page as usize, &raw))
+                })
+                .map_err(|e| BlinkerError::Database(format!("content snippets: {}", e)))?
+                .collect::<rusqlite::Result<Vec<_>>>()
+                .map_err(|e| BlinkerError::Database(format!("row: {}", e)))?;
+            hits.push(ContentHit { item, score: -rank, matches });
+        }
+        Ok(hits)
+    }
+
+    /// Strip highlight markers from an FTS5 snippet, recording their byte ranges.
+    fn content_match(page: usize, raw: &str) -> ContentMatch {
+        let mut snippet = String::with_capacity(raw.len());
+        let mut highlights = vec![];
+        let mut start = None;
+        for c in raw.chars() {
+            match c {
+                HIGHLIGHT_START => start = Some(snippet.len()),
+                HIGHLIGHT_END => {
+                    if let Some(s) = start.take() {
+                        highlights.push((s, snippet.len()));
+                    }
+                }
+                _ => snippet.push(c),
+            }
+        }
+        ContentMatch { page, snippet, highlights }
+    }
+}
@@ -0,0 +1,307 @@
+//! People credited on documents: authors, editors, translators and
+//! illustrators, each stored once with a sort name.
+//!
+//! Names are compared through a folded form: lowercase ASCII words with the
+//! surname last, so `Asimov, Isaac`, `ISAAC ASIMOV` and `Isaac Asimov` are
+//! one person and `Gabriel García Márquez` matches `Gabriel Garcia Marquez`.
+//! Queries by name are looser still, see [`LibraryQuery::author`].
+
+use blinker_core_common::types::{Contributor, ContributorRole};
+use blinker_core_common::{BlinkerError, Result};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::{LibraryDatabase, LibraryItem, LibraryQuery, LibraryStore, SortKey};
+
+/// Generational suffixes, kept out of comparisons and after the given names
+/// in sort names (`King, Martin Luther, Jr.`).
+const SUFFIXES: [&str; 5] = ["jr", "sr", "ii", "iii", "iv"];
+
+/// A person and how many items credit them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContributorSummary {
+    pub id: String,
+    /// Name as first seen.
+    pub name: String,
+    pub sort_name: String,
+    pub items: usize,
+}
+
+/// Queries over contributors.
+pub trait ContributorStore {
+    /// Everyone credited on at least one item, by sort name. With `role`,
+    /// only people credited in that role, counting those items.
+    fn list_contributors(&self, role: Option<ContributorRole>) -> Result<Vec<ContributorSummary>>;
+    /// Items crediting `name` in any role, by title. Matches the spellings
+    /// [`LibraryQuery::author`] does.
+    fn works_by(&self, name: &str) -> Result<Vec<LibraryItem>>;
+}
+
+/// Words of a name folded for comparison: transliterated to lowercase ASCII,
+/// without punctuation or generational suffixes, given names first.
+fn name_words(name: &str) -> Vec<String> {
+    let fold = |s: &str| -> Vec<String> {
+        deunicode::deunicode(s)
+            .to_ascii_lowercase()
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(str::to_string)
+            .collect()
+    };
+    // `Surname, Given[, Jr.]`
+    let mut parts = name.splitn(3, ',').map(fold);
+    let first = parts.next().unwrap_or_default();
+    let mut words = match parts.next() {
+        Some(given) if !given.is_empty() && !given.iter().all(|w| SUFFIXES.contains(&w.as_str())) => {
+            given.into_iter().chain(first).collect()
+        }
+        _ => fold(name),
+    };
+    if words.len() > 1 {
+        words.retain(|w| !SUFFIXES.contains(&w.as_str()));
+    }
+    words
+}
+
+/// Key under which a person is stored: their folded words. Empty for names
+/// without letters or digits.
+pub(crate) fn match_key(name: &str) -> String {
+    name_words(name).join(" ")
+}
+
+/// `Surname, Given Names` from a name written given names first. Names
+/// already containing a comma, and single names, are kept as they are.
+pub(crate) fn sort_name(name: &str) -> String {
+    let name = name.trim();
+    if name.contains(',') {
+        return name.to_string();
+    }
+    let mut words: Vec<&str> = name.split_whitespace().collect();
+    let suffix = match words.last() {
+        Some(w) if words.len() > 2 && SUFFIXES.contains(&w.trim_end_matches('.').to_ascii_lowercase().as_str()) => words.pop(),
+        _ => None,
+    };
+    match words.split_last() {
+        Some((surname, given)) if !given.is_empty() => {
+            let mut sorted = format!("{}, {}", surname, given.join(" "));
+            if let Some(suffix) = suffix {
+                sorted.push_str(", ");
+                sorted.push_str(suffix);
+            }
+            sorted
+        }
+        _ => name.to_string(),
+    }
+}
+
+/// Whether two folded names may be the same person: the surnames agree
+/// (allowing one typo in longer ones when given names are present), the
+/// first given names agree or one is the other's initial, and the remaining
+/// given names of one appear in order in the other. A surname alone matches
+/// everyone with that surname.
+fn same_person(a: &[&str], b: &[&str]) -> bool {
+    let (Some((a_last, a_given)), Some((b_last, b_given))) = (a.split_last(), b.split_last()) else {
+        return false;
+    };
+    if a_given.is_empty() || b_given.is_empty() {
+        return a_last == b_last;
+    }
+    let near = a_last == b_last || (a_last.len().min(b_last.len()) >= 6 && within_one_edit(a_last, b_last));
+    if !near {
+        return false;
+    }
+    let word_matches = |x: &str, y: &str| {
+        x == y || (x.len() == 1 && y.starts_with(x)) || (y.len() == 1 && x.starts_with(y))
+    };
+    if !word_matches(a_given[0], b_given[0]) {
+        return false;
+    }
+    let (short, long) = if a_given.len() <= b_given.len() { (a_given, b_given) } else { (b_given, a_given) };
+    let mut rest = long[1..].iter();
+    short[1..].iter().all(|w| rest.any(|l| word_matches(w, l)))
+}
+
+/// Whether `a` becomes `b` with at most one insertion, deletion or substitution.
+fn within_one_edit(a: &str, b: &str) -> bool {
+    let (a, b) = (a.as_bytes(), b.as_bytes());
+    let (short, long) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    if long.len() - short.len() > 1 {
+        return false;
+    }
+    let prefix = short.iter().zip(long).take_while(|(x, y)| x == y).count();
+    if short.len() == long.len() {
+        short[prefix..].iter().skip(1).eq(long[prefix..].iter().skip(1))
+    } else {
+        short[prefix..] == long[prefix + 1..]
+    }
+}
+
+impl LibraryDatabase {
+    /// Id of the stored person matching `c`, creating them if needed. A sort
+    /// name given by the source replaces the stored one. `None` for names
+    /// without letters or digits.
+    fn contributor_id(&self, c: &Contributor) -> Result<Option<String>> {
+        let key = match_key(&c.name);
+        if key.is_empty() {
+            return Ok(None);
+        }
+        let sort = c.sort_name.as_deref().map(str::trim).filter(|s| !s.is_empty());
+        let existing: Option<String> = self.conn
+            .query_row("SELECT id FROM contributor WHERE match_key = ?1", params![key], |row| row.get(0))
+            .optional()
+            .map_err(|e| BlinkerError::Database(format!("find contributor: {}", e)))?;
+        if let Some(id) = existing {
+            if let Some(sort) = sort {
+                self.conn
+                    .execute("UPDATE contributor SET sort_name = ?2 WHERE id = ?1", params![id, sort])
+                    .map_err(|e| BlinkerError::Database(format!("update sort name: {}", e)))?;
+            }
+            return Ok(Some(id));
+        }
+        let id = uuid::Uuid::new_v4().to_string();
+        let name = c.name.trim();
+        self.conn
+            .execute(
+                "INSERT INTO contributor (id, name, sort_name, match_key, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+                params![id, name, sort.map_or_else(|| sort_name(name), str::to_string), key, Self::now_secs()],
+            )
+            .map_err(|e| BlinkerError::Database(format!("insert contributor: {}", e)))?;
+        Ok(Some(id))
+    }
+
+    /// Replace the people credited on an item, or with `role` only the ones
+    /// in that role (other entries of `contributors` are ignored then).
+    pub(crate) fn replace_contributors(
+        &self,
+        item_id: &str,
+        contributors: &[Contributor],
+        role: Option<ContributorRole>,
+    ) -> Result<()> {
+        self.conn
+            .execute(
+                "DELETE FROM item_contributor WHERE item_id = ?1 AND (?2 IS NULL OR role = ?2)",
+                params![item_id, role.map(|r| r.as_str())],
+            )
+            .map_err(|e| BlinkerError::Database(format!("clear contributors: {}", e)))?;
+        let credited = contributors.iter().filter(|c| role.is_none_or(|r| c.role == r));
+        for (position, c) in credited.enumerate() {
+            let Some(contributor_id) = self.contributor_id(c)? else {
+                continue;
+            };
+            self.conn
+                .execute(
+                    "INSERT OR IGNORE INTO item_contributor (item_id, contributor_id, role, position) VALUES (?1, ?2, ?3, ?4)",
+                    params![item_id, contributor_id, c.role.as_str(), position as i64],
+                )
+                .map_err(|e| BlinkerError::Database(format!("credit contributor: {}", e)))?;
+        }
+        Ok(())
+    }
+
+    /// Ids of the stored people `name` may refer to.
+    pub(crate) fn matching_contributors(&self, name: &str) -> Result<Vec<String>> {
+        let wanted = name_words(name);
+        let wanted: Vec<&str> = wanted.iter().map(String::as_str).collect();
+        let mut stmt = self.conn
+            .prepare("SELECT id, match_key FROM contributor")
+            .map_err(|e| BlinkerError::Database(format!("prepare contributors: {}", e)))?;
+        let people = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?)))
+            .map_err(|e| BlinkerError::Database(format!("read contributors: {}", e)))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| BlinkerError::Database(format!("row: {}", e)))?;
+        Ok(people
+            .into_iter()
+            .filter(|(_, key)| same_person(&wanted, &key.split(' ').collect::<Vec<_>>()))
+            .map(|(id, _)| id)
+            .collect())
+    }
+}
+
+impl ContributorStore for LibraryDatabase {
+    fn list_contributors(&self, role: Option<ContributorRole>) -> Result<Vec<ContributorSummary>> {
+        let mut stmt = self.conn
+            .prepare(
+                "SELECT c.id, c.name, c.sort_name, COUNT(DISTINCT ic.item_id)
+                 FROM contributor c JOIN item_contributor ic ON ic.contributor_id = c.id
+                 WHERE ?1 IS NULL OR ic.role = ?1
+                 GROUP BY c.id ORDER BY c.sort_name COLLATE NOCASE, c.id",
+            )
+            .map_err(|e| BlinkerError::Database(format!("prepare contributors: {}", e)))?;
+        let people = stmt
+            .query_map(params![role.map(|r| r.as_str())], |row| {
+                let items: i64 = row.get(3)?;
+                Ok(ContributorSummary { id: row.get(0)?, name: row.get(1)?, sort_name: row.get(2)?, items: items as usize })
+            })
+            .map_err(|e| BlinkerError::Database(format!("list contributors: {}", e)))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| BlinkerError::Database(format!("row: {}", e)))?;
+        Ok(people)
+    }
+
+    fn works_by(&self, name: &str) -> Result<Vec<LibraryItem>> {
+        self.query(&LibraryQuery {
+            author: Some(name.to_string()),
+            sort: SortKey::Title,
+            ..Default::default()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn same(a: &str, b: &str) -> bool {
+        let (a, b) = (name_words(a), name_words(b));
+        let a: Vec<&str> = a.iter().map(String::as_str).collect();
+        let b: Vec<&str> = b.iter().map(String::as_str).collect();
+        same_person(&a, &b)
+    }
+
+    #[test]
+    fn names_fold_to_given_names_first() {
+        assert_eq!(match_key("Asimov, Isaac"), "isaac asimov");
+        assert_eq!(match_key("  Stanisław Lem "), "stanislaw lem");
+        assert_eq!(match_key("King, Martin Luther, Jr."), "martin luther king");
+        assert_eq!(match_key("?!"), "");
+    }
+
+    #[test]
+    fn sort_names_put_the_surname_first() {
+        assert_eq!(sort_name("Isaac Asimov"), "Asimov, Isaac");
+        assert_eq!(sort_name("Martin Luther King Jr."), "King, Martin Luther, Jr.");
+        assert_eq!(sort_name("Asimov, Isaac"), "Asimov, Isaac");
+        assert_eq!(sort_name("Moebius"), "Moebius");
+    }
+
+    #[test]
+    fn variants_of_a_name_are_the_same_person() {
+        assert!(same("Isaac Asimov", "Asimov, Isaac"));
+        assert!(same("J. R. R. Tolkien", "John Ronald Reuel Tolkien"));
+        assert!(same("J. Tolkien", "John Ronald Reuel Tolkien"));
+        assert!(same("Dostoevsky", "Fyodor Dostoevsky"));
+        assert!(same("Fyodor Dostoevsky", "Fyodor Dostoyevsky"));
+    }
+
+    #[test]
+    fn different_people_stay_apart() {
+        assert!(!same("Isaac Asimov", "Janet Asimov"));
+        assert!(!same("John Smith", "John Smyth"));
+        assert!(!same("Fyodor Dostoevsky", "Fyodor Dostoevskaya"));
+        assert!(!same("Ronald Tolkien", "John Ronald Reuel Tolkien"));
+        assert!(!same("", "Asimov"));
+    }
+
+    #[test]
+    fn one_edit_apart() {
+        assert!(within_one_edit("asimov", "asimov"));
+        assert!(within_one_edit("asimov", "azimov"));
+        assert!(within_one_edit("asimov", "asimof"));
+        assert!(within_one_edit("asimov", "asimovv"));
+        assert!(within_one_edit("asimov", "simov"));
+        assert!(!within_one_edit("asimov", "azimof"));
+        assert!(!within_one_edit("asimov", "asim"));
+        assert!(!within_one_edit("asimov", "sasimo"));
+    }
+}
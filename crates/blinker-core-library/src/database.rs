@@ -2,13 +2,144 @@ use blinker_core_common::{BlinkerError, Result};
 use std::fs::File;
 use std::io::{Read};
 use std::path::{Path, PathBuf};
-use rusqlite::{Connection, params};
-use crate::{LibraryItem, LibraryQuery, LibraryStore, AddOutcome};
+use rusqlite::{Connection, OptionalExtension, Transaction, TransactionBehavior, params};
+use rusqlite::types::Value;
+use blinker_core_common::types::{Contributor, ContributorRole, DocumentFormat, Metadata};
+use blinker_core_render::{format, AnyRenderer, PageText};
+use std::collections::HashSet;
+use crate::{ContentHit, MetadataField, PruneReport, LibraryItem, LibraryQuery, LibraryStore, AddOutcome, UnchangedFile, ReadStatus, SortDirection, SortKey, Tag, TagMatch};
 
-const SCHEMA_SQL: &str = include_str!("../../../sql/001_initial_schema.sql");
+/// Ordered schema migrations. Each entry is applied at most once, inside its
+/// own transaction, and recorded in `schema_version`.
+const MIGRATIONS: &[(i64, &str)] = &[
+    (1, include_str!("../../../sql/001_initial_schema.sql")),
+    (2, include_str!("../../../sql/002_tag_search.sql")),
+    (3, include_str!("../../../sql/003_content_index.sql")),
+    (4, include_str!("../../../sql/004_metadata_override.sql")),
+    (5, include_str!("../../../sql/005_document_metadata.sql")),
+    (6, include_str!("../../../sql/006_missing_files.sql")),
+    (7, include_str!("../../../sql/007_library_roots.sql")),
+    (8, include_str!("../../../sql/008_file_mtime.sql")),
+    (9, include_str!("../../../sql/009_item_location.sql")),
+    (10, include_str!("../../../sql/010_collections.sql")),
+    (11, include_str!("../../../sql/011_series.sql")),
+    (12, include_str!("../../../sql/012_item_identifier.sql")),
+    (13, include_str!("../../../sql/013_reading_direction.sql")),
+    (14, include_str!("../../../sql/014_isbn13.sql")),
+    (15, include_str!("../../../sql/015_contributors.sql")),
+    (16, include_str!("../../../sql/016_single_page_progress.sql")),
+    (17, include_str!("../../../sql/017_tag_source.sql")),
+];
+
+/// Highest schema version known to this build.
+pub const SCHEMA_VERSION: i64 = MIGRATIONS[MIGRATIONS.len() - 1].0;
+
+/// How long a write waits for another connection to release the database.
+const BUSY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Separator used when aggregating tag names into a single column.
+const TAG_SEPARATOR: char = '\u{1f}';
+
+/// Separates role, name and sort name of one aggregated contributor; the
+/// contributors themselves are separated by `TAG_SEPARATOR`.
+const CONTRIBUTOR_FIELD_SEPARATOR: char = '\u{1e}';
+
+/// bm25 column weights for `library_fts` (item_id, title, author, subject, tags).
+const FTS_WEIGHTS: &str = "0.0, 10.0, 5.0, 2.0, 3.0";
+
+/// Sort name of an item's first author, for sorting by author. Items
+/// without contributors yet (indexed before they were recorded) fall back
+/// to the author string.
+const AUTHOR_SORT: &str = "COALESCE((SELECT c.sort_name FROM item_contributor ic \
+    JOIN contributor c ON c.id = ic.contributor_id \
+    WHERE ic.item_id = library_item.id AND ic.role = 'author' ORDER BY ic.position LIMIT 1), author) COLLATE NOCASE";
+
+/// Last time an item was opened, for filtering and sorting queries.
+const LAST_OPENED: &str = "(SELECT rs.last_opened FROM reading_state rs WHERE rs.item_id = library_item.id)";
+
+/// Columns read by [`LibraryDatabase::item_from_row`].
+const ITEM_COLUMNS: &str = "id, file_path, file_hash, file_type, file_size, \
+    title, author, publisher, subject, language, page_count, doc_created_at, doc_modified_at, missing_since, \
+    series, series_index, right_to_left, \
+    (SELECT group_concat(n.name, char(31)) FROM \
+        (SELECT t.name FROM item_tag it JOIN tag t ON t.id = it.tag_id \
+         WHERE it.item_id = library_item.id ORDER BY t.name COLLATE NOCASE) n) AS tags, \
+    (SELECT group_concat(p.role || char(30) || p.name || char(30) || p.sort_name, char(31)) FROM \
+        (SELECT ic.role, c.name, c.sort_name FROM item_contributor ic JOIN contributor c ON c.id = ic.contributor_id \
+         WHERE ic.item_id = library_item.id ORDER BY ic.position, ic.role) p) AS contributors";
 
 pub struct LibraryDatabase {
-    conn: Connection,
+    pub(crate) conn: Connection,
+}
+
+/// A file hashed and parsed ahead of being written with
+/// [`LibraryStore::add_prepared`]. Preparing touches no database, so scans
+/// run it on worker threads.
+pub struct PreparedFile {
+    path: PathBuf,
+    file_size: u64,
+    file_mtime: Option<i64>,
+    file_hash: String,
+    file_type: String,
+    /// Format detected from the contents; `None` when unrecognised.
+    format: Option<DocumentFormat>,
+    metadata: Metadata,
+    /// Tags the document declares itself (front matter).
+    tags: Vec<String>,
+    /// Identifiers the document declares itself, as `(scheme, value)`.
+    identifiers: Vec<(String, String)>,
+    /// Page text, when extracted ahead of time.
+    pages: Option<Result<Vec<PageText>>>,
+}
+
+impl PreparedFile {
+    /// Hash `path` and extract its metadata.
+    pub fn read(path: &Path) -> Result<Self> {
+        // Normalize path to an absolute, canonical form when possible
+        let canon = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let meta = std::fs::metadata(&canon).map_err(BlinkerError::Io)?;
+        if !meta.is_file() {
+            return Err(BlinkerError::Parsing(format!("not a file: {}", path.display())));
+        }
+        let format = format::detect(&canon).ok();
+        let (tags, identifiers) =
+            format.map(|f| crate::metadata::MetadataExtractor::extract_tags_and_identifiers(path, f)).unwrap_or_default();
+        Ok(Self {
+            file_size: meta.len(),
+            file_mtime: LibraryDatabase::mtime_nanos(&meta),
+            file_hash: LibraryDatabase::file_hash(path)?,
+            file_type: LibraryDatabase::infer_file_type(path, format),
+            metadata: LibraryDatabase::extract_metadata_as(path, format),
+            tags,
+            identifiers,
+            format,
+            pages: None,
+            path: canon,
+        })
+    }
+
+    /// Also extract the page text for the content index. Without this the
+    /// text is extracted while writing, if the index is out of date.
+    pub fn load_text(&mut self) {
+        let renderer = match self.format {
+            Some(format) => Ok(AnyRenderer::with_format(&self.path, format)),
+            None => AnyRenderer::open_for(&self.path),
+        };
+        self.pages = Some(renderer.and_then(|r| r.extract_text()));
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn file_hash(&self) -> &str {
+        &self.file_hash
+    }
+
+    /// Format detected from the file's contents or extension.
+    pub fn format(&self) -> Option<DocumentFormat> {
+        self.format
+    }
 }
 
 impl LibraryDatabase {
@@ -17,38 +148,97 @@ impl LibraryDatabase {
             .map_err(|e| BlinkerError::Database(format!("open db: {}", e)))?;
         conn.execute("PRAGMA foreign_keys = ON", [])
             .map_err(|e| BlinkerError::Database(format!("pragma: {}", e)))?;
+        // WAL lets readers (the UI, the watcher) run alongside a scan, and with
+        // synchronous=NORMAL a commit no longer waits for a disk flush per file.
+        conn.pragma_update(None, "journal_mode", "WAL")
+            .map_err(|e| BlinkerError::Database(format!("pragma: {}", e)))?;
+        conn.pragma_update(None, "synchronous", "NORMAL")
+            .map_err(|e| BlinkerError::Database(format!("pragma: {}", e)))?;
+        // The UI, a scan and the watcher each write through their own
+        // connection; wait for the lock instead of failing with SQLITE_BUSY
+        conn.busy_timeout(BUSY_TIMEOUT)
+            .map_err(|e| BlinkerError::Database(format!("busy timeout: {}", e)))?;
         let db = Self { conn };
         db.migrate()?;
         Ok(db)
     }
 
     fn get_schema_version(&self) -> Result<i64> {
-        let mut stmt = match self.conn.prepare("SELECT MAX(version) FROM schema_version") {
-            Ok(s) => s,
-            Err(_) => return Ok(0),
-        };
-        let v: Option<i64> = stmt
-            .query_row([], |row| row.get(0))
-            .ok()
-            .unwrap_or(None);
+        let has_table: bool = self.conn
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'schema_version')",
+                [],
+                |row| row.get(0),
+            )
+            .map_err(|e| BlinkerError::Database(format!("read schema version: {}", e)))?;
+        if !has_table {
+            return Ok(0);
+        }
+        let v: Option<i64> = self.conn
+            .query_row("SELECT MAX(version) FROM schema_version", [], |row| row.get(0))
+            .map_err(|e| BlinkerError::Database(format!("read schema version: {}", e)))?;
         Ok(v.unwrap_or(0))
     }
 
+    /// Bring the schema up to [`SCHEMA_VERSION`], applying pending migrations in order.
+    ///
+    /// Refuses to touch a database written by a newer build.
     pub fn migrate(&self) -> Result<()> {
         let version = self.get_schema_version()?;
-        if version < 1 {
-            self.conn
-                .execute_batch(SCHEMA_SQL)
-                .map_err(|e| BlinkerError::Database(format!("migrate: {}", e)))?;
+        if version > SCHEMA_VERSION {
+            return Err(BlinkerError::Database(format!(
+                "database schema version {} is newer than supported version {}",
+                version, SCHEMA_VERSION
+            )));
+        }
+
+        for &(target, sql) in MIGRATIONS.iter().filter(|(v, _)| *v > version) {
+            // IMMEDIATE takes the write lock up front so two processes opening
+            // the same library cannot both apply a migration.
+            let tx = Transaction::new_unchecked(&self.conn, TransactionBehavior::Immediate)
+                .map_err(|e| BlinkerError::Database(format!("begin migration {}: {}", target, e)))?;
+            if self.get_schema_version()? >= target {
+                continue;
+            }
+            tx.execute_batch(
+                "CREATE TABLE IF NOT EXISTS schema_version (
+                    version INTEGER PRIMARY KEY,
+                    applied_at INTEGER NOT NULL
+                );",
+            )
+            .and_then(|_| tx.execute_batch(sql))
+            .and_then(|_| {
+                tx.execute(
+                    "INSERT INTO schema_version (version, applied_at) VALUES (?1, ?2)",
+                    params![target, Self::now_secs()],
+                )
+            })
+            .map_err(|e| BlinkerError::Database(format!("migration {}: {}", target, e)))?;
+            tx.commit()
+                .map_err(|e| BlinkerError::Database(format!("commit migration {}: {}", target, e)))?;
+            tracing::info!("Applied schema migration {}", target);
         }
         Ok(())
     }
 
-    fn now_secs() -> i64 {
+    /// Begin a transaction that takes the write lock up front. A deferred
+    /// one that reads first cannot wait for another connection's write to
+    /// finish: its snapshot is stale by then and the upgrade fails.
+    pub(crate) fn write_transaction(&self) -> rusqlite::Result<Transaction<'_>> {
+        Transaction::new_unchecked(&self.conn, TransactionBehavior::Immediate)
+    }
+
+    pub(crate) fn now_secs() -> i64 {
         use std::time::{SystemTime, UNIX_EPOCH};
         SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs() as i64
     }
 
+    /// Modification time in nanoseconds since the Unix epoch, if the platform reports one.
+    fn mtime_nanos(meta: &std::fs::Metadata) -> Option<i64> {
+        let since_epoch = meta.modified().ok()?.duration_since(std::time::UNIX_EPOCH).ok()?;
+        i64::try_from(since_epoch.as_nanos()).ok()
+    }
+
     fn file_hash(path: &Path) -> Result<String> {
         let mut file = File::open(path)?;
         let mut hasher = blake3::Hasher::new();
@@ -61,204 +251,1217 @@ impl LibraryDatabase {
         Ok(hasher.finalize().to_hex().to_string())
     }
 
-    fn infer_file_type(path: &Path) -> String {
-        path.extension()
-            .and_then(|e| e.to_str())
-            .map(|s| s.to_lowercase())
-            .unwrap_or_else(|| "unknown".into())
+    fn placeholders(n: usize) -> String {
+        vec!["?"; n].join(",")
+    }
+
+    /// Map a row selected with [`ITEM_COLUMNS`] plus a trailing `score`
+    /// column into a `LibraryItem`.
+    fn item_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<LibraryItem> {
+        let file_path: String = row.get("file_path")?;
+        let file_size: i64 = row.get("file_size")?;
+        let page_count: Option<i64> = row.get("page_count")?;
+        let tags: Option<String> = row.get("tags")?;
+        let contributors: Option<String> = row.get("contributors")?;
+        let contributors = contributors
+            .iter()
+            .flat_map(|all| all.split(TAG_SEPARATOR))
+            .filter_map(|entry| {
+                let mut fields = entry.split(CONTRIBUTOR_FIELD_SEPARATOR);
+                let role = ContributorRole::parse(fields.next()?)?;
+                let name = fields.next()?.to_string();
+                Some(Contributor { name, role, sort_name: fields.next().map(str::to_string) })
+            })
+            .collect();
+        let metadata = Metadata {
+            title: row.get("title")?,
+            author: row.get("author")?,
+            publisher: row.get("publisher")?,
+            subject: row.get("subject")?,
+            language: row.get("language")?,
+            created_at: row.get("doc_created_at")?,
+            modified_at: row.get("doc_modified_at")?,
+            page_count: page_count.map(|n| n as usize),
+            series: row.get("series")?,
+            series_index: row.get("series_index")?,
+            right_to_left: row.get("right_to_left")?,
+            contributors,
+        };
+        Ok(LibraryItem {
+            id: row.get("id")?,
+            file_path: PathBuf::from(file_path),
+            file_hash: row.get("file_hash")?,
+            file_type: row.get("file_type")?,
+            file_size: file_size as u64,
+            metadata,
+            tags: tags
+                .map(|t| t.split(TAG_SEPARATOR).map(str::to_string).collect())
+                .unwrap_or_default(),
+            missing_since: row.get("missing_since")?,
+            score: row.get("score")?,
+        })
+    }
+
+    fn tag_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Tag> {
+        Ok(Tag {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            color: row.get(2)?,
+            created_at: row.get(3)?,
+        })
+    }
+
+    fn normalize_tag_name(name: &str) -> Result<String> {
+        let name = name.trim();
+        if name.is_empty() || name.contains(TAG_SEPARATOR) {
+            return Err(BlinkerError::Parsing(format!("invalid tag name: {:?}", name)));
+        }
+        Ok(name.to_string())
+    }
+
+    /// Replace the tags `source` gave an item with `names`, creating tags
+    /// that do not exist yet. Names that are not valid tag names are skipped;
+    /// tags the user assigned themselves are left alone.
+    fn replace_tags(&self, item_id: &str, source: &str, names: &[String]) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM item_tag WHERE item_id = ?1 AND source = ?2", params![item_id, source])
+            .map_err(|e| BlinkerError::Database(format!("clear tags: {}", e)))?;
+        for name in names {
+            let Ok(name) = Self::normalize_tag_name(name) else {
+                continue;
+            };
+            let existing: Option<String> = self.conn
+                .query_row("SELECT id FROM tag WHERE lower(name) = lower(?1)", params![name], |row| row.get(0))
+                .optional()
+                .map_err(|e| BlinkerError::Database(format!("find tag: {}", e)))?;
+            let tag_id = match existing {
+                Some(id) => id,
+                None => self.create_tag(&name, None)?.id,
+            };
+            self.conn
+                .execute(
+                    "INSERT OR IGNORE INTO item_tag (item_id, tag_id, created_at, source) VALUES (?1, ?2, ?3, ?4)",
+                    params![item_id, tag_id, Self::now_secs(), source],
+                )
+                .map_err(|e| BlinkerError::Database(format!("assign tag: {}", e)))?;
+        }
+        Ok(())
+    }
+
+    /// Tag names are unique ignoring ASCII case, matching how queries compare them.
+    fn ensure_tag_name_free(&self, name: &str, except_id: Option<&str>) -> Result<()> {
+        let taken: bool = self.conn
+            .query_row(
+                "SELECT EXISTS(SELECT 1 FROM tag WHERE lower(name) = lower(?1) AND id IS NOT ?2)",
+                params![name, except_id],
+                |row| row.get(0),
+            )
+            .map_err(|e| BlinkerError::Database(format!("check tag name: {}", e)))?;
+        if taken {
+            return Err(BlinkerError::Database(format!("tag already exists: {}", name)));
+        }
+        Ok(())
+    }
+
+    /// Set one metadata field and record it as a user override.
+    pub(crate) fn write_override(&self, item_id: &str, field: MetadataField, value: Option<&str>, now: i64) -> Result<()> {
+        let value = value.map(str::trim).filter(|v| !v.is_empty());
+        if field == MetadataField::Title && value.is_none() {
+            return Err(BlinkerError::Parsing("title cannot be empty".into()));
+        }
+        if field == MetadataField::SeriesIndex && value.is_some_and(|v| v.parse::<f64>().is_err()) {
+            return Err(BlinkerError::Parsing(format!("series index is not a number: {:?}", value.unwrap_or_default())));
+        }
+        let sql = format!("UPDATE library_item SET {} = ?2, modified_at = ?3 WHERE id = ?1", field.column());
+        let n = self.conn
+            .execute(&sql, params![item_id, value, now])
+            .map_err(|e| BlinkerError::Database(format!("update {}: {}", field.column(), e)))?;
+        if n == 0 {
+            return Err(BlinkerError::NotFound(format!("item {}", item_id)));
+        }
+        self.conn
+            .execute(
+                "INSERT INTO metadata_override (item_id, field, value, updated_at) VALUES (?1, ?2, ?3, ?4)
+                 ON CONFLICT(item_id, field) DO UPDATE SET value = excluded.value, updated_at = excluded.updated_at",
+                params![item_id, field.column(), value, now],
+            )
+            .map_err(|e| BlinkerError::Database(format!("record override: {}", e)))?;
+        if field == MetadataField::Author {
+            let authors = value.map(|v| Contributor::split(v, ContributorRole::Author)).unwrap_or_default();
+            self.replace_contributors(item_id, &authors, Some(ContributorRole::Author))?;
+        }
+        Ok(())
+    }
+
+    /// `write_override` an edit made at `updated_at`, unless the item holds a
+    /// newer override for the field. Returns whether it was written.
+    pub(crate) fn write_override_if_newer(&self, item_id: &str, field: MetadataField, value: Option<&str>, updated_at: i64) -> Result<bool> {
+        let current: Option<i64> = self.conn
+            .query_row(
+                "SELECT updated_at FROM metadata_override WHERE item_id = ?1 AND field = ?2",
+                params![item_id, field.column()],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| BlinkerError::Database(format!("read override: {}", e)))?;
+        if current.is_some_and(|current| current >= updated_at) {
+            return Ok(false);
+        }
+        self.write_override(item_id, field, value, updated_at)?;
+        Ok(true)
+    }
+
+    /// Extract metadata from a file, falling back to the file name as title.
+    fn extract_metadata(path: &Path) -> Metadata {
+        Self::extract_metadata_as(path, format::detect(path).ok())
+    }
+
+    /// Like `extract_metadata` for a file whose format was already detected.
+    fn extract_metadata_as(path: &Path, format: Option<DocumentFormat>) -> Metadata {
+        let extracted = match format {
+            Some(format) => crate::metadata::MetadataExtractor::extract_as(path, format),
+            None => Err(BlinkerError::Parsing(format!("Unsupported format: {}", path.display()))),
+        };
+        let mut meta = extracted.unwrap_or_else(|_| Metadata {
+            title: path.file_stem().and_then(|s| s.to_str()).unwrap_or("Untitled").to_string(),
+            ..Default::default()
+        });
+
+        // Paged formats whose extractor did not report a count: ask the renderer
+        if meta.page_count.is_none() {
+            if let Some(format @ (DocumentFormat::Pdf | DocumentFormat::Cbz)) = format {
+                match AnyRenderer::with_format(path, format).page_count() {
+                    Ok(n) => meta.page_count = Some(n),
+                    Err(e) => tracing::debug!("No page count for {:?}: {}", path, e),
+                }
+            }
+        }
+        meta
+    }
+
+    /// Store every `Metadata` field of an item, contributors included.
+    fn write_metadata(&self, item_id: &str, m: &Metadata, now: i64) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE library_item SET
+                    title=?2, author=?3, publisher=?4, subject=?5, language=?6, page_count=?7,
+                    doc_created_at=?8, doc_modified_at=?9, series=?10, series_index=?11, right_to_left=?12,
+                    modified_at=?13
+                 WHERE id=?1",
+                params![
+                    item_id,
+                    m.title,
+                    m.author,
+                    m.publisher,
+                    m.subject,
+                    m.language,
+                    m.page_count.map(|n| n as i64),
+                    m.created_at,
+                    m.modified_at,
+                    m.series,
+                    m.series_index,
+                    m.right_to_left,
+                    now,
+                ],
+            )
+            .map_err(|e| BlinkerError::Database(format!("write metadata: {}", e)))?;
+        self.replace_contributors(item_id, &m.contributors, None)
+    }
+
+    /// Re-extract and store the full metadata of every item whose file is
+    /// still present, keeping user overrides. Returns the number of items
+    /// refreshed. Use this to fill fields on rows indexed by older versions.
+    pub fn backfill_metadata(&self) -> Result<usize> {
+        let mut stmt = self.conn
+            .prepare("SELECT id, file_path FROM library_item")
+            .map_err(|e| BlinkerError::Database(format!("prepare backfill: {}", e)))?;
+        let items = stmt
+            .query_map([], |row| Ok((row.get::<_, String>(0)?, PathBuf::from(row.get::<_, String>(1)?))))
+            .map_err(|e| BlinkerError::Database(format!("backfill: {}", e)))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| BlinkerError::Database(format!("row: {}", e)))?;
+
+        let mut refreshed = 0;
+        for (id, path) in items {
+            if !path.is_file() {
+                continue;
+            }
+            let format = format::detect(&path).ok();
+            let mut meta = Self::extract_metadata_as(&path, format);
+            self.apply_overrides(&id, &mut meta)?;
+            self.write_metadata(&id, &meta, Self::now_secs())?;
+            if let Some(format) = format {
+                let (tags, identifiers) = crate::metadata::MetadataExtractor::extract_tags_and_identifiers(&path, format);
+                self.replace_tags(&id, crate::identifiers::FILE_SOURCE, &tags)?;
+                self.replace_identifiers(&id, crate::identifiers::FILE_SOURCE, &identifiers)?;
+            }
+            refreshed += 1;
+        }
+        Ok(refreshed)
+    }
+
+    /// Replace extracted values with the user's overrides for `item_id`.
+    fn apply_overrides(&self, item_id: &str, meta: &mut Metadata) -> Result<()> {
+        let mut stmt = self.conn
+            .prepare("SELECT field, value FROM metadata_override WHERE item_id = ?1")
+            .map_err(|e| BlinkerError::Database(format!("prepare overrides: {}", e)))?;
+        let rows = stmt
+            .query_map(params![item_id], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)))
+            .map_err(|e| BlinkerError::Database(format!("read overrides: {}", e)))?;
+        for r in rows {
+            let (field, value) = r.map_err(|e| BlinkerError::Database(format!("row: {}", e)))?;
+            match MetadataField::from_column(&field) {
+                Some(field) => field.apply(meta, value),
+                None => tracing::warn!("Ignoring override for unknown field {:?}", field),
+            }
+        }
+        Ok(())
+    }
+
+    /// Insert a new item. Its id is the content hash unless an item whose file
+    /// has since changed already holds that id.
+    fn insert_item(&self, path: &str, file_hash: &str, file_type: &str, file_size: u64, m: &Metadata, now: i64) -> Result<String> {
+        let taken: bool = self.conn
+            .query_row("SELECT EXISTS(SELECT 1 FROM library_item WHERE id = ?1)", params![file_hash], |row| row.get(0))
+            .map_err(|e| BlinkerError::Database(format!("check item id: {}", e)))?;
+        let id = if taken { uuid::Uuid::new_v4().to_string() } else { file_hash.to_string() };
+        self.conn
+            .execute(
+                "INSERT INTO library_item (
+                    id, file_path, file_hash, file_type, file_size,
+                    title, author, publisher, subject, language, page_count,
+                    doc_created_at, doc_modified_at, series, series_index, right_to_left,
+                    created_at, modified_at, indexed_at
+                ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?17, ?17)",
+                params![
+                    id,
+                    path,
+                    file_hash,
+                    file_type,
+                    file_size as i64,
+                    m.title,
+                    m.author,
+                    m.publisher,
+                    m.subject,
+                    m.language,
+                    m.page_count.map(|n| n as i64),
+                    m.created_at,
+                    m.modified_at,
+                    m.series,
+                    m.series_index,
+                    m.right_to_left,
+                    now,
+                ],
+            )
+            .map_err(|e| BlinkerError::Database(format!("insert item: {}", e)))?;
+        self.replace_contributors(&id, &m.contributors, None)?;
+        Ok(id)
+    }
+
+    /// The file's extension, unless its contents were detected as a
+    /// different format, in which case that format's name.
+    fn infer_file_type(path: &Path, format: Option<DocumentFormat>) -> String {
+        let ext = path.extension().and_then(|e| e.to_str()).map(|s| s.to_lowercase());
+        match (format, ext) {
+            (Some(format), Some(ext)) if DocumentFormat::from_extension(&ext) == Some(format) => ext,
+            (Some(format), _) => format.as_str().to_string(),
+            (None, ext) => ext.unwrap_or_else(|| "unknown".into()),
+        }
     }
 }
 
 impl LibraryStore for LibraryDatabase {
     fn add_or_update_path(&self, path: &Path) -> Result<AddOutcome> {
-        // Normalize path to an absolute, canonical form when possible
+        if let Some(file) = self.touch_unchanged(path)? {
+            if !file.content_indexed {
+                if let Err(e) = self.retry_content(&file, path, None) {
+                    tracing::warn!("Content indexing failed for {:?}: {}", path, e);
+                }
+            }
+            return Ok(AddOutcome::Unchanged { id: file.id });
+        }
+        self.add_prepared(PreparedFile::read(path)?)
+    }
+
+    fn touch_unchanged(&self, path: &Path) -> Result<Option<UnchangedFile>> {
         let canon = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let meta = std::fs::metadata(&canon).map_err(BlinkerError::Io)?;
+        let Some(mtime) = Self::mtime_nanos(&meta) else {
+            return Ok(None);
+        };
+        let now = Self::now_secs();
+        let id: Option<String> = self.conn
+            .query_row(
+                "UPDATE item_location SET seen_at = ?4, missing_since = NULL
+                 WHERE file_path = ?1 AND file_size = ?2 AND file_mtime = ?3
+                 RETURNING item_id",
+                params![canon.to_string_lossy(), meta.len() as i64, mtime, now],
+                |row| row.get(0),
+            )
+            .optional()
+            .map_err(|e| BlinkerError::Database(format!("touch unchanged: {}", e)))?;
+        let Some(id) = id else {
+            return Ok(None);
+        };
+        let (file_hash, file_type, content_indexed) = self.conn
+            .query_row(
+                "UPDATE library_item SET indexed_at = ?2 WHERE id = ?1
+                 RETURNING file_hash, file_type, EXISTS(
+                     SELECT 1 FROM content_index_state s
+                     WHERE s.item_id = library_item.id AND s.file_hash = library_item.file_hash)",
+                params![id, now],
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+            )
+            .map_err(|e| BlinkerError::Database(format!("touch indexed_at: {}", e)))?;
+        self.sync_primary_location(&id)?;
+        Ok(Some(UnchangedFile { id, file_hash, file_type, content_indexed }))
+    }
+
+    fn retry_content(&self, file: &UnchangedFile, path: &Path, pages: Option<Result<Vec<PageText>>>) -> Result<()> {
+        let canon = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        self.refresh_content_index(&file.id, &file.file_hash, &canon, pages)
+    }
+
+    fn add_prepared(&self, file: PreparedFile) -> Result<AddOutcome> {
+        let PreparedFile { path: canon, file_size, file_mtime, file_hash, file_type, metadata: mut extracted_meta, tags, identifiers, pages, .. } = file;
         let path_str = canon.to_string_lossy().to_string();
-        let meta = std::fs::metadata(&canon).map_err(|e| BlinkerError::Io(e))?;
-        if !meta.is_file() {
-            return Err(BlinkerError::Parsing(format!("not a file: {}", path.display())));
-        }
-        let file_size = meta.len();
-        let file_hash = Self::file_hash(path)?;
-        let file_type = Self::infer_file_type(path);
-
-        // Extract metadata using MetadataExtractor
-        let extracted_meta = crate::metadata::MetadataExtractor::extract(path)
-            .unwrap_or_else(|_| {
-                // Fallback to basic metadata
-                blinker_core_common::types::Metadata {
-                    title: path.file_stem().and_then(|s| s.to_str()).unwrap_or("Untitled").to_string(),
-                    ..Default::default()
-                }
-            });
 
-        let title = extracted_meta.title;
-        let author = extracted_meta.author;
         let now = Self::now_secs();
-        // Determine if an entry exists for this file_path
-        let existing: Option<(String, String)> = self.conn
+        // The item currently located at this path, and how many paths it has
+        let at_path: Option<(String, String, i64)> = self.conn
             .query_row(
-                "SELECT id, file_hash FROM library_item WHERE file_path = ?1",
+                "SELECT i.id, i.file_hash, (SELECT COUNT(*) FROM item_location o WHERE o.item_id = i.id)
+                 FROM item_location l JOIN library_item i ON i.id = l.item_id
+                 WHERE l.file_path = ?1",
                 params![&path_str],
-                |row| Ok((row.get(0)?, row.get(1)?)),
+                |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
             )
-            .ok();
+            .optional()
+            .map_err(|e| BlinkerError::Database(format!("find location: {}", e)))?;
 
-        // If path doesn’t exist but same content hash exists, update path
+        // An item with the same content, possibly at other paths
         let by_hash: Option<String> = self.conn
             .query_row(
-                "SELECT id FROM library_item WHERE id = ?1",
+                "SELECT id FROM library_item WHERE file_hash = ?1 ORDER BY created_at LIMIT 1",
                 params![&file_hash],
                 |row| row.get(0),
             )
-            .ok();
-
-        let outcome = if let Some((existing_id, existing_hash)) = existing {
-            if existing_hash == file_hash {
-                // Unchanged metadata update: touch indexed_at
-                self.conn.execute(
-                    "UPDATE library_item SET indexed_at = ?2 WHERE id = ?1",
-                    params![existing_id, now],
-                ).map_err(|e| BlinkerError::Database(format!("touch indexed_at: {}", e)))?;
-                AddOutcome::Unchanged { id: existing_id }
-            } else {
+            .optional()
+            .map_err(|e| BlinkerError::Database(format!("find by hash: {}", e)))?;
+
+        // Location moves touch several rows; apply them together
+        let tx = self
+            .write_transaction()
+            .map_err(|e| BlinkerError::Database(format!("begin add: {}", e)))?;
+        let outcome = match (at_path, by_hash) {
+            (Some((id, hash, _)), _) if hash == file_hash => {
+                // Unchanged content: touch indexed_at, remember the current mtime
+                self.conn
+                    .execute("UPDATE library_item SET indexed_at = ?2 WHERE id = ?1", params![id, now])
+                    .map_err(|e| BlinkerError::Database(format!("touch indexed_at: {}", e)))?;
+                self.upsert_location(&id, &path_str, file_size, file_mtime)?;
+                self.sync_primary_location(&id)?;
+                AddOutcome::Unchanged { id }
+            }
+            (Some((id, _, 1)), Some(other)) => {
+                // The only copy of this item now holds content already known
+                // as another item: one item per content, so fold this one in
+                self.upsert_location(&id, &path_str, file_size, file_mtime)?;
+                self.merge_item(&id, &other)?;
+                self.conn
+                    .execute("UPDATE library_item SET indexed_at = ?2 WHERE id = ?1", params![other, now])
+                    .map_err(|e| BlinkerError::Database(format!("touch indexed_at: {}", e)))?;
+                AddOutcome::Updated { id: other }
+            }
+            (Some((id, _, 1)), None) => {
+                // The only copy of this item changed: update it in place so
+                // tags, annotations and reading state carry over
+                self.apply_overrides(&id, &mut extracted_meta)?;
                 self.conn
                     .execute(
-                        "UPDATE library_item SET file_hash=?2, file_type=?3, file_size=?4, title=?5, author=?6, modified_at=?7, indexed_at=?7 WHERE id=?1",
-                        params![existing_id, file_hash, file_type, file_size as i64, title, author, now],
+                        "UPDATE library_item SET file_hash=?2, file_type=?3, file_size=?4, indexed_at=?5 WHERE id=?1",
+                        params![id, file_hash, file_type, file_size as i64, now],
                     )
                     .map_err(|e| BlinkerError::Database(format!("update item: {}", e)))?;
-                AddOutcome::Updated { id: existing_id }
+                self.write_metadata(&id, &extracted_meta, now)?;
+                self.replace_tags(&id, crate::identifiers::FILE_SOURCE, &tags)?;
+                self.replace_identifiers(&id, crate::identifiers::FILE_SOURCE, &identifiers)?;
+                self.upsert_location(&id, &path_str, file_size, file_mtime)?;
+                self.sync_primary_location(&id)?;
+                AddOutcome::Updated { id }
+            }
+            (at_path, Some(id)) => {
+                // Same content as a known item: this path is another copy of it.
+                // A changed copy of a multi-location item leaves that item.
+                if let Some((old_id, _, _)) = at_path {
+                    self.remove_location(&path_str)?;
+                    self.sync_primary_location(&old_id)?;
+                }
+                self.upsert_location(&id, &path_str, file_size, file_mtime)?;
+                self.sync_primary_location(&id)?;
+                self.conn
+                    .execute("UPDATE library_item SET indexed_at = ?2 WHERE id = ?1", params![id, now])
+                    .map_err(|e| BlinkerError::Database(format!("touch indexed_at: {}", e)))?;
+                AddOutcome::Updated { id }
+            }
+            (at_path, None) => {
+                // New content. A changed copy of a multi-location item splits off.
+                if let Some((old_id, _, _)) = at_path {
+                    self.remove_location(&path_str)?;
+                    self.sync_primary_location(&old_id)?;
+                }
+                let id = self.insert_item(&path_str, &file_hash, &file_type, file_size, &extracted_meta, now)?;
+                self.replace_tags(&id, crate::identifiers::FILE_SOURCE, &tags)?;
+                self.replace_identifiers(&id, crate::identifiers::FILE_SOURCE, &identifiers)?;
+                self.upsert_location(&id, &path_str, file_size, file_mtime)?;
+                AddOutcome::Created { id }
             }
-        } else if let Some(existing_id) = by_hash {
-            // Same content seen at a different path; update file_path
-            self.conn
-                .execute(
-                    "UPDATE library_item SET file_path=?2, file_type=?3, file_size=?4, title=?5, author=?6, modified_at=?7, indexed_at=?7 WHERE id=?1",
-                    params![existing_id, path_str, file_type, file_size as i64, title, author, now],
-                )
-                .map_err(|e| BlinkerError::Database(format!("relink item: {}", e)))?;
-            AddOutcome::Updated { id: existing_id }
-        } else {
-            // New insert; ID uses content hash for PoC
-            let id = file_hash.clone();
-            self.conn
-                .execute(
-                    "INSERT INTO library_item (
-                        id, file_path, file_hash, file_type, file_size,
-                        title, author, publisher, subject, language, page_count,
-                        created_at, modified_at, indexed_at
-                    ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, NULL, NULL, NULL, NULL, ?8, ?8, ?8)",
-                    params![
-                        id,
-                        path_str,
-                        file_hash,
-                        file_type,
-                        file_size as i64,
-                        title,
-                        author,
-                        now,
-                    ],
-                )
-                .map_err(|e| BlinkerError::Database(format!("insert item: {}", e)))?;
-            AddOutcome::Created { id }
         };
+        tx.commit()
+            .map_err(|e| BlinkerError::Database(format!("commit add: {}", e)))?;
+
+        // Text extraction failures (e.g. PDFium unavailable) leave the item
+        // searchable by metadata; later scans retry them through `retry_content`
+        // even while the file stays unchanged.
+        if let Err(e) = self.refresh_content_index(outcome.id(), &file_hash, &canon, pages) {
+            tracing::warn!("Content indexing failed for {:?}: {}", canon, e);
+        }
 
         Ok(outcome)
     }
 
     fn get_item(&self, id: &str) -> Result<Option<LibraryItem>> {
+        let sql = format!("SELECT {}, NULL AS score FROM library_item WHERE id = ?1", ITEM_COLUMNS);
         let mut stmt = self.conn
-            .prepare("SELECT id, file_path, file_hash, file_type, file_size, title, author FROM library_item WHERE id = ?1")
+            .prepare(&sql)
             .map_err(|e| BlinkerError::Database(format!("prepare get: {}", e)))?;
-        let row = stmt.query_row(params![id], |row| {
-            let id: String = row.get(0)?;
-            let file_path: String = row.get(1)?;
-            let file_hash: String = row.get(2)?;
-            let file_type: String = row.get(3)?;
-            let file_size: i64 = row.get(4)?;
-            let title: String = row.get(5)?;
-            let author: Option<String> = row.get(6)?;
-            let metadata = blinker_core_common::types::Metadata {
-                title,
-                author,
-                ..Default::default()
-            };
-            Ok(LibraryItem {
-                id,
-                file_path: PathBuf::from(file_path),
-                file_hash,
-                file_type,
-                file_size: file_size as u64,
-                metadata,
-                tags: vec![],
-            })
-        });
-
-        match row {
+        match stmt.query_row(params![id], Self::item_from_row) {
             Ok(item) => Ok(Some(item)),
             Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
             Err(e) => Err(BlinkerError::Database(format!("get item: {}", e))),
         }
     }
 
+    fn find_by_identifier(&self, scheme: &str, value: &str) -> Result<Vec<LibraryItem>> {
+        let Some((scheme, value)) = crate::identifiers::normalize(scheme, value) else {
+            return Ok(vec![]);
+        };
+        let sql = format!(
+            "SELECT {}, NULL AS score FROM library_item \
+             WHERE id IN (SELECT item_id FROM item_identifier WHERE scheme = ?1 AND value = ?2) \
+             ORDER BY created_at, id",
+            ITEM_COLUMNS
+        );
+        let mut stmt = self.conn
+            .prepare(&sql)
+            .map_err(|e| BlinkerError::Database(format!("prepare identifier lookup: {}", e)))?;
+        let items = stmt
+            .query_map(params![scheme, value], Self::item_from_row)
+            .map_err(|e| BlinkerError::Database(format!("identifier lookup: {}", e)))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| BlinkerError::Database(format!("row: {}", e)))?;
+        Ok(items)
+    }
+
     fn query(&self, query: &LibraryQuery) -> Result<Vec<LibraryItem>> {
-        // Minimal PoC: simple SELECT with LIKE filters and optional type filter
-        let mut sql = String::from("SELECT id, file_path, file_hash, file_type, file_size, title, author FROM library_item");
         let mut clauses: Vec<String> = vec![];
-        let mut params_box: Vec<(String, String)> = vec![];
+        let mut values: Vec<Value> = vec![];
 
-        if let Some(text) = &query.text {
-            clauses.push("(title LIKE :text OR ifnull(author,'') LIKE :text)".into());
-            params_box.push((":text".into(), format!("%{}%", text)));
-        }
+        let fts = query.text.as_deref().and_then(crate::search::match_expression);
+        let mut sql = match &fts {
+            Some(expr) => {
+                values.push(Value::Text(expr.clone()));
+                format!(
+                    "SELECT {}, -fts.rank AS score FROM library_item \
+                     JOIN (SELECT item_id, bm25(library_fts, {}) AS rank FROM library_fts WHERE library_fts MATCH ?) fts \
+                     ON fts.item_id = library_item.id",
+                    ITEM_COLUMNS, FTS_WEIGHTS
+                )
+            }
+            None => format!("SELECT {}, NULL AS score FROM library_item", ITEM_COLUMNS),
+        };
         if let Some(types) = &query.file_types {
             if !types.is_empty() {
-                let placeholders: Vec<String> = (0..types.len()).map(|i| format!(":t{}", i)).collect();
-                clauses.push(format!("file_type IN ({})", placeholders.join(",")));
-                for (i, t) in types.iter().enumerate() {
-                    params_box.push((format!(":t{}", i), t.clone()));
+                clauses.push(format!("file_type IN ({})", Self::placeholders(types.len())));
+                values.extend(types.iter().map(|t| Value::Text(t.clone())));
+            }
+        }
+        if let Some(tags) = &query.tags {
+            // SQLite's lower() only folds ASCII, so fold the same way here and
+            // deduplicate so HAVING COUNT matches for TagMatch::All.
+            let mut names: Vec<String> = tags.iter().map(|t| t.trim().to_ascii_lowercase()).filter(|t| !t.is_empty()).collect();
+            names.sort();
+            names.dedup();
+            if !names.is_empty() {
+                let mut clause = format!(
+                    "id IN (SELECT it.item_id FROM item_tag it JOIN tag t ON t.id = it.tag_id \
+                     WHERE lower(t.name) IN ({})",
+                    Self::placeholders(names.len())
+                );
+                if query.tag_match == TagMatch::All {
+                    clause.push_str(&format!(" GROUP BY it.item_id HAVING COUNT(DISTINCT lower(t.name)) = {}", names.len()));
                 }
+                clause.push(')');
+                clauses.push(clause);
+                values.extend(names.into_iter().map(Value::Text));
+            }
+        }
+        if let Some(languages) = &query.languages {
+            let codes: Vec<String> = languages.iter().map(|l| l.trim().to_ascii_lowercase()).filter(|l| !l.is_empty()).collect();
+            if !codes.is_empty() {
+                let any: Vec<&str> = codes.iter().map(|_| "lower(language) = ? OR lower(language) LIKE ? || '-%'").collect();
+                clauses.push(format!("({})", any.join(" OR ")));
+                for code in codes {
+                    values.push(Value::Text(code.clone()));
+                    values.push(Value::Text(code));
+                }
+            }
+        }
+        if let Some(series) = query.series.as_deref().map(str::trim).filter(|s| !s.is_empty()) {
+            clauses.push("series = ? COLLATE NOCASE".to_string());
+            values.push(Value::Text(series.to_string()));
+        }
+        if let Some(author) = query.author.as_deref().map(str::trim).filter(|a| !a.is_empty()) {
+            let people = self.matching_contributors(author)?;
+            clauses.push(format!(
+                "id IN (SELECT item_id FROM item_contributor WHERE contributor_id IN ({}))",
+                Self::placeholders(people.len())
+            ));
+            values.extend(people.into_iter().map(Value::Text));
+        }
+        if let Some(status) = query.read_status {
+            clauses.push(match status {
+                ReadStatus::Unread => "NOT EXISTS(SELECT 1 FROM reading_state rs WHERE rs.item_id = library_item.id)",
+                ReadStatus::InProgress => "EXISTS(SELECT 1 FROM reading_state rs WHERE rs.item_id = library_item.id AND rs.progress < 1.0)",
+                ReadStatus::Finished => "EXISTS(SELECT 1 FROM reading_state rs WHERE rs.item_id = library_item.id AND rs.progress >= 1.0)",
+            }.to_string());
+        }
+        let bounded = [
+            ("file_size", query.size.min.map(|v| v as i64), query.size.max.map(|v| v as i64)),
+            ("page_count", query.page_count.min.map(|v| v as i64), query.page_count.max.map(|v| v as i64)),
+            ("created_at", query.added.min, query.added.max),
+            (LAST_OPENED, query.last_opened.min, query.last_opened.max),
+        ];
+        for (expr, min, max) in bounded {
+            if let Some(min) = min {
+                clauses.push(format!("{} >= ?", expr));
+                values.push(Value::Integer(min));
+            }
+            if let Some(max) = max {
+                clauses.push(format!("{} <= ?", expr));
+                values.push(Value::Integer(max));
             }
         }
         if !clauses.is_empty() {
             sql.push_str(" WHERE ");
             sql.push_str(&clauses.join(" AND "));
         }
-        sql.push_str(" ORDER BY title COLLATE NOCASE ASC");
-        if let Some(limit) = query.limit {
-            sql.push_str(&format!(" LIMIT {}", limit));
-        } else {
-            sql.push_str(" LIMIT 100");
+
+        let key = match query.sort {
+            SortKey::Relevance if fts.is_some() => None,
+            SortKey::Relevance | SortKey::Title => Some("title COLLATE NOCASE"),
+            SortKey::Author => Some(AUTHOR_SORT),
+            SortKey::Added => Some("created_at"),
+            SortKey::LastOpened => Some(LAST_OPENED),
+            SortKey::Size => Some("file_size"),
+            SortKey::PageCount => Some("page_count"),
+            SortKey::Series => Some("series COLLATE NOCASE"),
+        };
+        let direction = match (query.sort, query.direction) {
+            (SortKey::Relevance, _) | (_, SortDirection::Asc) => "ASC",
+            (_, SortDirection::Desc) => "DESC",
+        };
+        // Ties fall back to title then id so pages stay stable across offsets
+        match key {
+            Some(key) => sql.push_str(&format!(" ORDER BY {key} IS NULL, {key} {direction}, ")),
+            None => sql.push_str(" ORDER BY fts.rank ASC, "),
+        }
+        if query.sort == SortKey::Series {
+            sql.push_str(&format!("series_index IS NULL, series_index {direction}, "));
+        }
+        sql.push_str("title COLLATE NOCASE ASC, id ASC");
+        match (query.limit, query.offset) {
+            (Some(limit), offset) => sql.push_str(&format!(" LIMIT {} OFFSET {}", limit, offset)),
+            (None, 0) => {}
+            (None, offset) => sql.push_str(&format!(" LIMIT -1 OFFSET {}", offset)),
         }
 
         let mut stmt = self.conn
             .prepare(&sql)
             .map_err(|e| BlinkerError::Database(format!("prepare query: {}", e)))?;
+        let rows = stmt
+            .query_map(rusqlite::params_from_iter(values.iter()), Self::item_from_row)
+            .map_err(|e| BlinkerError::Database(format!("run query: {}", e)))?;
 
-        // Positional parameters vector in the same order as placeholders
-        let mut params_vec: Vec<String> = vec![];
-        if let Some(text) = &query.text { params_vec.push(format!("%{}%", text)); }
-        if let Some(types) = &query.file_types { for t in types { params_vec.push(t.clone()); } }
+        let mut out = vec![];
+        for r in rows {
+            out.push(r.map_err(|e| BlinkerError::Database(format!("row: {}", e)))?);
+        }
+        Ok(out)
+    }
 
+    fn list_tags(&self) -> Result<Vec<Tag>> {
+        let mut stmt = self.conn
+            .prepare("SELECT id, name, color, created_at FROM tag ORDER BY name COLLATE NOCASE ASC")
+            .map_err(|e| BlinkerError::Database(format!("prepare list tags: {}", e)))?;
         let rows = stmt
-            .query_map(rusqlite::params_from_iter(params_vec.iter()), |row| {
-                let id: String = row.get(0)?;
-                let file_path: String = row.get(1)?;
-                let file_hash: String = row.get(2)?;
-                let file_type: String = row.get(3)?;
-                let file_size: i64 = row.get(4)?;
-                let title: String = row.get(5)?;
-                let author: Option<String> = row.get(6)?;
-                let metadata = blinker_core_common::types::Metadata { title, author, ..Default::default() };
-                Ok(LibraryItem { id, file_path: PathBuf::from(file_path), file_hash, file_type, file_size: file_size as u64, metadata, tags: vec![] })
-            })
-            .map_err(|e| BlinkerError::Database(format!("run query: {}", e)))?;
+            .query_map([], Self::tag_from_row)
+            .map_err(|e| BlinkerError::Database(format!("list tags: {}", e)))?;
+        let mut out = vec![];
+        for r in rows {
+            out.push(r.map_err(|e| BlinkerError::Database(format!("row: {}", e)))?);
+        }
+        Ok(out)
+    }
+
+    fn create_tag(&self, name: &str, color: Option<&str>) -> Result<Tag> {
+        let name = Self::normalize_tag_name(name)?;
+        self.ensure_tag_name_free(&name, None)?;
+        let tag = Tag {
+            id: uuid::Uuid::new_v4().to_string(),
+            name,
+            color: color.map(str::to_string),
+            created_at: Self::now_secs(),
+        };
+        self.conn
+            .execute(
+                "INSERT INTO tag (id, name, color, created_at) VALUES (?1, ?2, ?3, ?4)",
+                params![tag.id, tag.name, tag.color, tag.created_at],
+            )
+            .map_err(|e| BlinkerError::Database(format!("create tag {:?}: {}", tag.name, e)))?;
+        Ok(tag)
+    }
+
+    fn rename_tag(&self, tag_id: &str, new_name: &str) -> Result<()> {
+        let name = Self::normalize_tag_name(new_name)?;
+        self.ensure_tag_name_free(&name, Some(tag_id))?;
+        let n = self.conn
+            .execute("UPDATE tag SET name = ?2 WHERE id = ?1", params![tag_id, name])
+            .map_err(|e| BlinkerError::Database(format!("rename tag {:?}: {}", name, e)))?;
+        if n == 0 {
+            return Err(BlinkerError::NotFound(format!("tag {}", tag_id)));
+        }
+        Ok(())
+    }
+
+    fn set_tag_color(&self, tag_id: &str, color: Option<&str>) -> Result<()> {
+        let n = self.conn
+            .execute("UPDATE tag SET color = ?2 WHERE id = ?1", params![tag_id, color])
+            .map_err(|e| BlinkerError::Database(format!("set tag color: {}", e)))?;
+        if n == 0 {
+            return Err(BlinkerError::NotFound(format!("tag {}", tag_id)));
+        }
+        Ok(())
+    }
+
+    fn delete_tag(&self, tag_id: &str) -> Result<()> {
+        let n = self.conn
+            .execute("DELETE FROM tag WHERE id = ?1", params![tag_id])
+            .map_err(|e| BlinkerError::Database(format!("delete tag: {}", e)))?;
+        if n == 0 {
+            return Err(BlinkerError::NotFound(format!("tag {}", tag_id)));
+        }
+        Ok(())
+    }
+
+    fn assign_tag(&self, item_id: &str, tag_id: &str) -> Result<()> {
+        let exists: bool = self.conn
+            .query_row("SELECT EXISTS(SELECT 1 FROM library_item WHERE id = ?1)", params![item_id], |row| row.get(0))
+            .map_err(|e| BlinkerError::Database(format!("assign tag: {}", e)))?;
+        if !exists {
+            return Err(BlinkerError::NotFound(format!("item {}", item_id)));
+        }
+        let exists: bool = self.conn
+            .query_row("SELECT EXISTS(SELECT 1 FROM tag WHERE id = ?1)", params![tag_id], |row| row.get(0))
+            .map_err(|e| BlinkerError::Database(format!("assign tag: {}", e)))?;
+        if !exists {
+            return Err(BlinkerError::NotFound(format!("tag {}", tag_id)));
+        }
+        self.conn
+            .execute(
+                // Assigning a tag the file already gave makes it the user's,
+                // so it outlives the front matter dropping it
+                "INSERT INTO item_tag (item_id, tag_id, created_at) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(item_id, tag_id) DO UPDATE SET source = NULL",
+                params![item_id, tag_id, Self::now_secs()],
+            )
+            .map_err(|e| BlinkerError::Database(format!("assign tag: {}", e)))?;
+        Ok(())
+    }
 
+    fn unassign_tag(&self, item_id: &str, tag_id: &str) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM item_tag WHERE item_id = ?1 AND tag_id = ?2", params![item_id, tag_id])
+            .map_err(|e| BlinkerError::Database(format!("unassign tag: {}", e)))?;
+        Ok(())
+    }
+
+    fn item_tags(&self, item_id: &str) -> Result<Vec<Tag>> {
+        let mut stmt = self.conn
+            .prepare(
+                "SELECT t.id, t.name, t.color, t.created_at FROM tag t
+                 JOIN item_tag it ON it.tag_id = t.id
+                 WHERE it.item_id = ?1
+                 ORDER BY t.name COLLATE NOCASE ASC",
+            )
+            .map_err(|e| BlinkerError::Database(format!("prepare item tags: {}", e)))?;
+        let rows = stmt
+            .query_map(params![item_id], Self::tag_from_row)
+            .map_err(|e| BlinkerError::Database(format!("item tags: {}", e)))?;
         let mut out = vec![];
         for r in rows {
             out.push(r.map_err(|e| BlinkerError::Database(format!("row: {}", e)))?);
         }
         Ok(out)
     }
+
+    fn search_content(&self, text: &str, limit: usize) -> Result<Vec<ContentHit>> {
+        self.search_content_impl(text, limit)
+    }
+
+    fn content_indexed_hashes(&self) -> Result<HashSet<String>> {
+        self.content_indexed_hashes_impl()
+    }
+
+    fn update_metadata(&self, item_id: &str, changes: &[(MetadataField, Option<String>)]) -> Result<()> {
+        let tx = self
+            .write_transaction()
+            .map_err(|e| BlinkerError::Database(format!("begin metadata update: {}", e)))?;
+        let now = Self::now_secs();
+        for (field, value) in changes {
+            self.write_override(item_id, *field, value.as_deref(), now)?;
+        }
+        tx.commit()
+            .map_err(|e| BlinkerError::Database(format!("commit metadata update: {}", e)))
+    }
+
+    fn metadata_overrides(&self, item_id: &str) -> Result<Vec<MetadataField>> {
+        let mut stmt = self.conn
+            .prepare("SELECT field FROM metadata_override WHERE item_id = ?1 ORDER BY field")
+            .map_err(|e| BlinkerError::Database(format!("prepare overrides: {}", e)))?;
+        let fields = stmt
+            .query_map(params![item_id], |row| row.get::<_, String>(0))
+            .map_err(|e| BlinkerError::Database(format!("read overrides: {}", e)))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| BlinkerError::Database(format!("row: {}", e)))?;
+        Ok(fields.iter().filter_map(|f| MetadataField::from_column(f)).collect())
+    }
+
+    fn revert_metadata(&self, item_id: &str, fields: &[MetadataField]) -> Result<()> {
+        let item = self.get_item(item_id)?
+            .ok_or_else(|| BlinkerError::NotFound(format!("item {}", item_id)))?;
+        let extracted = Self::extract_metadata(&item.file_path);
+
+        let tx = self
+            .write_transaction()
+            .map_err(|e| BlinkerError::Database(format!("begin metadata revert: {}", e)))?;
+        let now = Self::now_secs();
+        for field in fields {
+            tx.execute(
+                "DELETE FROM metadata_override WHERE item_id = ?1 AND field = ?2",
+                params![item_id, field.column()],
+            )
+            .map_err(|e| BlinkerError::Database(format!("clear override: {}", e)))?;
+            let sql = format!("UPDATE library_item SET {} = ?2, modified_at = ?3 WHERE id = ?1", field.column());
+            tx.execute(&sql, params![item_id, field.get(&extracted), now])
+                .map_err(|e| BlinkerError::Database(format!("revert {}: {}", field.column(), e)))?;
+            if *field == MetadataField::Author {
+                self.replace_contributors(item_id, &extracted.contributors, Some(ContributorRole::Author))?;
+            }
+        }
+        tx.commit()
+            .map_err(|e| BlinkerError::Database(format!("commit metadata revert: {}", e)))
+    }
+
+    fn detect_missing(&self, root: &Path) -> Result<usize> {
+        let root = std::fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+        let root_str = root.to_string_lossy().to_string();
+        let mut prefix = root_str.clone();
+        if !prefix.ends_with(std::path::MAIN_SEPARATOR) {
+            prefix.push(std::path::MAIN_SEPARATOR);
+        }
+
+        let under_root = "(file_path = ?1 OR substr(file_path, 1, length(?2)) = ?2)";
+        let sql = format!("SELECT item_id, file_path, missing_since FROM item_location WHERE {}", under_root);
+        let mut stmt = self.conn
+            .prepare(&sql)
+            .map_err(|e| BlinkerError::Database(format!("prepare missing check: {}", e)))?;
+        let locations = stmt
+            .query_map(params![root_str, prefix], |row| {
+                Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?, row.get::<_, Option<i64>>(2)?))
+            })
+            .map_err(|e| BlinkerError::Database(format!("missing check: {}", e)))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| BlinkerError::Database(format!("row: {}", e)))?;
+
+        let now = Self::now_secs();
+        let mut changed: HashSet<String> = HashSet::new();
+        for (item_id, file_path, missing_since) in locations {
+            let exists = Path::new(&file_path).is_file();
+            // Only write on transitions so missing_since keeps the first detection time
+            if exists == missing_since.is_some() {
+                self.conn
+                    .execute(
+                        "UPDATE item_location SET missing_since = ?2 WHERE file_path = ?1",
+                        params![file_path, if exists { None } else { Some(now) }],
+                    )
+                    .map_err(|e| BlinkerError::Database(format!("mark missing: {}", e)))?;
+                changed.insert(item_id);
+            }
+        }
+
+        // A vanished copy of a document that still exists elsewhere was moved
+        // or deleted on purpose; only an item's last location is kept as missing.
+        let sql = format!(
+            "DELETE FROM item_location WHERE missing_since IS NOT NULL AND {}
+               AND EXISTS(SELECT 1 FROM item_location o WHERE o.item_id = item_location.item_id AND o.missing_since IS NULL)
+             RETURNING item_id",
+            under_root
+        );
+        let mut stmt = self.conn
+            .prepare(&sql)
+            .map_err(|e| BlinkerError::Database(format!("prepare drop locations: {}", e)))?;
+        let dropped = stmt
+            .query_map(params![root_str, prefix], |row| row.get::<_, String>(0))
+            .map_err(|e| BlinkerError::Database(format!("drop locations: {}", e)))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| BlinkerError::Database(format!("row: {}", e)))?;
+        changed.extend(dropped);
+
+        for item_id in &changed {
+            self.sync_primary_location(item_id)?;
+        }
+
+        let sql = format!("SELECT COUNT(*) FROM item_location WHERE missing_since IS NOT NULL AND {}", under_root);
+        let missing: i64 = self.conn
+            .query_row(&sql, params![root_str, prefix], |row| row.get(0))
+            .map_err(|e| BlinkerError::Database(format!("count missing: {}", e)))?;
+        Ok(missing as usize)
+    }
+
+    fn missing_items(&self) -> Result<Vec<LibraryItem>> {
+        let sql = format!(
+            "SELECT {}, NULL AS score FROM library_item WHERE missing_since IS NOT NULL ORDER BY missing_since ASC",
+            ITEM_COLUMNS
+        );
+        let mut stmt = self.conn
+            .prepare(&sql)
+            .map_err(|e| BlinkerError::Database(format!("prepare missing items: {}", e)))?;
+        let items = stmt
+            .query_map([], Self::item_from_row)
+            .map_err(|e| BlinkerError::Database(format!("missing items: {}", e)))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| BlinkerError::Database(format!("row: {}", e)))?;
+        Ok(items)
+    }
+
+    fn prune_missing(&self, include_annotated: bool) -> Result<PruneReport> {
+        let tx = self
+            .write_transaction()
+            .map_err(|e| BlinkerError::Database(format!("begin prune: {}", e)))?;
+        let kept: i64 = if include_annotated {
+            0
+        } else {
+            tx.query_row(
+                "SELECT COUNT(*) FROM library_item li WHERE li.missing_since IS NOT NULL AND (
+                    EXISTS(SELECT 1 FROM annotation a WHERE a.item_id = li.id)
+                    OR EXISTS(SELECT 1 FROM reading_state r WHERE r.item_id = li.id))",
+                [],
+                |row| row.get(0),
+            )
+            .map_err(|e| BlinkerError::Database(format!("count kept: {}", e)))?
+        };
+        // Deleting the item cascades to tags, annotations and reading state
+        let sql = if include_annotated {
+            "DELETE FROM library_item WHERE missing_since IS NOT NULL"
+        } else {
+            "DELETE FROM library_item WHERE missing_since IS NOT NULL
+               AND NOT EXISTS(SELECT 1 FROM annotation a WHERE a.item_id = library_item.id)
+               AND NOT EXISTS(SELECT 1 FROM reading_state r WHERE r.item_id = library_item.id)"
+        };
+        let removed = tx
+            .execute(sql, [])
+            .map_err(|e| BlinkerError::Database(format!("prune: {}", e)))?;
+        tx.commit()
+            .map_err(|e| BlinkerError::Database(format!("commit prune: {}", e)))?;
+        Ok(PruneReport { removed, kept: kept as usize })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{add_file, library_with};
+
+    /// A database as a build at schema `version` left it.
+    fn database_at(path: &Path, version: i64) -> Connection {
+        let conn = Connection::open(path).unwrap();
+        conn.execute_batch("CREATE TABLE schema_version (version INTEGER PRIMARY KEY, applied_at INTEGER NOT NULL);")
+            .unwrap();
+        for &(v, sql) in MIGRATIONS.iter().take_while(|(v, _)| *v <= version) {
+            conn.execute_batch(sql).unwrap();
+            conn.execute("INSERT INTO schema_version (version, applied_at) VALUES (?1, 0)", params![v]).unwrap();
+        }
+        conn
+    }
+
+    fn identifiers(db: &LibraryDatabase) -> Vec<(String, String, String)> {
+        let mut stmt = db.conn
+            .prepare("SELECT item_id, scheme, value FROM item_identifier ORDER BY item_id, scheme, value")
+            .unwrap();
+        stmt.query_map([], |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn migrates_a_version_1_library() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("library.db");
+        let conn = database_at(&path, 1);
+        conn.execute_batch(
+            "INSERT INTO library_item (id, file_path, file_hash, file_type, file_size, title, author,
+                                       created_at, modified_at, indexed_at)
+             VALUES ('i1', '/books/foundation.epub', 'h1', 'epub', 10, 'Foundation', 'Isaac Asimov', 1, 1, 1);
+             INSERT INTO tag (id, name, created_at) VALUES ('t1', 'classics', 1);
+             INSERT INTO item_tag (item_id, tag_id, created_at) VALUES ('i1', 't1', 1);
+             INSERT INTO reading_state (id, item_id, current_page, total_pages, progress, last_opened)
+             VALUES ('r1', 'i1', 3, 10, 0.3, 1);",
+        )
+        .unwrap();
+        drop(conn);
+
+        let db = LibraryDatabase::new(&path).unwrap();
+        assert_eq!(db.get_schema_version().unwrap(), SCHEMA_VERSION);
+        let item = db.get_item("i1").unwrap().unwrap();
+        assert_eq!(item.metadata.title, "Foundation");
+        assert_eq!(item.tags, ["classics"]);
+        // The rebuilt full-text index covers rows from before the migrations
+        let query = LibraryQuery { text: Some("tags:classics asimov".into()), ..Default::default() };
+        assert_eq!(db.query(&query).unwrap().len(), 1);
+        let locations: i64 = db.conn
+            .query_row("SELECT COUNT(*) FROM item_location WHERE item_id = 'i1'", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(locations, 1);
+
+        // Opening again applies nothing
+        drop(db);
+        assert_eq!(LibraryDatabase::new(&path).unwrap().get_schema_version().unwrap(), SCHEMA_VERSION);
+    }
+
+    #[test]
+    fn migration_14_stores_isbns_as_isbn13() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("library.db");
+        let conn = database_at(&path, 13);
+        conn.execute_batch(
+            "INSERT INTO library_item (id, file_path, file_hash, file_type, file_size, title,
+                                       created_at, modified_at, indexed_at)
+             VALUES ('i1', '/books/a.epub', 'h1', 'epub', 10, 'A', 1, 1, 1),
+                    ('i2', '/books/b.epub', 'h2', 'epub', 10, 'B', 1, 1, 1);
+             INSERT INTO item_identifier (item_id, scheme, value, source) VALUES
+                ('i1', 'isbn', '0553293354', 'file'),
+                ('i1', 'isbn', '080442957X', 'calibre'),
+                ('i1', 'doi', '10.1000/xyz', 'text'),
+                ('i2', 'isbn', '9780306406157', 'file'),
+                ('i2', 'isbn', '0306406152', 'calibre'),
+                ('i2', 'isbn', 'not-an-isbn', 'calibre');",
+        )
+        .unwrap();
+        drop(conn);
+
+        let db = LibraryDatabase::new(&path).unwrap();
+        let row = |item: &str, scheme: &str, value: &str| (item.to_string(), scheme.to_string(), value.to_string());
+        assert_eq!(
+            identifiers(&db),
+            [
+                row("i1", "doi", "10.1000/xyz"),
+                row("i1", "isbn13", "9780553293357"),
+                row("i1", "isbn13", "9780804429573"),
+                // The ISBN-10 form of the same edition collapses into one row
+                row("i2", "isbn13", "9780306406157"),
+            ]
+        );
+    }
+
+    #[test]
+    fn refuses_a_newer_schema() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("library.db");
+        let conn = database_at(&path, SCHEMA_VERSION);
+        conn.execute("INSERT INTO schema_version (version, applied_at) VALUES (?1, 0)", params![SCHEMA_VERSION + 1])
+            .unwrap();
+        drop(conn);
+        assert!(LibraryDatabase::new(&path).is_err());
+    }
+
+    #[test]
+    fn connections_wait_for_each_other() {
+        let (dir, db, _) = library_with(&[]);
+        let path = dir.path().join("library.db");
+        let writers: Vec<_> = ["a", "b"]
+            .into_iter()
+            .map(|writer| {
+                let (path, root) = (path.clone(), dir.path().to_path_buf());
+                std::thread::spawn(move || {
+                    let db = LibraryDatabase::new(&path).unwrap();
+                    for i in 0..25 {
+                        let id = add_file(&db, &root.join(writer).join(format!("{}.md", i)), &format!("# {} {}", writer, i));
+                        db.update_metadata(&id, &[(MetadataField::Subject, Some(writer.to_string()))]).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+        assert_eq!(db.query(&LibraryQuery::default()).unwrap().len(), 50);
+    }
+
+    fn tag_names(db: &LibraryDatabase, id: &str) -> Vec<String> {
+        db.item_tags(id).unwrap().into_iter().map(|t| t.name).collect()
+    }
+
+    #[test]
+    fn front_matter_tags_follow_the_file() {
+        let (dir, db, _) = library_with(&[]);
+        let note = dir.path().join("note.md");
+        let id = add_file(&db, &note, "---\ntags: [draft, rust]\n---\nText");
+        assert_eq!(tag_names(&db, &id), ["draft", "rust"]);
+
+        // Tags assigned by hand stay, even one the file also gave
+        let starred = db.create_tag("starred", None).unwrap();
+        db.assign_tag(&id, &starred.id).unwrap();
+        let rust = db.item_tags(&id).unwrap().into_iter().find(|t| t.name == "rust").unwrap();
+        db.assign_tag(&id, &rust.id).unwrap();
+
+        let id = add_file(&db, &note, "---\ntags: [final]\n---\nText, edited");
+        assert_eq!(tag_names(&db, &id), ["final", "rust", "starred"]);
+    }
+
+    fn titles(db: &LibraryDatabase, query: &LibraryQuery) -> Vec<String> {
+        db.query(query).unwrap().into_iter().map(|i| i.metadata.title).collect()
+    }
+
+    #[test]
+    fn query_sorts_by_each_key_with_missing_values_last() {
+        use crate::ReadingStateStore;
+
+        let (_dir, db, ids) = library_with(&["b", "a", "c", "d"]);
+        let set = |sql: &str, values: [Option<i64>; 4]| {
+            for (id, value) in ids.iter().zip(values) {
+                db.conn.execute(sql, params![id, value]).unwrap();
+            }
+        };
+        set("UPDATE library_item SET created_at = ?2 WHERE id = ?1", [Some(300), Some(100), Some(200), Some(400)]);
+        set("UPDATE library_item SET file_size = ?2 WHERE id = ?1", [Some(30), Some(10), Some(20), Some(5)]);
+        set("UPDATE library_item SET page_count = ?2 WHERE id = ?1", [Some(1), Some(3), None, Some(2)]);
+        for (id, author) in ids.iter().zip(["Ursula Le Guin", "Zadie Smith", "Iain Banks"]) {
+            db.update_metadata(id, &[(MetadataField::Author, Some(author.into()))]).unwrap();
+        }
+        for (id, index) in ids.iter().zip([Some("1"), Some("2"), None]) {
+            db.update_metadata(id, &[(MetadataField::Series, Some("Saga".into())), (MetadataField::SeriesIndex, index.map(Into::into))])
+                .unwrap();
+        }
+        for (id, opened) in ids.iter().zip([20, 10]) {
+            db.record_position(id, 1, 10).unwrap();
+            db.conn.execute("UPDATE reading_state SET last_opened = ?2 WHERE item_id = ?1", params![id, opened]).unwrap();
+        }
+
+        let cases = [
+            (SortKey::Relevance, ["a", "b", "c", "d"], ["a", "b", "c", "d"]),
+            (SortKey::Title, ["a", "b", "c", "d"], ["d", "c", "b", "a"]),
+            (SortKey::Author, ["c", "b", "a", "d"], ["a", "b", "c", "d"]),
+            (SortKey::Added, ["a", "c", "b", "d"], ["d", "b", "c", "a"]),
+            (SortKey::LastOpened, ["a", "b", "c", "d"], ["b", "a", "c", "d"]),
+            (SortKey::Size, ["d", "a", "c", "b"], ["b", "c", "a", "d"]),
+            (SortKey::PageCount, ["b", "d", "a", "c"], ["a", "d", "b", "c"]),
+            (SortKey::Series, ["b", "a", "c", "d"], ["a", "b", "c", "d"]),
+        ];
+        for (sort, asc, desc) in cases {
+            for (direction, expected) in [(SortDirection::Asc, asc), (SortDirection::Desc, desc)] {
+                let query = LibraryQuery { sort, direction, ..Default::default() };
+                assert_eq!(titles(&db, &query), expected, "{:?} {:?}", sort, direction);
+            }
+        }
+    }
+
+    #[test]
+    fn query_pages_and_filters_languages() {
+        let (_dir, db, ids) = library_with(&["a", "b", "c", "d"]);
+        for (id, language) in ids.iter().zip(["en-GB", "en", "eng", "fr"]) {
+            db.update_metadata(id, &[(MetadataField::Language, Some(language.into()))]).unwrap();
+        }
+
+        let page = |offset, limit| titles(&db, &LibraryQuery { sort: SortKey::Title, offset, limit, ..Default::default() });
+        assert_eq!(page(2, None), ["c", "d"]);
+        assert_eq!(page(1, Some(2)), ["b", "c"]);
+        assert_eq!(page(4, None), Vec::<String>::new());
+
+        let languages = |codes: &[&str]| {
+            let languages = Some(codes.iter().map(|c| c.to_string()).collect());
+            titles(&db, &LibraryQuery { sort: SortKey::Title, languages, ..Default::default() })
+        };
+        assert_eq!(languages(&["EN"]), ["a", "b"]);
+        assert_eq!(languages(&["en-gb"]), ["a"]);
+        assert_eq!(languages(&["fr", "eng"]), ["c", "d"]);
+    }
+
+    #[test]
+    fn edits_survive_rescans_and_backfill_until_reverted() {
+        let (dir, db, ids) = library_with(&["draft"]);
+        let id = &ids[0];
+        db.update_metadata(id, &[
+            (MetadataField::Title, Some("Edited".into())),
+            (MetadataField::Series, Some("Notes".into())),
+        ])
+        .unwrap();
+
+        assert_eq!(add_file(&db, &dir.path().join("draft.md"), "# draft, revised"), *id);
+        assert_eq!(db.backfill_metadata().unwrap(), 1);
+        let item = db.get_item(id).unwrap().unwrap();
+        assert_eq!(item.metadata.title, "Edited");
+        assert_eq!(item.metadata.series.as_deref(), Some("Notes"));
+
+        db.revert_metadata(id, &[MetadataField::Title]).unwrap();
+        assert_eq!(db.get_item(id).unwrap().unwrap().metadata.title, "draft, revised");
+        assert_eq!(db.metadata_overrides(id).unwrap(), [MetadataField::Series]);
+    }
+
+    #[test]
+    fn missing_items_are_pruned_unless_annotated() {
+        let (dir, db, ids) = library_with(&["kept", "gone", "noted"]);
+        db.conn
+            .execute(
+                "INSERT INTO annotation (id, item_id, page, range_x, range_y, range_width, range_height,
+                                         kind, text, color, created_at, modified_at)
+                 VALUES ('note', ?1, 0, 0, 0, 1, 1, 'highlight', 'Remember this', '#ffeb3b', 0, 0)",
+                params![ids[2]],
+            )
+            .unwrap();
+        let annotations = |db: &LibraryDatabase| -> i64 {
+            db.conn.query_row("SELECT COUNT(*) FROM annotation", [], |row| row.get(0)).unwrap()
+        };
+        std::fs::remove_file(dir.path().join("gone.md")).unwrap();
+        std::fs::remove_file(dir.path().join("noted.md")).unwrap();
+
+        assert_eq!(db.detect_missing(dir.path()).unwrap(), 2);
+        assert!(db.get_item(&ids[0]).unwrap().unwrap().missing_since.is_none());
+        let mut missing: Vec<String> = db.missing_items().unwrap().into_iter().map(|i| i.metadata.title).collect();
+        missing.sort();
+        assert_eq!(missing, ["gone", "noted"]);
+
+        let report = db.prune_missing(false).unwrap();
+        assert_eq!((report.removed, report.kept), (1, 1));
+        assert!(db.get_item(&ids[1]).unwrap().is_none());
+        assert!(db.get_item(&ids[2]).unwrap().unwrap().missing_since.is_some());
+        assert_eq!(annotations(&db), 1);
+
+        let report = db.prune_missing(true).unwrap();
+        assert_eq!((report.removed, report.kept), (1, 0));
+        assert!(db.get_item(&ids[2]).unwrap().is_none());
+        assert_eq!(annotations(&db), 0);
+        assert!(db.get_item(&ids[0]).unwrap().is_some());
+    }
 }
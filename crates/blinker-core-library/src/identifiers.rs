@@ -0,0 +1,245 @@
+//! Standard identifiers of documents, such as ISBNs and DOIs.
+//!
+//! Values are stored normalized so the same identifier matches however it
+//! was written: ISBNs as ISBN-13 (`isbn13`, converted from ISBN-10), DOIs
+//! lowercase without a resolver prefix, UUIDs lowercase and hyphenated.
+
+use blinker_core_common::{BlinkerError, Result};
+use regex::Regex;
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+
+use crate::LibraryDatabase;
+
+/// Source of identifiers declared in a document's own metadata.
+pub(crate) const FILE_SOURCE: &str = "file";
+/// Source of identifiers found in a document's page text.
+pub(crate) const TEXT_SOURCE: &str = "text";
+
+/// Pages of a PDF searched for a printed ISBN or DOI. Later pages tend to
+/// cite other works.
+pub(crate) const TEXT_PAGES: usize = 3;
+
+/// One identifier of an item.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Identifier {
+    /// Lowercase scheme, e.g. `isbn13`, `doi`, `uuid`.
+    pub scheme: String,
+    pub value: String,
+    /// Where the value came from: `file` metadata, page `text`, or an
+    /// import such as `calibre`.
+    pub source: String,
+}
+
+/// Queries over item identifiers.
+pub trait IdentifierStore {
+    /// Identifiers of an item, ordered by scheme.
+    fn item_identifiers(&self, item_id: &str) -> Result<Vec<Identifier>>;
+}
+
+/// Canonical form of an identifier. ISBNs, whether given as `isbn`,
+/// `isbn10` or `isbn13`, become `isbn13` and must have a valid check digit.
+/// `None` when either part is empty or the value is malformed.
+pub(crate) fn normalize(scheme: &str, value: &str) -> Option<(String, String)> {
+    let scheme = scheme.trim().to_ascii_lowercase();
+    let value = value.trim();
+    let normalized = match scheme.as_str() {
+        "isbn" | "isbn10" | "isbn13" => return isbn13(value).map(|isbn| ("isbn13".to_string(), isbn)),
+        "doi" => doi(value)?,
+        "uuid" => uuid::Uuid::parse_str(strip_prefix_ignore_case(value, "urn:uuid:")).ok()?.hyphenated().to_string(),
+        _ => value.to_string(),
+    };
+    (!scheme.is_empty() && !normalized.is_empty()).then_some((scheme, normalized))
+}
+
+/// Work out the scheme of an identifier written on its own, as in an EPUB
+/// `dc:identifier` or a citation: `urn:isbn:…` or `isbn:…`, `urn:uuid:…`, `doi:…`, a DOI
+/// resolver URL, or a bare ISBN, DOI or UUID. `hint` is a declared scheme
+/// such as `opf:scheme="ISBN"`, used when it is one we know.
+pub fn recognize(hint: Option<&str>, text: &str) -> Option<(String, String)> {
+    let text = text.trim();
+    if let Some(hint) = hint.map(str::to_ascii_lowercase) {
+        if matches!(hint.as_str(), "isbn" | "isbn10" | "isbn13" | "doi" | "uuid") {
+            return normalize(&hint, text);
+        }
+    }
+    let lower = text.to_ascii_lowercase();
+    if let Some(isbn) = lower.strip_prefix("urn:isbn:").or_else(|| lower.strip_prefix("isbn:")) {
+        return normalize("isbn", isbn);
+    }
+    if lower.starts_with("urn:uuid:") {
+        return normalize("uuid", text);
+    }
+    if lower.starts_with("10.") || lower.starts_with("doi:") || lower.contains("doi.org/") {
+        return normalize("doi", text);
+    }
+    normalize("isbn", text).or_else(|| normalize("uuid", text))
+}
+
+/// ISBNs printed with an `ISBN` label and DOIs found in running text, such
+/// as the copyright page of a book or the header of a paper.
+pub(crate) fn find_in_text(text: &str) -> Vec<(String, String)> {
+    static ISBN: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"(?i)\bISBN(?:-?1[03])?(?:\s*\([^)]{0,20}\))?[\s:]*((?:\d[\s-]?){9}[\dX](?:[\s-]?\d){0,3})").unwrap()
+    });
+    static DOI: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"\b10\.\d{4,9}/[^\s"<>]+"#).unwrap());
+
+    let mut found: Vec<(String, String)> = vec![];
+    for caps in ISBN.captures_iter(text) {
+        let digits: String = caps[1].chars().filter(|c| c.is_ascii_alphanumeric()).collect();
+        // A 10-digit ISBN may run into a following number
+        let isbn = isbn13(&digits).or_else(|| digits.get(..10).and_then(isbn13));
+        found.extend(isbn.map(|isbn| ("isbn13".to_string(), isbn)));
+    }
+    for m in DOI.find_iter(text) {
+        let doi = m.as_str().trim_end_matches(['.', ',', ';', ':', ')', ']', '\'']);
+        found.extend(normalize("doi", doi));
+    }
+    let mut seen = std::collections::HashSet::new();
+    found.retain(|id| seen.insert(id.clone()));
+    found
+}
+
+/// ISBN-13 of an ISBN-10 or ISBN-13, ignoring separators. `None` when the
+/// check digit does not match.
+fn isbn13(value: &str) -> Option<String> {
+    let chars: Vec<char> = value.chars().filter(|c| !matches!(c, '-' | ' ')).map(|c| c.to_ascii_uppercase()).collect();
+    let digit = |c: char| c.to_digit(10);
+    let check13 = |digits: &[u32]| {
+        let sum: u32 = digits.iter().enumerate().map(|(i, d)| if i % 2 == 0 { *d } else { d * 3 }).sum();
+        (10 - sum % 10) % 10
+    };
+    match chars.len() {
+        13 => {
+            let digits = chars.iter().map(|&c| digit(c)).collect::<Option<Vec<u32>>>()?;
+            let valid = matches!(&digits[..3], [9, 7, 8] | [9, 7, 9]) && check13(&digits[..12]) == digits[12];
+            valid.then(|| chars.iter().collect())
+        }
+        10 => {
+            let body = chars[..9].iter().map(|&c| digit(c)).collect::<Option<Vec<u32>>>()?;
+            let check = if chars[9] == 'X' { 10 } else { digit(chars[9])? };
+            let sum: u32 = body.iter().zip((2..=10).rev()).map(|(d, w)| d * w).sum::<u32>() + check;
+            if !sum.is_multiple_of(11) {
+                return None;
+            }
+            let mut digits = vec![9, 7, 8];
+            digits.extend(body);
+            let check = check13(&digits);
+            Some(digits.iter().chain([&check]).map(u32::to_string).collect())
+        }
+        _ => None,
+    }
+}
+
+/// DOIs are case-insensitive; keep the `10.` prefix and suffix only.
+fn doi(value: &str) -> Option<String> {
+    let lower = value.trim().to_ascii_lowercase();
+    let start = lower.find("10.")?;
+    let prefix = &lower[..start];
+    let known = ["", "doi:", "urn:doi:", "https://doi.org/", "http://doi.org/", "https://dx.doi.org/", "http://dx.doi.org/"];
+    let doi = &lower[start..];
+    (known.contains(&prefix) && doi.contains('/')).then(|| doi.to_string())
+}
+
+fn strip_prefix_ignore_case<'a>(value: &'a str, prefix: &str) -> &'a str {
+    match value.get(..prefix.len()) {
+        Some(head) if head.eq_ignore_ascii_case(prefix) => &value[prefix.len()..],
+        _ => value,
+    }
+}
+
+impl LibraryDatabase {
+    /// Record an identifier of an item. Returns whether it was new.
+    pub(crate) fn add_identifier(&self, item_id: &str, scheme: &str, value: &str, source: &str) -> Result<bool> {
+        let Some((scheme, value)) = normalize(scheme, value) else {
+            return Ok(false);
+        };
+        let n = self.conn
+            .execute(
+                "INSERT OR IGNORE INTO item_identifier (item_id, scheme, value, source) VALUES (?1, ?2, ?3, ?4)",
+                params![item_id, scheme, value, source],
+            )
+            .map_err(|e| BlinkerError::Database(format!("add identifier: {}", e)))?;
+        Ok(n > 0)
+    }
+
+    /// Replace the identifiers an item got from `source`, leaving ones from
+    /// other sources (e.g. an import) in place.
+    pub(crate) fn replace_identifiers(&self, item_id: &str, source: &str, identifiers: &[(String, String)]) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM item_identifier WHERE item_id = ?1 AND source = ?2", params![item_id, source])
+            .map_err(|e| BlinkerError::Database(format!("clear identifiers: {}", e)))?;
+        for (scheme, value) in identifiers {
+            self.add_identifier(item_id, scheme, value, source)?;
+        }
+        Ok(())
+    }
+}
+
+impl IdentifierStore for LibraryDatabase {
+    fn item_identifiers(&self, item_id: &str) -> Result<Vec<Identifier>> {
+        let mut stmt = self.conn
+            .prepare("SELECT scheme, value, source FROM item_identifier WHERE item_id = ?1 ORDER BY scheme, value")
+            .map_err(|e| BlinkerError::Database(format!("prepare identifiers: {}", e)))?;
+        let identifiers = stmt
+            .query_map(params![item_id], |row| {
+                Ok(Identifier { scheme: row.get(0)?, value: row.get(1)?, source: row.get(2)? })
+            })
+            .map_err(|e| BlinkerError::Database(format!("read identifiers: {}", e)))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| BlinkerError::Database(format!("row: {}", e)))?;
+        Ok(identifiers)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn id(scheme: &str, value: &str) -> (String, String) {
+        (scheme.to_string(), value.to_string())
+    }
+
+    #[test]
+    fn isbn10_becomes_isbn13() {
+        assert_eq!(isbn13("0-553-29335-4").as_deref(), Some("9780553293357"));
+        assert_eq!(isbn13("0 8044 2957 x").as_deref(), Some("9780804429573"));
+        assert_eq!(isbn13("978-0-306-40615-7").as_deref(), Some("9780306406157"));
+    }
+
+    #[test]
+    fn isbn_check_digits_are_verified() {
+        assert_eq!(isbn13("0-553-29335-5"), None);
+        assert_eq!(isbn13("978-0-306-40615-8"), None);
+        // Valid check digit without the Bookland prefix
+        assert_eq!(isbn13("1234567890128"), None);
+        assert_eq!(isbn13("055329335"), None);
+    }
+
+    #[test]
+    fn normalize_canonicalizes_known_schemes() {
+        assert_eq!(normalize("ISBN10", " 0553293354 "), Some(id("isbn13", "9780553293357")));
+        assert_eq!(normalize("doi", "https://doi.org/10.1000/XYZ.123"), Some(id("doi", "10.1000/xyz.123")));
+        assert_eq!(normalize("doi", "see 10.1000/xyz"), None);
+        assert_eq!(
+            normalize("UUID", "urn:uuid:A1B2C3D4-0000-4000-8000-000000000001"),
+            Some(id("uuid", "a1b2c3d4-0000-4000-8000-000000000001"))
+        );
+        assert_eq!(normalize("calibre", " 42 "), Some(id("calibre", "42")));
+        assert_eq!(normalize("", "42"), None);
+        assert_eq!(normalize("isbn", ""), None);
+    }
+
+    #[test]
+    fn find_in_text_reads_labelled_isbns_and_dois() {
+        let text = "Copyright 1991. ISBN 0-553-29335-4 (pbk.)\n\
+                    ISBN-13 (ebook): 978-0-306-40615-7 12\n\
+                    Cite as doi:10.1000/xyz.123, or 10.1000/xyz.123.\n\
+                    Order no. 0553293354";
+        assert_eq!(
+            find_in_text(text),
+            [id("isbn13", "9780553293357"), id("isbn13", "9780306406157"), id("doi", "10.1000/xyz.123")]
+        );
+    }
+}
@@ -1,47 +1,334 @@
-/// Library management: indexing, metadata, tags, and search.
-///
-/// This crate handles:
-/// - File-system scanning and watching
-/// - BLAKE3 hashing for deduplication
-/// - SQLite database with FTS5 for search
-/// - Metadata extraction and management
+//! Library management: indexing, metadata, tags, and search.
+//!
+//! This crate handles:
+//! - File-system scanning and watching
+//! - BLAKE3 hashing for deduplication
+//! - SQLite database with FTS5 for search
+//! - Full-text indexing of document contents
+//! - Metadata extraction and management
+//! - Reading progress tracking
+//! - Manual and smart collections
+//! - Series grouping and volume order
+//! - Cover thumbnail cache
+//! - Library export and import archives
+//! - Calibre library import
+//! - Authors, editors, translators and illustrators with sort names
 
 pub mod scanner;
 pub mod database;
 pub mod metadata;
+pub mod content;
+pub mod reading_state;
+pub mod watcher;
+pub mod roots;
+pub mod locations;
+pub mod collections;
+pub mod series;
+pub mod thumbnails;
+pub mod archive;
+pub mod identifiers;
+pub mod calibre;
+pub mod contributors;
+mod search;
 
-pub use scanner::LibraryScanner;
-pub use database::LibraryDatabase;
+pub use scanner::{CancelToken, LibraryScanner, ScanProgress, ScanReport, ScanRules};
+pub use database::{LibraryDatabase, PreparedFile};
+pub use content::{ContentHit, ContentMatch};
+pub use reading_state::{ReadingEntry, ReadingState, ReadingStateStore};
+pub use watcher::{LibraryChange, LibraryWatcher};
+pub use roots::{LibraryRoot, LibraryRootStore};
+pub use locations::{DuplicateItem, ItemLocation, LocationStore};
+pub use collections::{Collection, CollectionStore};
+pub use series::{SeriesStore, SeriesSummary};
+pub use thumbnails::ThumbnailCache;
+pub use archive::{ArchiveStore, ExportReport, ImportReport, PathRemap};
+pub use identifiers::{Identifier, IdentifierStore};
+pub use calibre::{CalibreImport, CalibreReport};
+pub use contributors::{ContributorStore, ContributorSummary};
 
 use blinker_core_common::{types::Metadata, Result};
-use std::path::PathBuf;
+use blinker_core_render::PageText;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
 
 /// Representation of a library item in memory.
 #[derive(Debug, Clone)]
 pub struct LibraryItem {
     pub id: String,
+    /// Primary location. Copies at other paths are listed by
+    /// [`LocationStore::item_locations`].
     pub file_path: PathBuf,
     pub file_hash: String,
     pub file_type: String,
     pub file_size: u64,
     pub metadata: Metadata,
     pub tags: Vec<String>,
+    /// When the file was first found missing; `None` while it exists.
+    pub missing_since: Option<i64>,
+    /// Relevance of this item for a text query (higher is better); `None` when
+    /// the query had no text.
+    pub score: Option<f64>,
 }
 
-/// Query parameters for library search.
-#[derive(Debug, Clone, Default)]
+/// Metadata fields a user can edit. Edited fields are recorded as overrides
+/// and survive re-extraction when a file changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MetadataField {
+    Title,
+    Author,
+    Publisher,
+    Subject,
+    Language,
+    Series,
+    #[serde(rename = "series_index")]
+    SeriesIndex,
+}
+
+impl MetadataField {
+    /// Column in `library_item` (also the stored override name).
+    pub fn column(&self) -> &'static str {
+        match self {
+            Self::Title => "title",
+            Self::Author => "author",
+            Self::Publisher => "publisher",
+            Self::Subject => "subject",
+            Self::Language => "language",
+            Self::Series => "series",
+            Self::SeriesIndex => "series_index",
+        }
+    }
+
+    pub fn from_column(s: &str) -> Option<Self> {
+        match s {
+            "title" => Some(Self::Title),
+            "author" => Some(Self::Author),
+            "publisher" => Some(Self::Publisher),
+            "subject" => Some(Self::Subject),
+            "language" => Some(Self::Language),
+            "series" => Some(Self::Series),
+            "series_index" => Some(Self::SeriesIndex),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, meta: &Metadata) -> Option<String> {
+        match self {
+            Self::Title => Some(meta.title.clone()),
+            Self::Author => meta.author.clone(),
+            Self::Publisher => meta.publisher.clone(),
+            Self::Subject => meta.subject.clone(),
+            Self::Language => meta.language.clone(),
+            Self::Series => meta.series.clone(),
+            Self::SeriesIndex => meta.series_index.map(|i| i.to_string()),
+        }
+    }
+
+    pub fn apply(&self, meta: &mut Metadata, value: Option<String>) {
+        match self {
+            Self::Title => {
+                if let Some(v) = value {
+                    meta.title = v;
+                }
+            }
+            Self::Author => meta.set_author(value),
+            Self::Publisher => meta.publisher = value,
+            Self::Subject => meta.subject = value,
+            Self::Language => meta.language = value,
+            Self::Series => meta.series = value,
+            Self::SeriesIndex => meta.series_index = value.and_then(|v| v.parse().ok()),
+        }
+    }
+}
+
+/// A user-defined tag.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Tag {
+    pub id: String,
+    pub name: String,
+    pub color: Option<String>,
+    pub created_at: i64,
+}
+
+/// How `LibraryQuery::tags` combines multiple tag names.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TagMatch {
+    /// Item must carry every listed tag.
+    #[default]
+    All,
+    /// Item must carry at least one listed tag.
+    Any,
+}
+
+/// Key `LibraryQuery` results are ordered by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SortKey {
+    /// Best text match first when the query has text, otherwise by title.
+    /// Ignores `SortDirection`.
+    #[default]
+    Relevance,
+    Title,
+    Author,
+    /// When the item was added to the library.
+    Added,
+    LastOpened,
+    Size,
+    PageCount,
+    /// Series name, then position within the series. Items outside a
+    /// series come last.
+    Series,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortDirection {
+    #[default]
+    Asc,
+    Desc,
+}
+
+/// Inclusive bounds on a value; an unset end is open.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Bounds<T> {
+    pub min: Option<T>,
+    pub max: Option<T>,
+}
+
+impl<T> Bounds<T> {
+    pub fn is_unbounded(&self) -> bool {
+        self.min.is_none() && self.max.is_none()
+    }
+}
+
+/// Reading progress filter for `LibraryQuery`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReadStatus {
+    /// Never opened.
+    Unread,
+    /// Opened but not read to the end.
+    InProgress,
+    Finished,
+}
+
+/// Query parameters for library search. Serializable so smart collections
+/// can store one.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct LibraryQuery {
+    /// Full-text search over title, author, subject and tags. Words match as
+    /// prefixes, `"quoted phrases"` match exactly, and `title:`, `author:`,
+    /// `subject:` or `tags:` restrict the following word or phrase to one field.
     pub text: Option<String>,
     pub file_types: Option<Vec<String>>,
+    /// Tag names to filter by (case-insensitive), combined per `tag_match`.
     pub tags: Option<Vec<String>>,
+    pub tag_match: TagMatch,
+    /// Language codes (case-insensitive). `en` also matches regional
+    /// variants such as `en-GB`.
+    pub languages: Option<Vec<String>>,
+    /// File size in bytes.
+    pub size: Bounds<u64>,
+    pub page_count: Bounds<usize>,
+    /// When the item was added, in seconds since the epoch.
+    pub added: Bounds<i64>,
+    /// When the item was last opened, in seconds since the epoch. Items
+    /// never opened are excluded once either end is set.
+    pub last_opened: Bounds<i64>,
+    pub read_status: Option<ReadStatus>,
+    /// Series name (case-insensitive).
+    pub series: Option<String>,
+    /// A person credited in any role. Matches however their name is
+    /// written across files: surname first or last, in any case, with or
+    /// without accents, initials for given names, middle names left out, or
+    /// one letter off in a long surname. A surname alone matches everyone
+    /// with that surname.
+    pub author: Option<String>,
+    pub sort: SortKey,
+    /// Items without a value for the sort key come last either way.
+    pub direction: SortDirection,
+    /// Number of results to skip, for paging through a result set.
+    pub offset: usize,
+    /// Maximum number of results; `None` returns every match.
     pub limit: Option<usize>,
 }
 
 /// Store operations required by higher layers.
 pub trait LibraryStore {
-    fn add_or_update_path(&self, path: &std::path::Path) -> Result<AddOutcome>;
+    /// Index a file. Files whose size and modification time match what was
+    /// recorded are not re-read and report `Unchanged`.
+    fn add_or_update_path(&self, path: &Path) -> Result<AddOutcome>;
+    /// If `path` is indexed with its current size and modification time,
+    /// mark it as seen and return the item without reading the file.
+    fn touch_unchanged(&self, path: &Path) -> Result<Option<UnchangedFile>>;
+    /// Index the text of a file found unchanged whose text is not indexed,
+    /// e.g. because extraction failed when it was read. `pages` is used
+    /// instead of extracting when given.
+    fn retry_content(&self, file: &UnchangedFile, path: &Path, pages: Option<Result<Vec<PageText>>>) -> Result<()>;
+    /// Write a file prepared off-thread; same outcome as `add_or_update_path`.
+    fn add_prepared(&self, file: PreparedFile) -> Result<AddOutcome>;
     fn get_item(&self, id: &str) -> Result<Option<LibraryItem>>;
+    /// Items carrying an identifier, oldest first. The value is normalized
+    /// the way it is stored, so an ISBN-10 finds items recorded by ISBN-13.
+    fn find_by_identifier(&self, scheme: &str, value: &str) -> Result<Vec<LibraryItem>>;
     fn query(&self, query: &LibraryQuery) -> Result<Vec<LibraryItem>>;
+
+    fn list_tags(&self) -> Result<Vec<Tag>>;
+    fn create_tag(&self, name: &str, color: Option<&str>) -> Result<Tag>;
+    fn rename_tag(&self, tag_id: &str, new_name: &str) -> Result<()>;
+    fn set_tag_color(&self, tag_id: &str, color: Option<&str>) -> Result<()>;
+    /// Delete a tag and remove it from every item.
+    fn delete_tag(&self, tag_id: &str) -> Result<()>;
+    fn assign_tag(&self, item_id: &str, tag_id: &str) -> Result<()>;
+    fn unassign_tag(&self, item_id: &str, tag_id: &str) -> Result<()>;
+    fn item_tags(&self, item_id: &str) -> Result<Vec<Tag>>;
+
+    /// Edit metadata fields (`None` blanks a field). Edits are kept as
+    /// overrides so later re-extraction does not replace them.
+    fn update_metadata(&self, item_id: &str, changes: &[(MetadataField, Option<String>)]) -> Result<()>;
+    /// Fields of an item currently held by user overrides.
+    fn metadata_overrides(&self, item_id: &str) -> Result<Vec<MetadataField>>;
+    /// Drop overrides for `fields` and restore the values extracted from the file.
+    fn revert_metadata(&self, item_id: &str, fields: &[MetadataField]) -> Result<()>;
+
+    /// Search the text of every indexed document, best matching items first.
+    fn search_content(&self, text: &str, limit: usize) -> Result<Vec<ContentHit>>;
+    /// File hashes whose text is already indexed, so preparation can skip
+    /// extracting it again.
+    fn content_indexed_hashes(&self) -> Result<HashSet<String>>;
+
+    /// Flag locations under `root` whose files no longer exist, and clear the
+    /// flag on ones that came back. Vanished copies of items present elsewhere
+    /// are dropped; an item is missing once all its locations are. Returns the
+    /// number of locations under `root` currently missing.
+    fn detect_missing(&self, root: &Path) -> Result<usize>;
+    fn missing_items(&self) -> Result<Vec<LibraryItem>>;
+    /// Delete items flagged missing. Items with annotations or reading state
+    /// are kept unless `include_annotated` is set, in which case those are
+    /// deleted along with them.
+    fn prune_missing(&self, include_annotated: bool) -> Result<PruneReport>;
+}
+
+/// Result of [`LibraryStore::prune_missing`].
+#[derive(Debug, Clone, Default)]
+pub struct PruneReport {
+    pub removed: usize,
+    /// Missing items left in place because they carry user data.
+    pub kept: usize,
+}
+
+/// A file whose size and modification time match what was recorded.
+#[derive(Debug, Clone)]
+pub struct UnchangedFile {
+    pub id: String,
+    pub file_hash: String,
+    pub file_type: String,
+    /// Its text is in the content index. Unset after a failed extraction,
+    /// so scans retry it without re-reading the whole file.
+    pub content_indexed: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -50,3 +337,39 @@ pub enum AddOutcome {
     Updated { id: String },
     Unchanged { id: String },
 }
+
+impl AddOutcome {
+    pub fn id(&self) -> &str {
+        match self {
+            Self::Created { id } | Self::Updated { id } | Self::Unchanged { id } => id,
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test_support {
+    use std::path::Path;
+
+    use crate::{LibraryDatabase, LibraryStore};
+
+    /// A library in a fresh temporary directory holding a Markdown note
+    /// `<name>.md` titled `name` for each of `names`, with their ids in order.
+    pub(crate) fn library_with(names: &[&str]) -> (tempfile::TempDir, LibraryDatabase, Vec<String>) {
+        let dir = tempfile::tempdir().unwrap();
+        let db = LibraryDatabase::new(&dir.path().join("library.db")).unwrap();
+        let ids = names
+            .iter()
+            .map(|name| add_file(&db, &dir.path().join(format!("{}.md", name)), &format!("# {}", name)))
+            .collect();
+        (dir, db, ids)
+    }
+
+    /// Write `text` to `path` and index it, returning the item id.
+    pub(crate) fn add_file(db: &LibraryDatabase, path: &Path, text: &str) -> String {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).unwrap();
+        }
+        std::fs::write(path, text).unwrap();
+        db.add_or_update_path(path).unwrap().id().to_string()
+    }
+}
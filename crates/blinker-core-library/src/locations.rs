@@ -0,0 +1,315 @@
+//! Paths an item's file is found at. Identical copies of a document share one
+//! item with several locations instead of competing for a single path.
+
+use blinker_core_common::{BlinkerError, Result};
+use rusqlite::params;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::identifiers::{FILE_SOURCE, TEXT_SOURCE};
+use crate::{LibraryDatabase, LibraryItem, LibraryStore, MetadataField};
+
+/// One path an item was found at.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ItemLocation {
+    pub path: PathBuf,
+    pub file_size: u64,
+    /// When the file was first found missing at this path; `None` while it exists.
+    pub missing_since: Option<i64>,
+    pub added_at: i64,
+    /// Last scan that found the file here.
+    pub seen_at: i64,
+}
+
+/// An item found at more than one path.
+#[derive(Debug, Clone)]
+pub struct DuplicateItem {
+    pub item: LibraryItem,
+    /// Every location, oldest first. `item.file_path` is the primary one.
+    pub locations: Vec<ItemLocation>,
+}
+
+/// Queries over item locations.
+pub trait LocationStore {
+    fn item_locations(&self, item_id: &str) -> Result<Vec<ItemLocation>>;
+    /// Items with more than one location, most copies first.
+    fn duplicates(&self) -> Result<Vec<DuplicateItem>>;
+}
+
+impl LibraryDatabase {
+    fn location_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<ItemLocation> {
+        let file_size: i64 = row.get(1)?;
+        Ok(ItemLocation {
+            path: PathBuf::from(row.get::<_, String>(0)?),
+            file_size: file_size as u64,
+            missing_since: row.get(2)?,
+            added_at: row.get(3)?,
+            seen_at: row.get(4)?,
+        })
+    }
+
+    /// Record that `item_id` was found at `path`, taking the location over
+    /// from any other item.
+    pub(crate) fn upsert_location(&self, item_id: &str, path: &str, file_size: u64, file_mtime: Option<i64>) -> Result<()> {
+        let now = Self::now_secs();
+        self.conn
+            .execute(
+                "INSERT INTO item_location (item_id, file_path, file_size, file_mtime, added_at, seen_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?5)
+                 ON CONFLICT(file_path) DO UPDATE SET
+                    item_id = excluded.item_id,
+                    file_size = excluded.file_size,
+                    file_mtime = excluded.file_mtime,
+                    missing_since = NULL,
+                    seen_at = excluded.seen_at",
+                params![item_id, path, file_size as i64, file_mtime, now],
+            )
+            .map_err(|e| BlinkerError::Database(format!("record location: {}", e)))?;
+        Ok(())
+    }
+
+    pub(crate) fn remove_location(&self, path: &str) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM item_location WHERE file_path = ?1", params![path])
+            .map_err(|e| BlinkerError::Database(format!("remove location: {}", e)))?;
+        Ok(())
+    }
+
+    /// Fold item `from` into `into` once both hold the same content: its
+    /// locations, tags, annotations and imported identifiers move over, its
+    /// overrides and reading state replace older ones, and it takes the
+    /// place of `into` in collections holding only `from`. `from` is deleted.
+    pub(crate) fn merge_item(&self, from: &str, into: &str) -> Result<()> {
+        let run = |what: &str, sql: &str| -> Result<()> {
+            self.conn
+                .execute(sql, params![from, into])
+                .map_err(|e| BlinkerError::Database(format!("merge {}: {}", what, e)))?;
+            Ok(())
+        };
+        run("locations", "UPDATE item_location SET item_id = ?2 WHERE item_id = ?1")?;
+        run("annotations", "UPDATE annotation SET item_id = ?2 WHERE item_id = ?1")?;
+        run(
+            "tags",
+            "INSERT INTO item_tag (item_id, tag_id, created_at, source)
+             SELECT ?2, tag_id, created_at, source FROM item_tag WHERE item_id = ?1
+             ON CONFLICT(item_id, tag_id) DO UPDATE SET source = CASE WHEN excluded.source IS NULL THEN NULL ELSE source END",
+        )?;
+        let mut stmt = self.conn
+            .prepare("SELECT field, value, updated_at FROM metadata_override WHERE item_id = ?1")
+            .map_err(|e| BlinkerError::Database(format!("prepare overrides: {}", e)))?;
+        let overrides = stmt
+            .query_map(params![from], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?, row.get::<_, i64>(2)?)))
+            .map_err(|e| BlinkerError::Database(format!("read overrides: {}", e)))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| BlinkerError::Database(format!("row: {}", e)))?;
+        for (field, value, updated_at) in overrides {
+            if let Some(field) = MetadataField::from_column(&field) {
+                self.write_override_if_newer(into, field, value.as_deref(), updated_at)?;
+            }
+        }
+        run(
+            "reading state",
+            "UPDATE reading_state SET item_id = ?2
+             WHERE item_id = ?1 AND NOT EXISTS(SELECT 1 FROM reading_state WHERE item_id = ?2)",
+        )?;
+        run(
+            "reading state",
+            "UPDATE reading_state AS r SET
+                current_page = CASE WHEN f.last_opened > r.last_opened THEN f.current_page ELSE r.current_page END,
+                total_pages = CASE WHEN f.last_opened > r.last_opened THEN f.total_pages ELSE r.total_pages END,
+                progress = CASE WHEN f.last_opened > r.last_opened THEN f.progress ELSE r.progress END,
+                last_opened = MAX(r.last_opened, f.last_opened),
+                reading_time = r.reading_time + f.reading_time
+             FROM reading_state AS f WHERE r.item_id = ?2 AND f.item_id = ?1",
+        )?;
+        // Identifiers read from the old content belong to it; imported ones stay
+        self.conn
+            .execute(
+                "INSERT OR IGNORE INTO item_identifier (item_id, scheme, value, source)
+                 SELECT ?2, scheme, value, source FROM item_identifier
+                 WHERE item_id = ?1 AND source NOT IN (?3, ?4)",
+                params![from, into, FILE_SOURCE, TEXT_SOURCE],
+            )
+            .map_err(|e| BlinkerError::Database(format!("merge identifiers: {}", e)))?;
+
+        let mut stmt = self.conn
+            .prepare("SELECT collection_id FROM collection_item WHERE item_id = ?1")
+            .map_err(|e| BlinkerError::Database(format!("prepare collections: {}", e)))?;
+        let collections = stmt
+            .query_map(params![from], |row| row.get::<_, String>(0))
+            .map_err(|e| BlinkerError::Database(format!("collections: {}", e)))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| BlinkerError::Database(format!("row: {}", e)))?;
+        for collection in collections {
+            let moved = self.conn
+                .execute(
+                    "UPDATE collection_item SET item_id = ?3 WHERE collection_id = ?1 AND item_id = ?2
+                       AND NOT EXISTS(SELECT 1 FROM collection_item WHERE collection_id = ?1 AND item_id = ?3)",
+                    params![collection, from, into],
+                )
+                .map_err(|e| BlinkerError::Database(format!("merge collection: {}", e)))?;
+            if moved == 0 {
+                self.remove_member(&collection, from)?;
+            }
+        }
+
+        run("item", "DELETE FROM library_item WHERE id = ?1 AND id != ?2")?;
+        self.sync_primary_location(into)
+    }
+
+    /// Point `library_item.file_path` at a present location (keeping the
+    /// current one while it exists) and flag the item missing only when none
+    /// of its locations exist.
+    pub(crate) fn sync_primary_location(&self, item_id: &str) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE library_item SET
+                    file_path = COALESCE(
+                        (SELECT l.file_path FROM item_location l WHERE l.item_id = ?1
+                         ORDER BY l.missing_since IS NOT NULL,
+                                  l.file_path != (SELECT i.file_path FROM library_item i WHERE i.id = ?1),
+                                  l.id
+                         LIMIT 1),
+                        file_path),
+                    missing_since = CASE
+                        WHEN EXISTS(SELECT 1 FROM item_location l WHERE l.item_id = ?1 AND l.missing_since IS NULL) THEN NULL
+                        ELSE COALESCE(missing_since, ?2)
+                    END
+                 WHERE id = ?1",
+                params![item_id, Self::now_secs()],
+            )
+            .map_err(|e| BlinkerError::Database(format!("sync primary location: {}", e)))?;
+        Ok(())
+    }
+}
+
+impl LocationStore for LibraryDatabase {
+    fn item_locations(&self, item_id: &str) -> Result<Vec<ItemLocation>> {
+        let mut stmt = self.conn
+            .prepare(
+                "SELECT file_path, file_size, missing_since, added_at, seen_at
+                 FROM item_location WHERE item_id = ?1 ORDER BY added_at, id",
+            )
+            .map_err(|e| BlinkerError::Database(format!("prepare locations: {}", e)))?;
+        let locations = stmt
+            .query_map(params![item_id], Self::location_from_row)
+            .map_err(|e| BlinkerError::Database(format!("locations: {}", e)))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| BlinkerError::Database(format!("row: {}", e)))?;
+        Ok(locations)
+    }
+
+    fn duplicates(&self) -> Result<Vec<DuplicateItem>> {
+        let mut stmt = self.conn
+            .prepare(
+                "SELECT item_id FROM item_location GROUP BY item_id HAVING COUNT(*) > 1
+                 ORDER BY COUNT(*) DESC, item_id",
+            )
+            .map_err(|e| BlinkerError::Database(format!("prepare duplicates: {}", e)))?;
+        let ids = stmt
+            .query_map([], |row| row.get::<_, String>(0))
+            .map_err(|e| BlinkerError::Database(format!("duplicates: {}", e)))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| BlinkerError::Database(format!("row: {}", e)))?;
+
+        let mut out = Vec::with_capacity(ids.len());
+        for id in ids {
+            if let Some(item) = self.get_item(&id)? {
+                out.push(DuplicateItem { locations: self.item_locations(&id)?, item });
+            }
+        }
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::library_with;
+    use crate::{AddOutcome, CollectionStore, PreparedFile};
+
+    fn tag_names(db: &LibraryDatabase, id: &str) -> Vec<String> {
+        db.item_tags(id).unwrap().into_iter().map(|t| t.name).collect()
+    }
+
+    #[test]
+    fn copies_share_one_item() {
+        let (dir, db, ids) = library_with(&["a"]);
+        let original = dir.path().join("a.md");
+        let copy = dir.path().join("copies").join("a.md");
+        std::fs::create_dir_all(copy.parent().unwrap()).unwrap();
+        std::fs::copy(&original, &copy).unwrap();
+
+        let outcome = db.add_or_update_path(&copy).unwrap();
+        assert!(matches!(&outcome, AddOutcome::Updated { id } if *id == ids[0]));
+        let duplicates = db.duplicates().unwrap();
+        assert_eq!(duplicates.len(), 1);
+        assert_eq!(duplicates[0].item.id, ids[0]);
+        assert_eq!(duplicates[0].locations.len(), 2);
+
+        // Deleting one copy drops that location and leaves the item at the other
+        std::fs::remove_file(&original).unwrap();
+        db.detect_missing(dir.path()).unwrap();
+        let item = db.get_item(&ids[0]).unwrap().unwrap();
+        assert_eq!(item.missing_since, None);
+        assert_eq!(item.file_path, std::fs::canonicalize(&copy).unwrap());
+        let paths: Vec<_> = db.item_locations(&ids[0]).unwrap().into_iter().map(|l| l.path).collect();
+        assert_eq!(paths, [item.file_path]);
+        assert!(db.duplicates().unwrap().is_empty());
+    }
+
+    #[test]
+    fn changed_copy_splits_off() {
+        let (dir, db, ids) = library_with(&["a"]);
+        let copy = dir.path().join("b.md");
+        std::fs::copy(dir.path().join("a.md"), &copy).unwrap();
+        db.add_or_update_path(&copy).unwrap();
+
+        std::fs::write(&copy, "# b, edited").unwrap();
+        let outcome = db.add_prepared(PreparedFile::read(&copy).unwrap()).unwrap();
+        let AddOutcome::Created { id } = outcome else {
+            panic!("expected a new item, got {:?}", outcome);
+        };
+        assert_ne!(id, ids[0]);
+        assert_eq!(db.item_locations(&ids[0]).unwrap().len(), 1);
+        assert_eq!(db.item_locations(&id).unwrap().len(), 1);
+        assert!(db.duplicates().unwrap().is_empty());
+    }
+
+    #[test]
+    fn merge_keeps_newer_overrides_and_all_tags_and_collections() {
+        let (dir, db, ids) = library_with(&["a", "b"]);
+        let (a, b) = (&ids[0], &ids[1]);
+        db.write_override(a, MetadataField::Title, Some("Title from a"), 300).unwrap();
+        db.write_override(b, MetadataField::Title, Some("Title from b"), 200).unwrap();
+        db.write_override(a, MetadataField::Publisher, Some("Publisher from a"), 100).unwrap();
+        db.write_override(b, MetadataField::Publisher, Some("Publisher from b"), 400).unwrap();
+        for (name, items) in [("x", vec![a]), ("y", vec![b]), ("z", vec![a, b])] {
+            let tag = db.create_tag(name, None).unwrap();
+            for id in items {
+                db.assign_tag(id, &tag.id).unwrap();
+            }
+        }
+        let only_a = db.create_collection("Only a").unwrap();
+        let both = db.create_collection("Both").unwrap();
+        db.add_to_collection(&only_a.id, a, None).unwrap();
+        db.add_to_collection(&both.id, a, None).unwrap();
+        db.add_to_collection(&both.id, b, None).unwrap();
+
+        // a's only file now holds b's content, so a folds into b
+        let path = dir.path().join("a.md");
+        std::fs::write(&path, "# b").unwrap();
+        let outcome = db.add_prepared(PreparedFile::read(&path).unwrap()).unwrap();
+        assert!(matches!(&outcome, AddOutcome::Updated { id } if id == b));
+
+        assert!(db.get_item(a).unwrap().is_none());
+        let merged = db.get_item(b).unwrap().unwrap();
+        assert_eq!(merged.metadata.title, "Title from a");
+        assert_eq!(merged.metadata.publisher.as_deref(), Some("Publisher from b"));
+        assert_eq!(tag_names(&db, b), ["x", "y", "z"]);
+        let collections: Vec<String> = db.item_collections(b).unwrap().into_iter().map(|c| c.name).collect();
+        assert_eq!(collections, ["Both", "Only a"]);
+        assert_eq!(db.collection_items(&both.id).unwrap().len(), 1);
+        assert_eq!(db.item_locations(b).unwrap().len(), 2);
+    }
+}
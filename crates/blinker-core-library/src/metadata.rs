@@ -1,21 +1,216 @@
-use blinker_core_common::{types::Metadata, types::DocumentFormat, Result, BlinkerError};
+use blinker_core_common::types::{Contributor, ContributorRole, DocumentFormat, Metadata};
+use blinker_core_common::Result;
+#[cfg(any(feature = "pdf-metadata", feature = "epub-metadata"))]
+use blinker_core_common::BlinkerError;
+use blinker_core_render::{format, rar};
+use regex::Regex;
+use std::io::Read;
 use std::path::Path;
+use std::sync::LazyLock;
+
+/// Largest container entry (OPF, ComicInfo.xml) read for metadata.
+const MAX_ENTRY_BYTES: u64 = 4 * 1024 * 1024;
+/// How much of a text document is searched for front matter and a heading.
+const MAX_HEADER_BYTES: u64 = 64 * 1024;
 
 pub struct MetadataExtractor;
 
 impl MetadataExtractor {
-    /// Extract metadata from a file based on its type
+    /// Extract metadata from a file based on its detected format
     pub fn extract(path: &Path) -> Result<Metadata> {
-        let ext = path
-            .extension()
-            .and_then(|e| e.to_str())
-            .ok_or_else(|| BlinkerError::Parsing("Missing file extension".into()))?;
+        Self::extract_as(path, format::detect(path)?)
+    }
+
+    /// Extract metadata from a file whose format is already known
+    pub fn extract_as(path: &Path, format: DocumentFormat) -> Result<Metadata> {
+        let mut meta = match format {
+            DocumentFormat::Pdf => Self::extract_pdf(path),
+            DocumentFormat::Epub => Self::extract_epub(path),
+            DocumentFormat::Cbz | DocumentFormat::Cbr => Self::extract_comic(path),
+            DocumentFormat::Txt | DocumentFormat::Markdown => Self::extract_text(path, format),
+        }?;
+
+        if meta.contributors.is_empty() && format == DocumentFormat::Epub {
+            meta.contributors = Self::epub_contributors(path).unwrap_or_default();
+            meta.author = Metadata::author_display(&meta.contributors).or(meta.author);
+        }
+        // Keep the author string and the author contributors in step
+        if meta.contributors.is_empty() {
+            if let Some(author) = &meta.author {
+                meta.contributors = Contributor::split(author, ContributorRole::Author);
+            }
+        } else if meta.author.is_none() {
+            meta.author = Metadata::author_display(&meta.contributors);
+        }
+
+        if meta.series.is_none() {
+            let embedded = match format {
+                DocumentFormat::Epub => Self::epub_series(path),
+                _ => None,
+            };
+            if let Some((series, index)) = embedded.or_else(|| Self::series_from_file_name(path, format)) {
+                meta.series = Some(series);
+                meta.series_index = index;
+            }
+        }
+        Ok(meta)
+    }
+
+    /// Tags and standard identifiers a document declares for itself. Tags
+    /// are the `tags` (or `keywords`) of Markdown and text front matter, or
+    /// a PDF's keywords. Identifiers, as `(scheme, value)`, are the ISBNs,
+    /// DOIs and UUIDs among an EPUB's `dc:identifier`s, or those in a PDF's
+    /// Info dictionary and XMP packet; PDFs are also searched for printed
+    /// ones when their text is indexed.
+    pub fn extract_tags_and_identifiers(path: &Path, format: DocumentFormat) -> (Vec<String>, Vec<(String, String)>) {
+        match format {
+            DocumentFormat::Txt | DocumentFormat::Markdown => {
+                let tags = read_header(path).and_then(|text| FrontMatter::parse(&text).map(|(fm, _)| fm.tags));
+                (tags.unwrap_or_default(), vec![])
+            }
+            DocumentFormat::Epub => (vec![], Self::epub_identifiers(path).unwrap_or_default()),
+            DocumentFormat::Pdf => PdfInfo::read(path).map(|info| (info.keywords, info.identifiers)).unwrap_or_default(),
+            _ => Default::default(),
+        }
+    }
+
+    /// `dc:identifier`s of the OPF package, typed by an EPUB 2 `opf:scheme`
+    /// or recognised from the value.
+    fn epub_identifiers(path: &Path) -> Option<Vec<(String, String)>> {
+        const OPF: &str = "http://www.idpf.org/2007/opf";
+        let opf = read_opf(path)?;
+        let opf = parse_xml(&opf)?;
+        let identifiers = opf
+            .descendants()
+            .filter(|n| n.tag_name().name() == "identifier")
+            .filter_map(|n| {
+                let hint = n.attribute((OPF, "scheme")).or_else(|| n.attribute("scheme"));
+                crate::identifiers::recognize(hint, n.text()?)
+            })
+            .collect();
+        Some(identifiers)
+    }
+
+    /// `dc:creator`s and `dc:contributor`s of the OPF package with their
+    /// roles and sort names, from EPUB 2 `opf:role`/`opf:file-as` attributes
+    /// or EPUB 3 refining `meta`s. Creators without a role are authors;
+    /// contributors without one, and roles not modelled, are left out.
+    fn epub_contributors(path: &Path) -> Option<Vec<Contributor>> {
+        const DC: &str = "http://purl.org/dc/elements/1.1/";
+        const OPF: &str = "http://www.idpf.org/2007/opf";
+        let opf = read_opf(path)?;
+        let opf = parse_xml(&opf)?;
+        let metas: Vec<_> = opf.descendants().filter(|n| n.has_tag_name("meta")).collect();
+
+        let contributors = opf
+            .descendants()
+            .filter(|n| n.has_tag_name((DC, "creator")) || n.has_tag_name((DC, "contributor")))
+            .filter_map(|n| {
+                let name = n.text().map(str::trim).filter(|t| !t.is_empty())?;
+                let refines = n.attribute("id").map(|id| format!("#{}", id));
+                let refinement = |property: &str| {
+                    metas
+                        .iter()
+                        .find(|m| m.attribute("property") == Some(property) && m.attribute("refines") == refines.as_deref())
+                        .and_then(|m| m.text())
+                        .map(str::trim)
+                };
+                let role = match n.attribute((OPF, "role")).or_else(|| refinement("role")) {
+                    Some(role) => ContributorRole::parse(role)?,
+                    None if n.has_tag_name((DC, "creator")) => ContributorRole::Author,
+                    None => return None,
+                };
+                let sort_name = n.attribute((OPF, "file-as")).or_else(|| refinement("file-as"));
+                Some(Contributor {
+                    sort_name: sort_name.map(str::trim).filter(|s| !s.is_empty()).map(str::to_string),
+                    ..Contributor::new(name, role)
+                })
+            })
+            .collect();
+        Some(contributors)
+    }
+
+    /// Series from the OPF package: calibre's `calibre:series` meta, or an
+    /// EPUB 3 `belongs-to-collection` with its `group-position`.
+    fn epub_series(path: &Path) -> Option<(String, Option<f64>)> {
+        let opf = read_opf(path)?;
+        let opf = parse_xml(&opf)?;
+        let metas: Vec<_> = opf.descendants().filter(|n| n.has_tag_name("meta")).collect();
+
+        let calibre = |name: &str| {
+            metas
+                .iter()
+                .find(|n| n.attribute("name") == Some(name))
+                .and_then(|n| n.attribute("content"))
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+        };
+        if let Some(series) = calibre("calibre:series") {
+            let index = calibre("calibre:series_index").and_then(|i| i.parse().ok());
+            return Some((series.to_string(), index));
+        }
+
+        // EPUB 3: collections are refined by id; skip ones typed as something
+        // other than a series (e.g. `set`)
+        metas
+            .iter()
+            .filter(|n| n.attribute("property") == Some("belongs-to-collection"))
+            .find_map(|collection| {
+                let name = collection.text().map(str::trim).filter(|t| !t.is_empty())?;
+                let refines = collection.attribute("id").map(|id| format!("#{}", id));
+                let refinement = |property: &str| {
+                    metas
+                        .iter()
+                        .find(|n| n.attribute("property") == Some(property) && n.attribute("refines") == refines.as_deref())
+                        .and_then(|n| n.text())
+                        .map(str::trim)
+                };
+                if refinement("collection-type").is_some_and(|t| t != "series") {
+                    return None;
+                }
+                let index = refinement("group-position").and_then(|i| i.parse().ok());
+                Some((name.to_string(), index))
+            })
+    }
+
+    /// Series from names like `Saga v03`, `Dune Book 2`, `Saga #12` or
+    /// `Discworld - 03 - Equal Rites`. Loose markers (`v3`, `vol 3`, `book 3`)
+    /// are only read in comic and EPUB names; other documents need an
+    /// explicit `vol. 3`, `volume 3`, `no. 3` or `#3`, so `paper_v2.pdf` stays
+    /// a paper. A bare trailing number (`Saga 003`) is only taken as an issue
+    /// number for comics.
+    fn series_from_file_name(path: &Path, format: DocumentFormat) -> Option<(String, Option<f64>)> {
+        // Bracketed tags such as `(2014)` or `[Digital]`
+        static TAGS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\s*[(\[][^)\]]*[)\]]").unwrap());
+        static MARKED: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(r"(?i)^(?P<series>.+?)[\s.,-]+(?:vol(?:ume)?\.?\s*|v|book\s*|#\s*|no\.\s*|tome\s*)(?P<index>\d+(?:\.\d+)?)\b").unwrap()
+        });
+        static EXPLICIT: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(r"(?i)^(?P<series>.+?)[\s.,-]+(?:vol\.\s*|volume\s+|#\s*|no\.\s*)(?P<index>\d+(?:\.\d+)?)\b").unwrap()
+        });
+        // `Series - 03 - Title` or `Series 03 - Title`; the number must follow
+        // a dash or be zero-padded so `Catch 22 - Joseph Heller` is left alone
+        static BEFORE_TITLE: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(r"^(?P<series>.+?)\s+(?:-\s+(?P<index>\d{1,3}(?:\.\d+)?)|(?P<padded>0\d{1,2}(?:\.\d+)?))\s+-\s+\S").unwrap()
+        });
+        static TRAILING: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(?P<series>.*\D)\s+(?P<index>\d{1,3}(?:\.\d+)?)$").unwrap());
+
+        let stem = path.file_stem()?.to_str()?.replace('_', " ");
+        let stem = TAGS.replace_all(&stem, "");
+        let stem = stem.trim();
 
-        match DocumentFormat::from_extension(ext) {
-            Some(DocumentFormat::Pdf) => Self::extract_pdf(path),
-            Some(DocumentFormat::Epub) => Self::extract_epub(path),
-            _ => Self::extract_basic(path),
+        let is_comic = matches!(format, DocumentFormat::Cbz | DocumentFormat::Cbr);
+        let markers = if is_comic || format == DocumentFormat::Epub { &MARKED } else { &EXPLICIT };
+        let caps = markers
+            .captures(stem)
+            .or_else(|| BEFORE_TITLE.captures(stem))
+            .or_else(|| is_comic.then(|| TRAILING.captures(stem)).flatten())?;
+        let series = caps["series"].trim_end_matches(|c: char| c.is_whitespace() || "-.,".contains(c));
+        if !series.chars().any(char::is_alphabetic) {
+            return None;
         }
+        let index = caps.name("index").or_else(|| caps.name("padded")).and_then(|i| i.as_str().parse().ok());
+        Some((series.to_string(), index))
     }
 
     /// Extract basic metadata from filename
@@ -32,9 +227,75 @@ impl MetadataExtractor {
         })
     }
 
-    /// Extract metadata from PDF using PDFium
+    /// Metadata of a comic archive from its ComicInfo.xml, if it has one.
+    /// The title falls back to `Series #Number`, then the file name.
+    fn extract_comic(path: &Path) -> Result<Metadata> {
+        let mut meta = Self::extract_basic(path)?;
+        let Some(info) = ComicInfo::read(path) else {
+            return Ok(meta);
+        };
+
+        let issue_title = info.series.as_ref().map(|series| match &info.number {
+            Some(number) => format!("{} #{}", series, number),
+            None => series.clone(),
+        });
+        if let Some(title) = info.title.clone().or(issue_title) {
+            meta.title = title;
+        }
+        meta.contributors = info
+            .credits
+            .iter()
+            .filter_map(|(role, name)| Some(Contributor::new(name, ContributorRole::parse(role)?)))
+            .collect();
+        meta.author = Metadata::author_display(&meta.contributors);
+        meta.publisher = info.publisher.clone();
+        meta.subject = info.genre.clone();
+        meta.language = info.language.clone();
+        meta.created_at = info.published_at();
+        meta.right_to_left = info.right_to_left;
+        meta.series_index = info.series.as_ref().and(info.series_index());
+        meta.series = info.series;
+        Ok(meta)
+    }
+
+    /// Metadata of a text document from its front matter. Markdown without a
+    /// front matter title is named after its first `#` heading.
+    fn extract_text(path: &Path, format: DocumentFormat) -> Result<Metadata> {
+        let mut meta = Self::extract_basic(path)?;
+        let Some(text) = read_header(path) else {
+            return Ok(meta);
+        };
+        let (front, body) = FrontMatter::parse(&text).unwrap_or((FrontMatter::default(), text.as_str()));
+
+        let heading = || (format == DocumentFormat::Markdown).then(|| first_heading(body)).flatten();
+        if let Some(title) = front.title.or_else(heading) {
+            meta.title = title;
+        }
+        meta.contributors = front.authors.iter().map(|a| Contributor::new(a, ContributorRole::Author)).collect();
+        meta.author = Metadata::author_display(&meta.contributors);
+        meta.subject = front.subject;
+        meta.language = front.language;
+        meta.created_at = front.date;
+        Ok(meta)
+    }
+
+    /// Extract metadata from PDF using PDFium, falling back to reading the
+    /// file structure when PDFium cannot be loaded or cannot open the file
     #[cfg(feature = "pdf-metadata")]
     fn extract_pdf(path: &Path) -> Result<Metadata> {
+        Self::extract_pdfium(path).or_else(|e| {
+            tracing::debug!("PDFium unavailable for {:?} ({}), reading the PDF structure", path, e);
+            Self::extract_pdf_structure(path)
+        })
+    }
+
+    #[cfg(not(feature = "pdf-metadata"))]
+    fn extract_pdf(path: &Path) -> Result<Metadata> {
+        Self::extract_pdf_structure(path)
+    }
+
+    #[cfg(feature = "pdf-metadata")]
+    fn extract_pdfium(path: &Path) -> Result<Metadata> {
         use pdfium_render::prelude::*;
 
         tracing::debug!("Extracting PDF metadata from {:?}", path);
@@ -74,9 +335,24 @@ impl MetadataExtractor {
         })
     }
 
-    #[cfg(not(feature = "pdf-metadata"))]
-    fn extract_pdf(path: &Path) -> Result<Metadata> {
-        Self::extract_basic(path)
+    /// Metadata of a PDF from its Info dictionary and XMP packet, without
+    /// PDFium. An unparseable file keeps its file name as the title.
+    fn extract_pdf_structure(path: &Path) -> Result<Metadata> {
+        let mut meta = Self::extract_basic(path)?;
+        let Some(info) = PdfInfo::read(path) else {
+            return Ok(meta);
+        };
+
+        if let Some(title) = info.title {
+            meta.title = title;
+        }
+        meta.contributors = info.authors.iter().map(|a| Contributor::new(a, ContributorRole::Author)).collect();
+        meta.author = Metadata::author_display(&meta.contributors);
+        meta.subject = info.subject;
+        meta.language = info.language;
+        meta.created_at = info.created_at;
+        meta.page_count = info.page_count;
+        Ok(meta)
     }
 
     /// Extract metadata from EPUB
@@ -115,3 +391,735 @@ impl MetadataExtractor {
         Self::extract_basic(path)
     }
 }
+
+/// Metadata from a comic archive's `ComicInfo.xml` (the ComicRack schema).
+/// RAR archives are read too, as long as the file is stored uncompressed.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ComicInfo {
+    pub title: Option<String>,
+    pub series: Option<String>,
+    /// Issue number as written, e.g. `12` or `1.5`.
+    pub number: Option<String>,
+    pub volume: Option<String>,
+    pub summary: Option<String>,
+    pub genre: Option<String>,
+    pub publisher: Option<String>,
+    pub year: Option<i32>,
+    pub month: Option<u32>,
+    pub day: Option<u32>,
+    /// `LanguageISO`, e.g. `en`.
+    pub language: Option<String>,
+    /// People credited, as `(role, name)` with the element name as role in
+    /// lowercase: `writer`, `penciller`, `inker`, `colorist`, `letterer`,
+    /// `coverartist` or `editor`.
+    pub credits: Vec<(String, String)>,
+    /// `Manga` is `YesAndRightToLeft`. A plain `Yes` only marks the book as
+    /// manga without stating its direction.
+    pub right_to_left: bool,
+}
+
+impl ComicInfo {
+    const CREDIT_ROLES: [&'static str; 7] = ["Writer", "Penciller", "Inker", "Colorist", "Letterer", "CoverArtist", "Editor"];
+
+    /// Read `ComicInfo.xml` from a comic archive. The file is matched
+    /// case-insensitively and may sit in a folder; one at the top wins.
+    /// In RAR archives it is only read when stored uncompressed.
+    pub fn read(path: &Path) -> Option<Self> {
+        let xml = Self::read_zip(path).or_else(|| Self::read_rar(path))?;
+        Self::parse(&xml)
+    }
+
+    fn is_comic_info(name: &str) -> bool {
+        name.rsplit('/').next().is_some_and(|f| f.eq_ignore_ascii_case("ComicInfo.xml"))
+    }
+
+    fn read_zip(path: &Path) -> Option<String> {
+        let file = std::fs::File::open(path).ok()?;
+        let mut archive = zip::ZipArchive::new(file).ok()?;
+        let name = archive
+            .file_names()
+            .filter(|n| Self::is_comic_info(n))
+            .min_by_key(|n| n.matches('/').count())?
+            .to_string();
+        let entry = archive.by_name(&name).ok()?;
+        let mut xml = String::new();
+        entry.take(MAX_ENTRY_BYTES).read_to_string(&mut xml).ok()?;
+        Some(xml)
+    }
+
+    fn read_rar(path: &Path) -> Option<String> {
+        let entries = rar::entries(path).map_err(|e| tracing::debug!("Unreadable RAR {}: {}", path.display(), e)).ok()?;
+        let entry = entries
+            .iter()
+            .filter(|e| !e.is_dir && Self::is_comic_info(&e.name))
+            .min_by_key(|e| e.name.matches('/').count())?;
+        let bytes = rar::read_entry(path, entry, MAX_ENTRY_BYTES)
+            .map_err(|e| tracing::debug!("Skipping ComicInfo.xml in {}: {}", path.display(), e))
+            .ok()?;
+        String::from_utf8(bytes).ok()
+    }
+
+    pub fn parse(xml: &str) -> Option<Self> {
+        let doc = parse_xml(xml)?;
+        let root = doc.root_element();
+        if !root.has_tag_name("ComicInfo") {
+            return None;
+        }
+        let field = |name: &str| {
+            root.children()
+                .find(|n| n.has_tag_name(name))
+                .and_then(|n| n.text())
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .map(str::to_string)
+        };
+        // Unset numbers are written as -1 by some taggers
+        let positive = |name: &str| field(name).and_then(|v| v.parse::<i64>().ok()).filter(|n| *n > 0);
+
+        let credits = Self::CREDIT_ROLES
+            .iter()
+            .flat_map(|role| {
+                let names = field(role).unwrap_or_default();
+                names
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|n| !n.is_empty())
+                    .map(|n| (role.to_ascii_lowercase(), n.to_string()))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+
+        Some(Self {
+            title: field("Title"),
+            series: field("Series"),
+            number: field("Number"),
+            volume: field("Volume"),
+            summary: field("Summary"),
+            genre: field("Genre"),
+            publisher: field("Publisher"),
+            year: positive("Year").and_then(|y| i32::try_from(y).ok()),
+            month: positive("Month").and_then(|m| u32::try_from(m).ok()).filter(|m| *m <= 12),
+            day: positive("Day").and_then(|d| u32::try_from(d).ok()).filter(|d| *d <= 31),
+            language: field("LanguageISO"),
+            credits,
+            right_to_left: field("Manga").is_some_and(|m| m.eq_ignore_ascii_case("YesAndRightToLeft")),
+        })
+    }
+
+    /// Position in the series: `Number`, falling back to `Volume`.
+    pub fn series_index(&self) -> Option<f64> {
+        self.number.as_deref().or(self.volume.as_deref()).and_then(|i| i.parse().ok())
+    }
+
+    /// Publication date at midnight UTC in seconds since the epoch, from
+    /// `Year` and, when set, `Month` and `Day`.
+    pub fn published_at(&self) -> Option<i64> {
+        Some(epoch_seconds(i64::from(self.year?), self.month.unwrap_or(1), self.day.unwrap_or(1)))
+    }
+}
+
+/// Metadata from the front matter of a Markdown or text document: a YAML
+/// block between `---` lines or a TOML block between `+++` lines.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FrontMatter {
+    pub title: Option<String>,
+    /// `author`, `authors` or `creator`, as one name or a list.
+    pub authors: Vec<String>,
+    /// `tags` or `keywords`, as a list or a comma-separated string.
+    pub tags: Vec<String>,
+    /// `date` at midnight UTC in seconds since the epoch.
+    pub date: Option<i64>,
+    /// `lang` or `language`.
+    pub language: Option<String>,
+    /// `subject` or `description`.
+    pub subject: Option<String>,
+}
+
+/// A front matter value reduced to what metadata needs.
+enum FrontValue {
+    Text(String),
+    List(Vec<String>),
+}
+
+impl FrontValue {
+    fn text(self) -> Option<String> {
+        match self {
+            Self::Text(t) => Some(t),
+            Self::List(l) => l.into_iter().next(),
+        }
+    }
+
+    fn list(self, separator: Option<char>) -> Vec<String> {
+        match (self, separator) {
+            (Self::List(l), _) => l,
+            (Self::Text(t), Some(sep)) => t.split(sep).map(str::trim).filter(|t| !t.is_empty()).map(str::to_string).collect(),
+            (Self::Text(t), None) => vec![t],
+        }
+    }
+}
+
+impl FrontMatter {
+    /// Parse the front matter at the start of `text`. Returns it with the
+    /// rest of the document, or `None` when there is none or it is invalid.
+    pub fn parse(text: &str) -> Option<(Self, &str)> {
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+        let first = text.lines().next()?.trim_end();
+        let (fence, is_yaml) = match first {
+            "---" => ("---", true),
+            "+++" => ("+++", false),
+            _ => return None,
+        };
+        let rest = &text[text.find('\n')? + 1..];
+        // The block ends at the next fence line (`...` also ends YAML)
+        let mut offset = 0;
+        let (block, body) = loop {
+            let line_end = rest[offset..].find('\n').map_or(rest.len(), |i| offset + i + 1);
+            let line = rest[offset..line_end].trim_end();
+            if line == fence || (is_yaml && line == "...") {
+                break (&rest[..offset], &rest[line_end..]);
+            }
+            if line_end == rest.len() {
+                return None;
+            }
+            offset = line_end;
+        };
+
+        let mut fields: Vec<(String, FrontValue)> = if is_yaml {
+            let value: serde_yaml::Value = serde_yaml::from_str(block)
+                .map_err(|e| tracing::debug!("Unparseable YAML front matter: {}", e))
+                .ok()?;
+            let serde_yaml::Value::Mapping(map) = value else {
+                return None;
+            };
+            map.into_iter()
+                .filter_map(|(k, v)| Some((k.as_str()?.to_ascii_lowercase(), Self::yaml_value(v)?)))
+                .collect()
+        } else {
+            let table: toml::Table = toml::from_str(block)
+                .map_err(|e| tracing::debug!("Unparseable TOML front matter: {}", e))
+                .ok()?;
+            table.into_iter()
+                .filter_map(|(k, v)| Some((k.to_ascii_lowercase(), Self::toml_value(v)?)))
+                .collect()
+        };
+        let mut take = |keys: &[&str]| {
+            keys.iter().find_map(|key| fields.iter().position(|(k, _)| k == key)).map(|i| fields.swap_remove(i).1)
+        };
+
+        let front = Self {
+            title: take(&["title"]).and_then(FrontValue::text),
+            authors: take(&["author", "authors", "creator"]).map(|v| v.list(None)).unwrap_or_default(),
+            tags: take(&["tags", "keywords"]).map(|v| v.list(Some(','))).unwrap_or_default(),
+            date: take(&["date"]).and_then(FrontValue::text).and_then(|d| parse_date(&d)),
+            language: take(&["lang", "language"]).and_then(FrontValue::text),
+            subject: take(&["subject", "description"]).and_then(FrontValue::text),
+        };
+        Some((front, body))
+    }
+
+    fn yaml_value(value: serde_yaml::Value) -> Option<FrontValue> {
+        fn scalar(value: serde_yaml::Value) -> Option<String> {
+            let text = match value {
+                serde_yaml::Value::String(s) => s,
+                serde_yaml::Value::Number(n) => n.to_string(),
+                serde_yaml::Value::Bool(b) => b.to_string(),
+                _ => return None,
+            };
+            Some(text.trim().to_string()).filter(|t| !t.is_empty())
+        }
+        match value {
+            serde_yaml::Value::Sequence(items) => Some(FrontValue::List(items.into_iter().filter_map(scalar).collect())),
+            value => scalar(value).map(FrontValue::Text),
+        }
+    }
+
+    fn toml_value(value: toml::Value) -> Option<FrontValue> {
+        fn scalar(value: toml::Value) -> Option<String> {
+            let text = match value {
+                toml::Value::String(s) => s,
+                toml::Value::Integer(i) => i.to_string(),
+                toml::Value::Float(f) => f.to_string(),
+                toml::Value::Boolean(b) => b.to_string(),
+                toml::Value::Datetime(d) => d.to_string(),
+                _ => return None,
+            };
+            Some(text.trim().to_string()).filter(|t| !t.is_empty())
+        }
+        match value {
+            toml::Value::Array(items) => Some(FrontValue::List(items.into_iter().filter_map(scalar).collect())),
+            value => scalar(value).map(FrontValue::Text),
+        }
+    }
+}
+
+/// Metadata read straight from a PDF's structure: the document Info
+/// dictionary, the catalog's XMP metadata stream and the page tree. Used
+/// when PDFium is not available.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PdfInfo {
+    pub title: Option<String>,
+    /// Info `Author` as one entry, or each XMP `dc:creator`.
+    pub authors: Vec<String>,
+    pub subject: Option<String>,
+    /// Info `Keywords`, or XMP `pdf:Keywords`, split on commas and semicolons.
+    pub keywords: Vec<String>,
+    /// XMP `dc:language`; the Info dictionary has no such entry.
+    pub language: Option<String>,
+    /// `CreationDate` in seconds since the epoch.
+    pub created_at: Option<i64>,
+    pub page_count: Option<usize>,
+    /// ISBNs and DOIs as `(scheme, value)`: XMP `dc:identifier`,
+    /// `prism:doi` and `prism:isbn`, and ones written into the subject or
+    /// keywords.
+    pub identifiers: Vec<(String, String)>,
+}
+
+impl PdfInfo {
+    /// Read a PDF file. Info dictionary entries take precedence; the XMP
+    /// packet fills whatever they leave empty. `None` when the file cannot
+    /// be parsed.
+    pub fn read(path: &Path) -> Option<Self> {
+        // Page content and images are never looked at, so their streams are
+        // dropped while loading; object streams hold the dictionaries we need.
+        fn keep(id: lopdf::ObjectId, object: &mut lopdf::Object) -> Option<(lopdf::ObjectId, lopdf::Object)> {
+            if let lopdf::Object::Stream(stream) = object {
+                if !stream.dict.has_type(b"ObjStm") && !stream.dict.has_type(b"Metadata") {
+                    return None;
+                }
+            }
+            Some((id, object.clone()))
+        }
+
+        let doc = lopdf::Document::load_filtered(path, keep)
+            .map_err(|e| tracing::debug!("Unreadable PDF structure in {:?}: {}", path, e))
+            .ok()?;
+
+        let mut info = Self::default();
+        if let Ok(dict) = doc.trailer.get_deref(b"Info", &doc).and_then(|o| o.as_dict()) {
+            let text = |key: &[u8]| {
+                dict.get_deref(key, &doc)
+                    .ok()
+                    .and_then(|o| lopdf::decode_text_string(o).ok())
+                    .map(|s| s.trim().to_string())
+                    .filter(|s| !s.is_empty())
+            };
+            info.title = text(b"Title");
+            info.authors = text(b"Author").into_iter().collect();
+            info.subject = text(b"Subject");
+            info.keywords = text(b"Keywords").map(|k| split_keywords(&k)).unwrap_or_default();
+            for value in [text(b"Subject"), text(b"Keywords")].into_iter().flatten() {
+                info.add_identifiers(crate::identifiers::find_in_text(&value));
+            }
+            info.created_at = text(b"CreationDate").and_then(|d| parse_pdf_date(&d));
+        }
+
+        let xmp = doc
+            .catalog()
+            .and_then(|catalog| catalog.get_deref(b"Metadata", &doc))
+            .and_then(|o| o.as_stream())
+            .ok()
+            .and_then(|stream| {
+                if stream.dict.has(b"Filter") {
+                    stream.decompressed_content().ok()
+                } else {
+                    Some(stream.content.clone())
+                }
+            });
+        if let Some(xmp) = xmp {
+            info.merge_xmp(&String::from_utf8_lossy(&xmp));
+        }
+
+        let pages = doc.get_pages().len();
+        info.page_count = (pages > 0).then_some(pages);
+        Some(info)
+    }
+
+    /// Fill empty fields from an XMP packet. Properties may be written as
+    /// elements or as attributes of `rdf:Description`.
+    pub fn merge_xmp(&mut self, xml: &str) {
+        const DC: &str = "http://purl.org/dc/elements/1.1/";
+        const XMP: &str = "http://ns.adobe.com/xap/1.0/";
+        const PDF: &str = "http://ns.adobe.com/pdf/1.3/";
+        const PRISM: [&str; 3] = [
+            "http://prismstandard.org/namespaces/basic/2.0/",
+            "http://prismstandard.org/namespaces/basic/2.1/",
+            "http://prismstandard.org/namespaces/basic/3.0/",
+        ];
+        // Some writers pad the packet with NULs or put junk before it
+        let start = xml.find("<x:xmpmeta").or_else(|| xml.find("<rdf:RDF")).unwrap_or(0);
+        let Some(doc) = parse_xml(xml[start..].trim_end_matches(['\0', ' ', '\n', '\r'])) else {
+            return;
+        };
+
+        let first = |ns: &str, name: &str| xmp_values(&doc, ns, name).into_iter().next();
+        if self.title.is_none() {
+            self.title = first(DC, "title");
+        }
+        if self.authors.is_empty() {
+            self.authors = xmp_values(&doc, DC, "creator");
+        }
+        if self.subject.is_none() {
+            self.subject = first(DC, "description");
+        }
+        if self.keywords.is_empty() {
+            self.keywords = first(PDF, "Keywords").map(|k| split_keywords(&k)).unwrap_or_default();
+        }
+        if self.language.is_none() {
+            self.language = first(DC, "language").filter(|l| l != "x-unknown");
+        }
+        if self.created_at.is_none() {
+            self.created_at = first(XMP, "CreateDate").and_then(|d| parse_pdf_date(&d));
+        }
+
+        let mut found = vec![];
+        for value in xmp_values(&doc, DC, "identifier") {
+            match crate::identifiers::recognize(None, &value) {
+                Some(id) => found.push(id),
+                None => found.extend(crate::identifiers::find_in_text(&value)),
+            }
+        }
+        for ns in PRISM {
+            found.extend(xmp_values(&doc, ns, "doi").iter().filter_map(|v| crate::identifiers::normalize("doi", v)));
+            found.extend(xmp_values(&doc, ns, "isbn").iter().filter_map(|v| crate::identifiers::normalize("isbn", v)));
+        }
+        for value in [xmp_values(&doc, DC, "description"), xmp_values(&doc, DC, "subject"), xmp_values(&doc, PDF, "Keywords")].concat() {
+            found.extend(crate::identifiers::find_in_text(&value));
+        }
+        self.add_identifiers(found);
+    }
+
+    /// Append identifiers not already known.
+    fn add_identifiers(&mut self, found: Vec<(String, String)>) {
+        for id in found {
+            if !self.identifiers.contains(&id) {
+                self.identifiers.push(id);
+            }
+        }
+    }
+}
+
+/// Values of an XMP property: the items of its `rdf:Seq`, `rdf:Bag` or
+/// `rdf:Alt` (the `x-default` alternative first), or its simple value.
+fn xmp_values(doc: &roxmltree::Document, ns: &str, name: &str) -> Vec<String> {
+    const RDF: &str = "http://www.w3.org/1999/02/22-rdf-syntax-ns#";
+    let mut values = vec![];
+    for node in doc.descendants().filter(|n| n.is_element()) {
+        if let Some(value) = node.attribute((ns, name)) {
+            values.push(value.trim().to_string());
+        }
+        if !node.has_tag_name((ns, name)) {
+            continue;
+        }
+        let mut items: Vec<_> = node.descendants().filter(|n| n.has_tag_name((RDF, "li"))).collect();
+        if items.is_empty() {
+            values.extend(node.text().map(|t| t.trim().to_string()));
+            continue;
+        }
+        items.sort_by_key(|li| li.attribute(("http://www.w3.org/XML/1998/namespace", "lang")) != Some("x-default"));
+        values.extend(items.iter().filter_map(|li| li.text()).map(|t| t.trim().to_string()));
+    }
+    values.retain(|v| !v.is_empty());
+    values
+}
+
+fn split_keywords(keywords: &str) -> Vec<String> {
+    keywords.split([',', ';']).map(str::trim).filter(|k| !k.is_empty()).map(str::to_string).collect()
+}
+
+/// Seconds since the epoch for a PDF date (`D:YYYYMMDDHHmmSSOHH'mm'`) or an
+/// XMP one (`YYYY-MM-DDThh:mm:ss+hh:mm`). Every part after the year is
+/// optional; a missing offset means UTC.
+fn parse_pdf_date(text: &str) -> Option<i64> {
+    static DATE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(
+            r"^(?:D:)?(?P<y>\d{4})(?:-?(?P<mo>\d{2})(?:-?(?P<d>\d{2})(?:T?(?P<h>\d{2})(?::?(?P<mi>\d{2})(?::?(?P<s>\d{2}))?)?)?)?)?(?:\.\d+)?(?:(?P<z>Z)|(?P<sign>[+-])(?P<oh>\d{2})(?:'?:?(?P<om>\d{2}))?)?",
+        )
+        .unwrap()
+    });
+    let caps = DATE.captures(text.trim())?;
+    let part = |name: &str, default: u32| caps.name(name).map_or(Some(default), |m| m.as_str().parse().ok());
+    let year: i64 = caps["y"].parse().ok()?;
+    let (month, day) = (part("mo", 1)?, part("d", 1)?);
+    let (hour, minute, second) = (part("h", 0)?, part("mi", 0)?, part("s", 0)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+    let mut offset = i64::from(part("oh", 0)?) * 3600 + i64::from(part("om", 0)?) * 60;
+    if caps.name("sign").is_some_and(|s| s.as_str() == "-") {
+        offset = -offset;
+    }
+    let time = i64::from(hour) * 3600 + i64::from(minute) * 60 + i64::from(second);
+    Some(epoch_seconds(year, month, day) + time - offset)
+}
+
+/// Text of the first level-one ATX heading (`# Title`) outside code fences.
+fn first_heading(markdown: &str) -> Option<String> {
+    let mut in_fence = false;
+    for line in markdown.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence || line.len() - trimmed.len() > 3 {
+            continue;
+        }
+        if let Some(heading) = trimmed.strip_prefix("# ") {
+            // Closing hashes are decoration: `# Title #`
+            let heading = heading.trim().trim_end_matches('#').trim_end();
+            if !heading.is_empty() {
+                return Some(heading.to_string());
+            }
+        }
+    }
+    None
+}
+
+/// Seconds since the epoch for a `YYYY`, `YYYY-MM` or `YYYY-MM-DD` date,
+/// ignoring any time that follows.
+fn parse_date(text: &str) -> Option<i64> {
+    static DATE: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r"^(?P<y>\d{4})(?:-(?P<m>\d{1,2})(?:-(?P<d>\d{1,2}))?)?(?:$|[T\s])").unwrap()
+    });
+    let caps = DATE.captures(text.trim())?;
+    let year: i64 = caps["y"].parse().ok()?;
+    let month: u32 = caps.name("m").map_or(Some(1), |m| m.as_str().parse().ok())?;
+    let day: u32 = caps.name("d").map_or(Some(1), |d| d.as_str().parse().ok())?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+    Some(epoch_seconds(year, month, day))
+}
+
+/// Midnight UTC of a civil (proleptic Gregorian) date in seconds since the epoch.
+fn epoch_seconds(year: i64, month: u32, day: u32) -> i64 {
+    let (month, day) = (i64::from(month), i64::from(day));
+    // Shifted so March starts the year and leap days fall at its end
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let doy = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    (era * 146_097 + doe - 719_468) * 86_400
+}
+
+/// The start of a text document, decoded leniently.
+fn read_header(path: &Path) -> Option<String> {
+    let file = std::fs::File::open(path).ok()?;
+    let mut bytes = vec![];
+    file.take(MAX_HEADER_BYTES).read_to_end(&mut bytes).ok()?;
+    Some(String::from_utf8_lossy(&bytes).into_owned())
+}
+
+/// The OPF package document of an EPUB, located through its container.
+fn read_opf(path: &Path) -> Option<String> {
+    let container = read_zip_entry(path, "META-INF/container.xml")?;
+    let container = parse_xml(&container)?;
+    let opf_path = container
+        .descendants()
+        .find(|n| n.has_tag_name("rootfile"))?
+        .attribute("full-path")?
+        .to_string();
+    read_zip_entry(path, &opf_path)
+}
+
+/// Read a small text entry from a zip container.
+fn read_zip_entry(path: &Path, name: &str) -> Option<String> {
+    let file = std::fs::File::open(path).ok()?;
+    let mut archive = zip::ZipArchive::new(file).ok()?;
+    let entry = archive.by_name(name).ok()?;
+    let mut text = String::new();
+    entry.take(MAX_ENTRY_BYTES).read_to_string(&mut text).ok()?;
+    Some(text)
+}
+
+fn parse_xml(text: &str) -> Option<roxmltree::Document<'_>> {
+    let options = roxmltree::ParsingOptions { allow_dtd: true, ..Default::default() };
+    roxmltree::Document::parse_with_options(text, options)
+        .map_err(|e| tracing::debug!("Unparseable XML metadata: {}", e))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series(name: &str, format: DocumentFormat) -> Option<(String, Option<f64>)> {
+        MetadataExtractor::series_from_file_name(Path::new(name), format)
+    }
+
+    fn named(series: &str, index: f64) -> Option<(String, Option<f64>)> {
+        Some((series.to_string(), Some(index)))
+    }
+
+    #[test]
+    fn series_from_marked_comic_and_epub_names() {
+        assert_eq!(series("Saga v03 (2014) [Digital].cbz", DocumentFormat::Cbz), named("Saga", 3.0));
+        assert_eq!(series("Saga #12.cbr", DocumentFormat::Cbr), named("Saga", 12.0));
+        assert_eq!(series("Saga 003.cbz", DocumentFormat::Cbz), named("Saga", 3.0));
+        assert_eq!(series("Dune Book 2.epub", DocumentFormat::Epub), named("Dune", 2.0));
+        assert_eq!(series("One_Piece_Vol_7.5.epub", DocumentFormat::Epub), named("One Piece", 7.5));
+    }
+
+    #[test]
+    fn series_before_title() {
+        assert_eq!(
+            series("Discworld - 03 - Equal Rites.epub", DocumentFormat::Epub),
+            named("Discworld", 3.0)
+        );
+        assert_eq!(series("Discworld 03 - Equal Rites.pdf", DocumentFormat::Pdf), named("Discworld", 3.0));
+        assert_eq!(series("Catch 22 - Joseph Heller.epub", DocumentFormat::Epub), None);
+    }
+
+    #[test]
+    fn other_documents_need_explicit_markers() {
+        assert_eq!(series("paper_v2.pdf", DocumentFormat::Pdf), None);
+        assert_eq!(series("thesis draft v3.pdf", DocumentFormat::Pdf), None);
+        assert_eq!(series("Dune Book 2.pdf", DocumentFormat::Pdf), None);
+        assert_eq!(series("Report 2019.pdf", DocumentFormat::Pdf), None);
+        assert_eq!(series("Proceedings Vol. 4.pdf", DocumentFormat::Pdf), named("Proceedings", 4.0));
+        assert_eq!(series("Journal No. 12.pdf", DocumentFormat::Pdf), named("Journal", 12.0));
+        assert_eq!(series("Notes #3.md", DocumentFormat::Markdown), named("Notes", 3.0));
+    }
+
+    #[test]
+    fn series_needs_letters() {
+        assert_eq!(series("2019 v2.cbz", DocumentFormat::Cbz), None);
+    }
+
+    #[test]
+    fn comic_info_fields() {
+        let info = ComicInfo::parse(
+            r#"<?xml version="1.0"?>
+            <ComicInfo xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+              <Series>Vinland Saga</Series>
+              <Number>3</Number>
+              <Writer>Makoto Yukimura</Writer>
+              <Penciller>Makoto Yukimura, Studio Assistant</Penciller>
+              <Year>-1</Year>
+              <Manga>YesAndRightToLeft</Manga>
+            </ComicInfo>"#,
+        )
+        .unwrap();
+        assert_eq!(info.series.as_deref(), Some("Vinland Saga"));
+        assert_eq!(info.number.as_deref(), Some("3"));
+        assert_eq!(info.series_index(), Some(3.0));
+        assert_eq!(info.credits, [
+            ("writer".to_string(), "Makoto Yukimura".to_string()),
+            ("penciller".to_string(), "Makoto Yukimura".to_string()),
+            ("penciller".to_string(), "Studio Assistant".to_string()),
+        ]);
+        assert_eq!(info.year, None);
+        assert!(info.right_to_left);
+
+        let plain = ComicInfo::parse("<ComicInfo><Manga>Yes</Manga></ComicInfo>").unwrap();
+        assert!(!plain.right_to_left);
+        assert!(ComicInfo::parse("<Other/>").is_none());
+    }
+
+    #[test]
+    fn yaml_front_matter() {
+        let text = "---\ntitle: Field Notes\nauthors:\n  - Ada Lovelace\n  - Charles Babbage\ntags: rust, notes\ndate: 2021-03-04\nlang: en\n---\n# Body\n";
+        let (front, body) = FrontMatter::parse(text).unwrap();
+        assert_eq!(front.title.as_deref(), Some("Field Notes"));
+        assert_eq!(front.authors, ["Ada Lovelace", "Charles Babbage"]);
+        assert_eq!(front.tags, ["rust", "notes"]);
+        assert_eq!(front.date, Some(1_614_816_000));
+        assert_eq!(front.language.as_deref(), Some("en"));
+        assert_eq!(body, "# Body\n");
+    }
+
+    #[test]
+    fn toml_front_matter() {
+        let text = "\u{feff}+++\nTitle = \"Field Notes\"\nauthor = \"Ada Lovelace\"\nkeywords = [\"rust\", \"notes\"]\ndate = 2021-03-04\ndescription = \"Jottings\"\n+++\nBody";
+        let (front, body) = FrontMatter::parse(text).unwrap();
+        assert_eq!(front.title.as_deref(), Some("Field Notes"));
+        assert_eq!(front.authors, ["Ada Lovelace"]);
+        assert_eq!(front.tags, ["rust", "notes"]);
+        assert_eq!(front.date, Some(1_614_816_000));
+        assert_eq!(front.subject.as_deref(), Some("Jottings"));
+        assert_eq!(body, "Body");
+    }
+
+    #[test]
+    fn front_matter_must_be_closed_and_valid() {
+        assert!(FrontMatter::parse("# Title\n---\ntitle: x\n---\n").is_none());
+        assert!(FrontMatter::parse("---\ntitle: x\n").is_none());
+        assert!(FrontMatter::parse("---\n- a list\n---\n").is_none());
+        assert!(FrontMatter::parse("+++\ntitle = \n+++\n").is_none());
+        let (front, body) = FrontMatter::parse("---\ntitle: x\n...\nrest").unwrap();
+        assert_eq!(front.title.as_deref(), Some("x"));
+        assert_eq!(body, "rest");
+    }
+
+    #[test]
+    fn epoch_seconds_of_civil_dates() {
+        assert_eq!(epoch_seconds(1970, 1, 1), 0);
+        assert_eq!(epoch_seconds(1969, 12, 31), -86_400);
+        assert_eq!(epoch_seconds(2000, 3, 1), 951_868_800);
+        assert_eq!(epoch_seconds(2024, 2, 29), 1_709_164_800);
+        assert_eq!(epoch_seconds(2021, 3, 4), 1_614_816_000);
+    }
+
+    #[test]
+    fn pdf_info_dates() {
+        assert_eq!(parse_pdf_date("D:20210304120000Z"), Some(1_614_859_200));
+        assert_eq!(parse_pdf_date("D:20210304120000+02'00'"), Some(1_614_852_000));
+        assert_eq!(parse_pdf_date("D:20210304120000-05'30"), Some(1_614_879_000));
+        assert_eq!(parse_pdf_date("D:202103"), Some(epoch_seconds(2021, 3, 1)));
+        assert_eq!(parse_pdf_date("20210304"), Some(1_614_816_000));
+    }
+
+    #[test]
+    fn xmp_dates() {
+        assert_eq!(parse_pdf_date("2021-03-04T12:00:00-05:00"), Some(1_614_877_200));
+        assert_eq!(parse_pdf_date("2021-03-04T12:00:00.250Z"), Some(1_614_859_200));
+        assert_eq!(parse_pdf_date("2021-03-04"), Some(1_614_816_000));
+    }
+
+    #[test]
+    fn pdf_identifiers_from_info_and_xmp() {
+        use lopdf::dictionary;
+
+        let mut doc = lopdf::Document::with_version("1.5");
+        let pages = doc.new_object_id();
+        doc.objects.insert(pages, dictionary! { "Type" => "Pages", "Kids" => vec![], "Count" => 0 }.into());
+        let catalog = doc.add_object(dictionary! { "Type" => "Catalog", "Pages" => pages });
+        let info = doc.add_object(dictionary! {
+            "Title" => lopdf::Object::string_literal("Notes"),
+            "Subject" => lopdf::Object::string_literal("Printed as ISBN 0-306-40615-2"),
+            "Keywords" => lopdf::Object::string_literal("rust; notes,"),
+        });
+        doc.trailer.set("Root", catalog);
+        doc.trailer.set("Info", info);
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notes.pdf");
+        doc.save(&path).unwrap();
+        let (tags, identifiers) = MetadataExtractor::extract_tags_and_identifiers(&path, DocumentFormat::Pdf);
+        assert_eq!(tags, ["rust", "notes"]);
+        assert_eq!(identifiers, [("isbn13".to_string(), "9780306406157".to_string())]);
+
+        let mut info = PdfInfo::default();
+        info.merge_xmp(
+            r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF xmlns:rdf="http://www.w3.org/1999/02/22-rdf-syntax-ns#">
+              <rdf:Description xmlns:dc="http://purl.org/dc/elements/1.1/"
+                  xmlns:prism="http://prismstandard.org/namespaces/basic/2.0/" prism:doi="10.1000/XYZ.123">
+                <dc:identifier>urn:isbn:9780306406157</dc:identifier>
+                <dc:subject><rdf:Bag><rdf:li>see doi:10.1000/xyz.123</rdf:li></rdf:Bag></dc:subject>
+              </rdf:Description>
+            </rdf:RDF></x:xmpmeta>"#,
+        );
+        assert_eq!(info.identifiers, [
+            ("isbn13".to_string(), "9780306406157".to_string()),
+            ("doi".to_string(), "10.1000/xyz.123".to_string()),
+        ]);
+    }
+
+    #[test]
+    fn invalid_dates_are_rejected() {
+        assert_eq!(parse_pdf_date("D:20211304"), None);
+        assert_eq!(parse_pdf_date("D:20210304250000"), None);
+        assert_eq!(parse_pdf_date("yesterday"), None);
+        assert_eq!(parse_pdf_date(""), None);
+    }
+}
@@ -0,0 +1,223 @@
+//! Reading position and time tracking per library item.
+
+use blinker_core_common::{BlinkerError, Result};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+use crate::{LibraryDatabase, LibraryItem, LibraryStore};
+
+/// Where a reader left off in an item.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReadingState {
+    pub item_id: String,
+    /// Last page viewed (starts at 1).
+    pub current_page: usize,
+    pub total_pages: usize,
+    /// Fraction read, 0.0 to 1.0. Single-page and flow documents stay at
+    /// 0.0 until marked finished.
+    pub progress: f64,
+    pub last_opened: i64,
+    /// Accumulated reading time in seconds.
+    pub reading_time: u64,
+}
+
+impl ReadingState {
+    pub fn is_finished(&self) -> bool {
+        self.progress >= 1.0
+    }
+}
+
+/// A library item together with its reading state.
+#[derive(Debug, Clone)]
+pub struct ReadingEntry {
+    pub item: LibraryItem,
+    pub state: ReadingState,
+}
+
+/// Persistence of reading progress, used to resume documents.
+pub trait ReadingStateStore {
+    fn reading_state(&self, item_id: &str) -> Result<Option<ReadingState>>;
+    /// Record the page being read and mark the item as opened now. Progress
+    /// follows the page through multi-page documents; re-recording the same
+    /// page, or any page of a single-page document, keeps it as it was.
+    fn record_position(&self, item_id: &str, page: usize, total_pages: usize) -> Result<ReadingState>;
+    /// Mark an item read to the end, wherever the reader is. Undone by the
+    /// next position recorded on a different page, or by clearing the state.
+    fn mark_finished(&self, item_id: &str) -> Result<ReadingState>;
+    /// Add `seconds` to the item's accumulated reading time.
+    fn add_reading_time(&self, item_id: &str, seconds: u64) -> Result<()>;
+    fn clear_reading_state(&self, item_id: &str) -> Result<()>;
+    /// Items ordered by when they were last opened, most recent first.
+    fn recently_opened(&self, limit: usize) -> Result<Vec<ReadingEntry>>;
+    /// Started but unfinished items, most recently opened first.
+    fn in_progress(&self, limit: usize) -> Result<Vec<ReadingEntry>>;
+}
+
+impl LibraryDatabase {
+    fn reading_state_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<ReadingState> {
+        let current_page: i64 = row.get(1)?;
+        let total_pages: i64 = row.get(2)?;
+        let reading_time: i64 = row.get(5)?;
+        Ok(ReadingState {
+            item_id: row.get(0)?,
+            current_page: current_page as usize,
+            total_pages: total_pages as usize,
+            progress: row.get(3)?,
+            last_opened: row.get(4)?,
+            reading_time: reading_time as u64,
+        })
+    }
+
+    fn reading_entries(&self, filter: &str, limit: usize) -> Result<Vec<ReadingEntry>> {
+        let sql = format!(
+            "SELECT item_id, current_page, total_pages, progress, last_opened, reading_time
+             FROM reading_state {} ORDER BY last_opened DESC LIMIT ?1",
+            filter
+        );
+        let mut stmt = self.conn
+            .prepare(&sql)
+            .map_err(|e| BlinkerError::Database(format!("prepare reading states: {}", e)))?;
+        let states = stmt
+            .query_map(params![limit as i64], Self::reading_state_from_row)
+            .map_err(|e| BlinkerError::Database(format!("reading states: {}", e)))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| BlinkerError::Database(format!("row: {}", e)))?;
+
+        let mut out = Vec::with_capacity(states.len());
+        for state in states {
+            if let Some(item) = self.get_item(&state.item_id)? {
+                out.push(ReadingEntry { item, state });
+            }
+        }
+        Ok(out)
+    }
+}
+
+impl ReadingStateStore for LibraryDatabase {
+    fn reading_state(&self, item_id: &str) -> Result<Option<ReadingState>> {
+        self.conn
+            .query_row(
+                "SELECT item_id, current_page, total_pages, progress, last_opened, reading_time
+                 FROM reading_state WHERE item_id = ?1",
+                params![item_id],
+                Self::reading_state_from_row,
+            )
+            .optional()
+            .map_err(|e| BlinkerError::Database(format!("get reading state: {}", e)))
+    }
+
+    fn record_position(&self, item_id: &str, page: usize, total_pages: usize) -> Result<ReadingState> {
+        let page = page.clamp(1, total_pages.max(1));
+        // Having the only page (or a whole flow document) open says nothing
+        // about how much of it was read
+        let progress = if total_pages <= 1 { 0.0 } else { page as f64 / total_pages as f64 };
+        let now = Self::now_secs();
+        self.conn
+            .execute(
+                "INSERT INTO reading_state (id, item_id, current_page, total_pages, progress, last_opened, reading_time)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, 0)
+                 ON CONFLICT(item_id) DO UPDATE SET
+                    progress = CASE
+                        WHEN excluded.total_pages <= 1
+                          OR (excluded.current_page = current_page AND excluded.total_pages = total_pages)
+                        THEN progress ELSE excluded.progress END,
+                    current_page = excluded.current_page,
+                    total_pages = excluded.total_pages,
+                    last_opened = excluded.last_opened",
+                params![
+                    uuid::Uuid::new_v4().to_string(),
+                    item_id,
+                    page as i64,
+                    total_pages as i64,
+                    progress,
+                    now,
+                ],
+            )
+            .map_err(|e| BlinkerError::Database(format!("record position: {}", e)))?;
+        self.reading_state(item_id)?
+            .ok_or_else(|| BlinkerError::NotFound(format!("reading state for {}", item_id)))
+    }
+
+    fn mark_finished(&self, item_id: &str) -> Result<ReadingState> {
+        self.conn
+            .execute(
+                "INSERT INTO reading_state (id, item_id, current_page, total_pages, progress, last_opened, reading_time)
+                 SELECT ?1, id, 1, COALESCE(page_count, 0), 1.0, ?3, 0 FROM library_item WHERE id = ?2
+                 ON CONFLICT(item_id) DO UPDATE SET progress = 1.0, last_opened = excluded.last_opened",
+                params![uuid::Uuid::new_v4().to_string(), item_id, Self::now_secs()],
+            )
+            .map_err(|e| BlinkerError::Database(format!("mark finished: {}", e)))?;
+        self.reading_state(item_id)?
+            .ok_or_else(|| BlinkerError::NotFound(format!("item {}", item_id)))
+    }
+
+    fn add_reading_time(&self, item_id: &str, seconds: u64) -> Result<()> {
+        let n = self.conn
+            .execute(
+                "UPDATE reading_state SET reading_time = reading_time + ?2 WHERE item_id = ?1",
+                params![item_id, seconds as i64],
+            )
+            .map_err(|e| BlinkerError::Database(format!("add reading time: {}", e)))?;
+        if n == 0 {
+            return Err(BlinkerError::NotFound(format!("reading state for {}", item_id)));
+        }
+        Ok(())
+    }
+
+    fn clear_reading_state(&self, item_id: &str) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM reading_state WHERE item_id = ?1", params![item_id])
+            .map_err(|e| BlinkerError::Database(format!("clear reading state: {}", e)))?;
+        Ok(())
+    }
+
+    fn recently_opened(&self, limit: usize) -> Result<Vec<ReadingEntry>> {
+        self.reading_entries("", limit)
+    }
+
+    fn in_progress(&self, limit: usize) -> Result<Vec<ReadingEntry>> {
+        self.reading_entries("WHERE progress < 1.0", limit)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::library_with;
+
+    fn library_with_note() -> (tempfile::TempDir, LibraryDatabase, String) {
+        let (dir, db, mut ids) = library_with(&["note"]);
+        (dir, db, ids.remove(0))
+    }
+
+    #[test]
+    fn opening_a_single_page_document_does_not_finish_it() {
+        let (_dir, db, id) = library_with_note();
+        let state = db.record_position(&id, 1, 1).unwrap();
+        assert_eq!(state.progress, 0.0);
+        assert_eq!(db.in_progress(10).unwrap().len(), 1);
+
+        assert!(db.mark_finished(&id).unwrap().is_finished());
+        // Reopening records the same position again
+        assert!(db.record_position(&id, 1, 1).unwrap().is_finished());
+        assert!(db.in_progress(10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn progress_follows_pages_of_longer_documents() {
+        let (_dir, db, id) = library_with_note();
+        assert_eq!(db.record_position(&id, 1, 4).unwrap().progress, 0.25);
+        assert!(db.record_position(&id, 4, 4).unwrap().is_finished());
+
+        assert_eq!(db.record_position(&id, 2, 4).unwrap().progress, 0.5);
+        assert!(db.mark_finished(&id).unwrap().is_finished());
+        assert!(db.record_position(&id, 2, 4).unwrap().is_finished());
+        assert_eq!(db.record_position(&id, 3, 4).unwrap().progress, 0.75);
+    }
+
+    #[test]
+    fn mark_finished_needs_an_item() {
+        let (_dir, db, _) = library_with_note();
+        assert!(db.mark_finished("no-such-item").is_err());
+    }
+}
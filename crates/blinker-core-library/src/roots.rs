@@ -0,0 +1,197 @@
+//! Stored library roots and the rules each one is scanned with.
+
+use blinker_core_common::{BlinkerError, Result};
+use rusqlite::{params, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+use crate::{LibraryDatabase, ScanRules};
+
+/// A directory the library is built from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LibraryRoot {
+    pub id: String,
+    pub path: PathBuf,
+    pub rules: ScanRules,
+    /// Disabled roots are kept but skipped by `rescan_all`.
+    pub enabled: bool,
+    pub created_at: i64,
+    pub last_scanned_at: Option<i64>,
+}
+
+/// Persistence of library roots.
+pub trait LibraryRootStore {
+    /// Roots ordered by path.
+    fn list_roots(&self) -> Result<Vec<LibraryRoot>>;
+    fn get_root(&self, id: &str) -> Result<Option<LibraryRoot>>;
+    /// Register a directory; fails if it is already a root or a pattern is invalid.
+    fn add_root(&self, path: &Path, rules: ScanRules) -> Result<LibraryRoot>;
+    fn update_root(&self, id: &str, rules: ScanRules, enabled: bool) -> Result<LibraryRoot>;
+    /// Forget a root. Items already indexed from it are kept.
+    fn remove_root(&self, id: &str) -> Result<()>;
+    fn mark_root_scanned(&self, id: &str) -> Result<()>;
+}
+
+const ROOT_COLUMNS: &str = "id, path, include_patterns, exclude_patterns, max_depth, \
+    follow_symlinks, enabled, created_at, last_scanned_at";
+
+impl LibraryDatabase {
+    fn root_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<LibraryRoot> {
+        let patterns = |idx: usize| -> rusqlite::Result<Vec<String>> {
+            let json: String = row.get(idx)?;
+            serde_json::from_str(&json)
+                .map_err(|e| rusqlite::Error::FromSqlConversionFailure(idx, rusqlite::types::Type::Text, Box::new(e)))
+        };
+        let max_depth: Option<i64> = row.get(4)?;
+        Ok(LibraryRoot {
+            id: row.get(0)?,
+            path: PathBuf::from(row.get::<_, String>(1)?),
+            rules: ScanRules {
+                include: patterns(2)?,
+                exclude: patterns(3)?,
+                max_depth: max_depth.map(|d| d as usize),
+                follow_symlinks: row.get(5)?,
+            },
+            enabled: row.get(6)?,
+            created_at: row.get(7)?,
+            last_scanned_at: row.get(8)?,
+        })
+    }
+
+    fn patterns_json(patterns: &[String]) -> Result<String> {
+        serde_json::to_string(patterns).map_err(|e| BlinkerError::Parsing(format!("encode patterns: {}", e)))
+    }
+}
+
+impl LibraryRootStore for LibraryDatabase {
+    fn list_roots(&self) -> Result<Vec<LibraryRoot>> {
+        let sql = format!("SELECT {} FROM library_root ORDER BY path", ROOT_COLUMNS);
+        let mut stmt = self.conn
+            .prepare(&sql)
+            .map_err(|e| BlinkerError::Database(format!("prepare roots: {}", e)))?;
+        let roots = stmt
+            .query_map([], Self::root_from_row)
+            .map_err(|e| BlinkerError::Database(format!("list roots: {}", e)))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| BlinkerError::Database(format!("row: {}", e)))?;
+        Ok(roots)
+    }
+
+    fn get_root(&self, id: &str) -> Result<Option<LibraryRoot>> {
+        let sql = format!("SELECT {} FROM library_root WHERE id = ?1", ROOT_COLUMNS);
+        self.conn
+            .query_row(&sql, params![id], Self::root_from_row)
+            .optional()
+            .map_err(|e| BlinkerError::Database(format!("get root: {}", e)))
+    }
+
+    fn add_root(&self, path: &Path, rules: ScanRules) -> Result<LibraryRoot> {
+        rules.validate()?;
+        // Stored canonical so it compares with item paths and watcher events
+        let canon = std::fs::canonicalize(path).map_err(BlinkerError::Io)?;
+        if !canon.is_dir() {
+            return Err(BlinkerError::Parsing(format!("not a directory: {}", path.display())));
+        }
+        let id = uuid::Uuid::new_v4().to_string();
+        self.conn
+            .execute(
+                "INSERT INTO library_root (id, path, include_patterns, exclude_patterns, max_depth, follow_symlinks, enabled, created_at)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, 1, ?7)",
+                params![
+                    id,
+                    canon.to_string_lossy(),
+                    Self::patterns_json(&rules.include)?,
+                    Self::patterns_json(&rules.exclude)?,
+                    rules.max_depth.map(|d| d as i64),
+                    rules.follow_symlinks,
+                    Self::now_secs(),
+                ],
+            )
+            .map_err(|e| BlinkerError::Database(format!("add root: {}", e)))?;
+        self.get_root(&id)?
+            .ok_or_else(|| BlinkerError::NotFound(format!("root {}", id)))
+    }
+
+    fn update_root(&self, id: &str, rules: ScanRules, enabled: bool) -> Result<LibraryRoot> {
+        rules.validate()?;
+        let n = self.conn
+            .execute(
+                "UPDATE library_root SET include_patterns = ?2, exclude_patterns = ?3, max_depth = ?4,
+                    follow_symlinks = ?5, enabled = ?6
+                 WHERE id = ?1",
+                params![
+                    id,
+                    Self::patterns_json(&rules.include)?,
+                    Self::patterns_json(&rules.exclude)?,
+                    rules.max_depth.map(|d| d as i64),
+                    rules.follow_symlinks,
+                    enabled,
+                ],
+            )
+            .map_err(|e| BlinkerError::Database(format!("update root: {}", e)))?;
+        if n == 0 {
+            return Err(BlinkerError::NotFound(format!("root {}", id)));
+        }
+        self.get_root(id)?
+            .ok_or_else(|| BlinkerError::NotFound(format!("root {}", id)))
+    }
+
+    fn remove_root(&self, id: &str) -> Result<()> {
+        let n = self.conn
+            .execute("DELETE FROM library_root WHERE id = ?1", params![id])
+            .map_err(|e| BlinkerError::Database(format!("remove root: {}", e)))?;
+        if n == 0 {
+            return Err(BlinkerError::NotFound(format!("root {}", id)));
+        }
+        Ok(())
+    }
+
+    fn mark_root_scanned(&self, id: &str) -> Result<()> {
+        self.conn
+            .execute(
+                "UPDATE library_root SET last_scanned_at = ?2 WHERE id = ?1",
+                params![id, Self::now_secs()],
+            )
+            .map_err(|e| BlinkerError::Database(format!("mark root scanned: {}", e)))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::library_with;
+    use crate::LibraryStore;
+
+    #[test]
+    fn roots_round_trip_their_rules() {
+        let (dir, db, ids) = library_with(&["a"]);
+        let rules = ScanRules {
+            include: vec!["*.pdf".into(), "comics/**/*.cbz".into(), "\"quoted\" [ab].md".into()],
+            exclude: vec!["node_modules".into(), "Téléchargements/tmp".into()],
+            max_depth: Some(3),
+            follow_symlinks: true,
+        };
+        let root = db.add_root(dir.path(), rules.clone()).unwrap();
+        assert_eq!(root.path, std::fs::canonicalize(dir.path()).unwrap());
+        assert!(root.enabled);
+        assert_eq!(root.last_scanned_at, None);
+        assert_eq!(db.get_root(&root.id).unwrap().unwrap().rules, rules);
+        assert!(db.add_root(dir.path(), ScanRules::default()).is_err());
+        let invalid = ScanRules { exclude: vec!["[".into()], ..Default::default() };
+        assert!(db.update_root(&root.id, invalid, true).is_err());
+
+        let updated = db.update_root(&root.id, ScanRules::default(), false).unwrap();
+        assert_eq!(updated.rules, ScanRules::default());
+        assert!(!updated.enabled);
+        db.mark_root_scanned(&root.id).unwrap();
+        let listed = db.list_roots().unwrap();
+        assert_eq!(listed.len(), 1);
+        assert!(listed[0].last_scanned_at.is_some());
+
+        db.remove_root(&root.id).unwrap();
+        assert!(db.list_roots().unwrap().is_empty());
+        assert!(matches!(db.remove_root(&root.id), Err(BlinkerError::NotFound(_))));
+        assert!(db.get_item(&ids[0]).unwrap().is_some());
+    }
+}
@@ -1,53 +1,613 @@
-use blinker_core_common::Result;
+use blinker_core_common::{BlinkerError, Result};
+use blinker_core_render::{format, AnyRenderer, PageText};
+use globset::{GlobBuilder, GlobSet, GlobSetBuilder};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
-use super::{LibraryStore, AddOutcome};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Arc};
+use super::{LibraryStore, AddOutcome, UnchangedFile};
+use crate::database::PreparedFile;
+use crate::roots::LibraryRootStore;
+use crate::thumbnails::ThumbnailCache;
 
-pub struct LibraryScanner;
+/// Which files under a root are part of the library.
+///
+/// Patterns without a `/` match a file or directory name anywhere below the
+/// root (`node_modules`, `*.tmp.pdf`); patterns with a `/` match the path
+/// relative to the root (`Downloads/tmp`, `comics/**/*.cbz`).
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScanRules {
+    /// When non-empty, only files matching one of these are indexed.
+    #[serde(default)]
+    pub include: Vec<String>,
+    /// Files and directories matching any of these are skipped.
+    #[serde(default)]
+    pub exclude: Vec<String>,
+    /// How many directory levels below the root to descend; `None` for no limit.
+    #[serde(default)]
+    pub max_depth: Option<usize>,
+    #[serde(default)]
+    pub follow_symlinks: bool,
+}
+
+impl ScanRules {
+    /// Check that every pattern is a valid glob.
+    pub fn validate(&self) -> Result<()> {
+        CompiledRules::new(self).map(|_| ())
+    }
+}
+
+/// Glob sets built from `ScanRules`, split by what each pattern matches against.
+pub(crate) struct CompiledRules {
+    include_name: GlobSet,
+    include_path: GlobSet,
+    exclude_name: GlobSet,
+    exclude_path: GlobSet,
+    has_include: bool,
+    max_depth: Option<usize>,
+    follow_symlinks: bool,
+}
+
+impl CompiledRules {
+    pub(crate) fn new(rules: &ScanRules) -> Result<Self> {
+        fn build<'a>(patterns: impl Iterator<Item = &'a String>) -> Result<GlobSet> {
+            let mut b = GlobSetBuilder::new();
+            for p in patterns {
+                // `*` stays within one path component; `**` crosses directories
+                let glob = GlobBuilder::new(p)
+                    .literal_separator(true)
+                    .build()
+                    .map_err(|e| BlinkerError::Parsing(format!("invalid pattern {:?}: {}", p, e)))?;
+                b.add(glob);
+            }
+            b.build().map_err(|e| BlinkerError::Parsing(format!("build patterns: {}", e)))
+        }
+        let is_path = |p: &&String| p.contains('/');
+        Ok(Self {
+            include_name: build(rules.include.iter().filter(|p| !is_path(p)))?,
+            include_path: build(rules.include.iter().filter(is_path))?,
+            exclude_name: build(rules.exclude.iter().filter(|p| !is_path(p)))?,
+            exclude_path: build(rules.exclude.iter().filter(is_path))?,
+            has_include: !rules.include.is_empty(),
+            max_depth: rules.max_depth,
+            follow_symlinks: rules.follow_symlinks,
+        })
+    }
+
+    /// Whether a scan of `root` would reach `path` and, for files, index it:
+    /// no directory on the way is excluded, too deep or a symlink not to be
+    /// followed, and the file itself is included.
+    pub(crate) fn admits(&self, root: &Path, path: &Path, is_dir: bool) -> bool {
+        let Ok(rel) = path.strip_prefix(root) else {
+            return false;
+        };
+        let names: Vec<String> = rel.components().map(|c| c.as_os_str().to_string_lossy().into_owned()).collect();
+        let mut current = root.to_path_buf();
+        let mut rel = String::new();
+        for (i, name) in names.iter().enumerate() {
+            current.push(name);
+            if !rel.is_empty() {
+                rel.push('/');
+            }
+            rel.push_str(name);
+            if self.excluded(name, &rel) {
+                return false;
+            }
+            let link = std::fs::symlink_metadata(&current).map(|m| m.file_type().is_symlink()).unwrap_or(false);
+            if link && !self.follow_symlinks {
+                return false;
+            }
+            let dir = is_dir || i + 1 < names.len();
+            if dir && self.max_depth.is_some_and(|max| i + 1 > max) {
+                return false;
+            }
+        }
+        match names.last() {
+            Some(name) if !is_dir => self.included(name, &rel),
+            _ => true,
+        }
+    }
+
+    fn excluded(&self, name: &str, rel: &str) -> bool {
+        self.exclude_name.is_match(name) || self.exclude_path.is_match(rel)
+    }
+
+    fn included(&self, name: &str, rel: &str) -> bool {
+        !self.has_include || self.include_name.is_match(name) || self.include_path.is_match(rel)
+    }
+}
+
+/// Shared flag to stop a running scan. Clones observe the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Progress of a running scan, counted across all roots of the call.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScanProgress {
+    /// Supported files found so far.
+    pub discovered: usize,
+    /// Files written to the library (or failed) so far.
+    pub processed: usize,
+    /// Last file processed.
+    pub current: Option<PathBuf>,
+}
+
+/// Receives scan progress; called on the scanning thread.
+pub type ProgressFn = Box<dyn Fn(&ScanProgress) + Send>;
+
+/// Prepared files buffered between the worker threads and the database writer.
+const PIPELINE_DEPTH: usize = 64;
+
+/// How often discovery reports progress while walking.
+const DISCOVERY_REPORT_EVERY: usize = 100;
+
+/// Walks roots and indexes supported files. Hashing and metadata/text
+/// extraction run on a thread pool; all writes happen on the calling thread.
+#[derive(Default)]
+pub struct LibraryScanner {
+    progress: Option<ProgressFn>,
+    cancel: CancelToken,
+    verify: bool,
+    thumbnails: Option<ThumbnailCache>,
+}
 
 impl LibraryScanner {
-    pub fn new() -> Self { Self }
+    pub fn new() -> Self { Self::default() }
 
+    /// Report progress to `f` as files are discovered and processed.
+    pub fn with_progress(mut self, f: impl Fn(&ScanProgress) + Send + 'static) -> Self {
+        self.progress = Some(Box::new(f));
+        self
+    }
+
+    /// Stop early when `token` is cancelled; the report then has `cancelled` set.
+    pub fn with_cancel(mut self, token: CancelToken) -> Self {
+        self.cancel = token;
+        self
+    }
+
+    /// Re-hash every file even when its size and modification time are
+    /// unchanged since it was indexed.
+    pub fn with_verify(mut self, verify: bool) -> Self {
+        self.verify = verify;
+        self
+    }
+
+    /// Render cover thumbnails of new and changed files into `cache`, and
+    /// evict old ones once a scan finishes.
+    pub fn with_thumbnails(mut self, cache: ThumbnailCache) -> Self {
+        self.thumbnails = Some(cache);
+        self
+    }
+
+    /// Scan ad-hoc paths, taking every supported file below them.
     pub fn scan_paths<S: LibraryStore>(&self, store: &S, paths: &[&Path]) -> Result<ScanReport> {
         tracing::info!("Scanning library paths: {}", paths.len());
 
-        let mut total = 0usize;
-        let mut new = 0usize;
-        let mut updated = 0usize;
-        let mut errors: Vec<String> = vec![];
+        let mut progress = ScanProgress::default();
+        let mut report = ScanReport::default();
+        for root in paths {
+            report.merge(self.scan_root(store, root, root, &ScanRules::default(), &mut progress)?);
+            if report.cancelled {
+                break;
+            }
+        }
+        self.evict_thumbnails();
+        Ok(report)
+    }
+
+    /// Scan every enabled stored root with its own rules.
+    pub fn rescan_all<S: LibraryStore + LibraryRootStore>(&self, store: &S) -> Result<ScanReport> {
+        let roots = store.list_roots()?;
+        tracing::info!("Rescanning {} library roots", roots.len());
+
+        let mut progress = ScanProgress::default();
+        let mut report = ScanReport::default();
+        for root in roots.into_iter().filter(|r| r.enabled) {
+            report.merge(self.scan_root(store, &root.path, &root.path, &root.rules, &mut progress)?);
+            if report.cancelled {
+                break;
+            }
+            store.mark_root_scanned(&root.id)?;
+        }
+        self.evict_thumbnails();
+        Ok(report)
+    }
+
+    fn evict_thumbnails(&self) {
+        if let Some(cache) = &self.thumbnails {
+            if let Err(e) = cache.evict() {
+                tracing::warn!("Thumbnail eviction failed: {}", e);
+            }
+        }
+    }
+
+    /// Scan one root, indexing supported files allowed by `rules`.
+    pub fn scan_with_rules<S: LibraryStore>(&self, store: &S, root: &Path, rules: &ScanRules) -> Result<ScanReport> {
+        self.scan_root(store, root, root, rules, &mut ScanProgress::default())
+    }
+
+    /// Scan `dir`, a directory below `root`, as a scan of `root` would:
+    /// patterns match paths relative to `root` and depth counts from it.
+    /// Nothing is scanned when the rules keep the scan out of `dir`.
+    pub fn scan_within<S: LibraryStore>(&self, store: &S, root: &Path, rules: &ScanRules, dir: &Path) -> Result<ScanReport> {
+        if !CompiledRules::new(rules)?.admits(root, dir, true) {
+            return Ok(ScanReport::default());
+        }
+        self.scan_root(store, root, dir, rules, &mut ScanProgress::default())
+    }
+
+    fn report(&self, progress: &ScanProgress) {
+        if let Some(f) = &self.progress {
+            f(progress);
+        }
+    }
+
+    /// Index the files below `start` that a scan of `root` with `rules`
+    /// takes, and flag the ones gone from under `start` missing.
+    fn scan_root<S: LibraryStore>(
+        &self,
+        store: &S,
+        root: &Path,
+        start: &Path,
+        rules: &ScanRules,
+        progress: &mut ScanProgress,
+    ) -> Result<ScanReport> {
+        let files = self.discover(root, start, rules, progress)?;
+        let mut report = ScanReport { total: files.len(), ..Default::default() };
+        if self.cancel.is_cancelled() {
+            report.cancelled = true;
+            return Ok(report);
+        }
+
+        // Files with the size and mtime on record are only marked as seen,
+        // unless their text or cover is still missing
+        let files = if self.verify {
+            files.into_iter().map(Pending::Read).collect()
+        } else {
+            self.skip_unchanged(store, files, progress)
+        };
+
+        // Text already indexed for a hash is not extracted again
+        let indexed = store.content_indexed_hashes()?;
+        let cancel = &self.cancel;
+        let thumbnails = self.thumbnails.as_ref();
+        let (tx, rx) = mpsc::sync_channel::<(&PathBuf, Prepared)>(PIPELINE_DEPTH);
+        std::thread::scope(|s| {
+            s.spawn(|| {
+                files.par_iter().for_each_with(tx, |tx, pending| {
+                    if cancel.is_cancelled() {
+                        return;
+                    }
+                    // A missing cover is not a scan error
+                    let thumbnail = |path: &Path, format, hash: &str| {
+                        if let Some(cache) = thumbnails {
+                            if let Err(e) = cache.create(path, format, hash) {
+                                tracing::debug!("No thumbnail for {:?}: {}", path, e);
+                            }
+                        }
+                    };
+                    let (p, prepared) = match pending {
+                        Pending::Read(p) => (p, Prepared::File(Box::new(PreparedFile::read(p).map(|mut f| {
+                            if !indexed.contains(f.file_hash()) {
+                                f.load_text();
+                            }
+                            thumbnail(f.path(), f.format(), f.file_hash());
+                            f
+                        })))),
+                        Pending::Incomplete(p, file) => {
+                            let pages = (!file.content_indexed)
+                                .then(|| AnyRenderer::open_for(p).and_then(|r| r.extract_text()));
+                            thumbnail(p, None, &file.file_hash);
+                            (p, Prepared::Retry(file.clone(), pages))
+                        }
+                    };
+                    // The writer hung up after a cancel
+                    let _ = tx.send((p, prepared));
+                });
+            });
+
+            for (p, prepared) in rx {
+                if cancel.is_cancelled() {
+                    break;
+                }
+                match prepared {
+                    Prepared::File(file) => match (*file).and_then(|f| store.add_prepared(f)) {
+                        Ok(AddOutcome::Created { .. }) => { report.new += 1; }
+                        Ok(AddOutcome::Updated { .. }) => { report.updated += 1; }
+                        Ok(AddOutcome::Unchanged { .. }) => {}
+                        Err(e) => report.errors.push(format!("{}: {}", p.display(), e)),
+                    },
+                    // Like a first failed extraction, a failed retry only leaves the text unsearchable
+                    Prepared::Retry(file, Some(pages)) => {
+                        if let Err(e) = store.retry_content(&file, p, Some(pages)) {
+                            tracing::warn!("Content indexing failed for {:?}: {}", p, e);
+                        }
+                    }
+                    Prepared::Retry(_, None) => {}
+                }
+                progress.processed += 1;
+                progress.current = Some(p.clone());
+                self.report(progress);
+            }
+        });
+
+        // A partial scan says nothing about what is missing
+        if cancel.is_cancelled() {
+            report.cancelled = true;
+            return Ok(report);
+        }
+
+        report.missing = match store.detect_missing(start) {
+            Ok(n) => n,
+            Err(e) => {
+                report.errors.push(format!("{}: {}", start.display(), e));
+                0
+            }
+        };
+        Ok(report)
+    }
+
+    /// Touch files that have not changed since they were indexed and return
+    /// the rest, along with unchanged files whose text is not indexed or
+    /// whose cover is not cached (evicted, or indexed before thumbnails).
+    fn skip_unchanged<S: LibraryStore>(&self, store: &S, files: Vec<PathBuf>, progress: &mut ScanProgress) -> Vec<Pending> {
+        let mut pending = Vec::with_capacity(files.len());
+        for p in files {
+            if self.cancel.is_cancelled() {
+                break;
+            }
+            match store.touch_unchanged(&p) {
+                Ok(Some(file)) => {
+                    let no_cover = self.thumbnails.as_ref().is_some_and(|cache| {
+                        ThumbnailCache::has_covers(&file.file_type) && !cache.contains(&file.file_hash)
+                    });
+                    if !file.content_indexed || no_cover {
+                        pending.push(Pending::Incomplete(p, file));
+                        continue;
+                    }
+                    progress.processed += 1;
+                    progress.current = Some(p);
+                    self.report(progress);
+                }
+                // Errors surface when the file is read
+                Ok(None) | Err(_) => pending.push(Pending::Read(p)),
+            }
+        }
+        pending
+    }
+
+    /// Supported files under `start` allowed by the `rules` of `root`.
+    fn discover(&self, root: &Path, start: &Path, rules: &ScanRules, progress: &mut ScanProgress) -> Result<Vec<PathBuf>> {
+        let compiled = CompiledRules::new(rules)?;
+        let mut files = vec![];
+
+        // Canonical directories already walked, to survive symlink cycles
+        let mut visited: HashSet<PathBuf> = HashSet::new();
+        let start_depth = start.strip_prefix(root).map_or(0, |rel| rel.components().count());
+        let mut queue: Vec<(PathBuf, usize)> = vec![(start.to_path_buf(), start_depth)];
+        while let Some((p, depth)) = queue.pop() {
+            if self.cancel.is_cancelled() {
+                break;
+            }
+            let is_root = depth == start_depth && p == start;
+            let name = p.file_name().and_then(|s| s.to_str()).unwrap_or_default();
+            let rel = p.strip_prefix(root).unwrap_or(&p).to_string_lossy().replace('\\', "/");
+            if !is_root && compiled.excluded(name, &rel) {
+                continue;
+            }
+
+            let link = std::fs::symlink_metadata(&p).map(|m| m.file_type().is_symlink()).unwrap_or(false);
+            if link && !rules.follow_symlinks && !is_root {
+                continue;
+            }
 
-        let mut queue: Vec<PathBuf> = paths.iter().map(|p| (*p).to_path_buf()).collect();
-        while let Some(p) = queue.pop() {
             match std::fs::metadata(&p) {
                 Ok(m) if m.is_dir() => {
+                    if rules.max_depth.is_some_and(|max| depth > max) {
+                        continue;
+                    }
+                    let canon = std::fs::canonicalize(&p).unwrap_or_else(|_| p.clone());
+                    if !visited.insert(canon) {
+                        continue;
+                    }
                     if let Ok(rd) = std::fs::read_dir(&p) {
-                        for e in rd.flatten() { queue.push(e.path()); }
+                        for e in rd.flatten() { queue.push((e.path(), depth + 1)); }
                     }
                 }
                 Ok(m) if m.is_file() => {
-                    // Filter by supported extensions (simple check)
-                    if let Some(ext) = p.extension().and_then(|s| s.to_str()) {
-                        if blinker_core_common::types::DocumentFormat::from_extension(ext).is_some() {
-                            total += 1;
-                            match store.add_or_update_path(&p) {
-                                Ok(AddOutcome::Created { .. }) => { new += 1; }
-                                Ok(AddOutcome::Updated { .. }) => { updated += 1; }
-                                Ok(AddOutcome::Unchanged { .. }) => {}
-                                Err(e) => errors.push(format!("{}: {}", p.display(), e)),
-                            }
+                    if !is_root && !compiled.included(name, &rel) {
+                        continue;
+                    }
+                    // Known extension, or contents recognised by their signature
+                    if format::is_document(&p) {
+                        files.push(p);
+                        progress.discovered += 1;
+                        if progress.discovered.is_multiple_of(DISCOVERY_REPORT_EVERY) {
+                            self.report(progress);
                         }
                     }
                 }
                 _ => {}
             }
         }
-
-        Ok(ScanReport { total, new, updated, errors })
+        self.report(progress);
+        Ok(files)
     }
 }
 
+/// A discovered file left for the preparation pool.
+enum Pending {
+    /// New or changed: hash it and extract everything.
+    Read(PathBuf),
+    /// Unchanged, but its text or cover is missing: redo only those.
+    Incomplete(PathBuf, UnchangedFile),
+}
+
+/// What the pool hands to the writer for a `Pending` file.
+enum Prepared {
+    File(Box<Result<PreparedFile>>),
+    /// Page text for `retry_content`, when it was missing.
+    Retry(UnchangedFile, Option<Result<Vec<PageText>>>),
+}
+
+#[derive(Default)]
 pub struct ScanReport {
     pub total: usize,
     pub new: usize,
     pub updated: usize,
+    /// Known items under the scanned roots whose files are gone.
+    pub missing: usize,
     pub errors: Vec<String>,
+    /// The scan was stopped through its `CancelToken` before finishing.
+    pub cancelled: bool,
+}
+
+impl ScanReport {
+    fn merge(&mut self, other: ScanReport) {
+        self.total += other.total;
+        self.new += other.new;
+        self.updated += other.updated;
+        self.missing += other.missing;
+        self.errors.extend(other.errors);
+        self.cancelled |= other.cancelled;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::library_with;
+    use std::time::{Duration, SystemTime};
+
+    /// Replace a file's contents, then give it modification time `mtime`.
+    fn rewrite(path: &Path, text: &str, mtime: SystemTime) {
+        std::fs::write(path, text).unwrap();
+        std::fs::File::options().write(true).open(path).unwrap().set_modified(mtime).unwrap();
+    }
+
+    fn title(store: &impl LibraryStore, id: &str) -> String {
+        store.get_item(id).unwrap().unwrap().metadata.title
+    }
+
+    #[test]
+    fn admits_follows_root_rules() {
+        let rules = ScanRules {
+            include: vec!["*.pdf".into(), "*.md".into()],
+            exclude: vec!["node_modules".into(), "Downloads/tmp".into()],
+            max_depth: Some(1),
+            follow_symlinks: false,
+        };
+        let compiled = CompiledRules::new(&rules).unwrap();
+        let root = Path::new("/nonexistent/library");
+        assert!(compiled.admits(root, &root.join("book.pdf"), false));
+        assert!(compiled.admits(root, &root.join("papers/a.pdf"), false));
+        assert!(!compiled.admits(root, &root.join("papers/old/a.pdf"), false));
+        assert!(!compiled.admits(root, &root.join("papers/old"), true));
+        assert!(!compiled.admits(root, &root.join("node_modules/pkg.md"), false));
+        assert!(!compiled.admits(root, &root.join("Downloads/tmp/a.pdf"), false));
+        assert!(compiled.admits(root, &root.join("Downloads/a.pdf"), false));
+        assert!(!compiled.admits(root, &root.join("notes.txt"), false));
+        assert!(!compiled.admits(root, Path::new("/elsewhere/book.pdf"), false));
+    }
+
+    #[test]
+    fn rescans_hash_only_changed_files() {
+        let (dir, db, ids) = library_with(&["alpha"]);
+        let path = dir.path().join("alpha.md");
+        let indexed_mtime = std::fs::metadata(&path).unwrap().modified().unwrap();
+
+        // Same size and mtime: taken as unchanged without reading the file
+        rewrite(&path, "# omega", indexed_mtime);
+        let report = LibraryScanner::new().scan_paths(&db, &[dir.path()]).unwrap();
+        assert_eq!((report.total, report.new, report.updated), (1, 0, 0));
+        assert_eq!(title(&db, &ids[0]), "alpha");
+
+        // Verifying re-hashes it anyway
+        let report = LibraryScanner::new().with_verify(true).scan_paths(&db, &[dir.path()]).unwrap();
+        assert_eq!((report.new, report.updated), (0, 1));
+        assert_eq!(title(&db, &ids[0]), "omega");
+
+        // A touched file is re-indexed
+        rewrite(&path, "# gamma", indexed_mtime + Duration::from_secs(60));
+        let report = LibraryScanner::new().scan_paths(&db, &[dir.path()]).unwrap();
+        assert_eq!((report.new, report.updated), (0, 1));
+        assert_eq!(title(&db, &ids[0]), "gamma");
+    }
+
+    #[test]
+    fn scan_within_keeps_root_rules() {
+        let (dir, db, _) = library_with(&[]);
+        let root = dir.path();
+        for name in ["top.md", "books/a.md", "books/deep/b.md", "books/skip/c.md", "skip/d.md"] {
+            let path = root.join(name);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, format!("# {}", name)).unwrap();
+        }
+        let rules = ScanRules { exclude: vec!["skip".into()], max_depth: Some(2), ..Default::default() };
+
+        let report = LibraryScanner::new().scan_within(&db, root, &rules, &root.join("books")).unwrap();
+        assert_eq!((report.total, report.new), (2, 2));
+        let report = LibraryScanner::new().scan_within(&db, root, &rules, &root.join("skip")).unwrap();
+        assert_eq!(report.total, 0);
+        let mut titles: Vec<String> = db.query(&crate::LibraryQuery::default()).unwrap().into_iter().map(|i| i.metadata.title).collect();
+        titles.sort();
+        assert_eq!(titles, ["books/a.md", "books/deep/b.md"]);
+    }
+
+    #[test]
+    fn cancelled_scan_leaves_a_consistent_library() {
+        let (dir, db, ids) = library_with(&["gone"]);
+        std::fs::remove_file(dir.path().join("gone.md")).unwrap();
+        for i in 0..40 {
+            std::fs::write(dir.path().join(format!("{:02}.md", i)), format!("# note {}", i)).unwrap();
+        }
+
+        let token = CancelToken::new();
+        let cancel = token.clone();
+        let report = LibraryScanner::new()
+            .with_cancel(token)
+            .with_progress(move |p| {
+                if p.processed >= 5 {
+                    cancel.cancel();
+                }
+            })
+            .scan_paths(&db, &[dir.path()])
+            .unwrap();
+        assert!(report.cancelled);
+        assert!(report.new >= 5 && report.new < 40, "{} new", report.new);
+
+        // Items written before the cancel are complete, and a partial scan
+        // flags nothing missing
+        let items = db.query(&crate::LibraryQuery::default()).unwrap();
+        assert_eq!(items.len(), report.new + 1);
+        assert_eq!(report.missing, 0);
+        assert!(items.iter().all(|i| i.missing_since.is_none()));
+        let orphans: i64 = db.conn
+            .query_row("SELECT COUNT(*) FROM library_item i WHERE NOT EXISTS(SELECT 1 FROM item_location l WHERE l.item_id = i.id)", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(orphans, 0);
+
+        let report = LibraryScanner::new().scan_paths(&db, &[dir.path()]).unwrap();
+        assert!(!report.cancelled);
+        assert_eq!(db.query(&crate::LibraryQuery::default()).unwrap().len(), 41);
+        assert_eq!(report.missing, 1);
+        assert!(db.get_item(&ids[0]).unwrap().unwrap().missing_since.is_some());
+    }
 }
@@ -0,0 +1,124 @@
+//! Translation of user search text into FTS5 match expressions.
+//!
+//! Supported syntax:
+//! - bare words match as prefixes (`asim` finds "Asimov")
+//! - `"quoted phrases"` match exactly, in order
+//! - `title:`, `author:`, `subject:` and `tags:` restrict the next word or phrase to one column
+//!
+//! Everything else is quoted before reaching FTS5, so user input can never
+//! produce an FTS5 syntax error.
+
+/// Columns of `library_fts` that may be used as `column:` filters.
+const FILTER_COLUMNS: &[&str] = &["title", "author", "subject", "tags"];
+
+/// Build an FTS5 MATCH expression from free-form search text.
+///
+/// Terms are combined with implicit AND. Returns `None` when the text holds
+/// nothing searchable.
+pub(crate) fn match_expression(text: &str) -> Option<String> {
+    match_expression_for(text, FILTER_COLUMNS)
+}
+
+/// Like [`match_expression`], accepting only the given column filters.
+/// Unknown `column:` prefixes are searched as ordinary text.
+pub(crate) fn match_expression_for(text: &str, columns: &[&str]) -> Option<String> {
+    let mut parts: Vec<String> = vec![];
+    let mut rest = text.trim_start();
+
+    while !rest.is_empty() {
+        let mut column: Option<&str> = None;
+        if let Some((prefix, after)) = rest.split_once(':') {
+            if let Some(col) = columns.iter().find(|c| c.eq_ignore_ascii_case(prefix)) {
+                column = Some(col);
+                rest = after.trim_start();
+            }
+        }
+
+        let (term, is_phrase, after) = if let Some(quoted) = rest.strip_prefix('"') {
+            match quoted.find('"') {
+                Some(end) => (&quoted[..end], true, &quoted[end + 1..]),
+                None => (quoted, true, ""),
+            }
+        } else {
+            let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+            (&rest[..end], false, &rest[end..])
+        };
+        rest = after.trim_start();
+
+        if !term.chars().any(char::is_alphanumeric) {
+            continue;
+        }
+        let mut part = format!("\"{}\"", term.replace('"', "\"\""));
+        if !is_phrase {
+            part.push('*');
+        }
+        if let Some(col) = column {
+            part = format!("{} : {}", col, part);
+        }
+        parts.push(part);
+    }
+
+    if parts.is_empty() {
+        None
+    } else {
+        Some(parts.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::library_with;
+    use crate::{LibraryQuery, LibraryStore};
+
+    #[test]
+    fn bare_words_match_as_prefixes() {
+        assert_eq!(match_expression("asim"), Some("\"asim\"*".into()));
+        assert_eq!(match_expression("  isaac   asimov "), Some("\"isaac\"* \"asimov\"*".into()));
+    }
+
+    #[test]
+    fn quoted_phrases_match_exactly() {
+        assert_eq!(match_expression("\"the end\" of"), Some("\"the end\" \"of\"*".into()));
+        // An unclosed quote runs to the end of the text
+        assert_eq!(match_expression("\"the end"), Some("\"the end\"".into()));
+    }
+
+    #[test]
+    fn column_prefixes_restrict_the_next_term() {
+        assert_eq!(
+            match_expression("Author:asimov title: \"the end\""),
+            Some("author : \"asimov\"* title : \"the end\"".into())
+        );
+        assert_eq!(match_expression("isbn:123"), Some("\"isbn:123\"*".into()));
+        assert_eq!(match_expression_for("title:dune", &[]), Some("\"title:dune\"*".into()));
+    }
+
+    #[test]
+    fn syntax_is_quoted_away() {
+        assert_eq!(match_expression("a\"b OR c*"), Some("\"a\"\"b\"* \"OR\"* \"c*\"*".into()));
+        assert_eq!(match_expression("- ( ) *"), None);
+        assert_eq!(match_expression("   "), None);
+    }
+
+    #[test]
+    fn tag_search_follows_renames_and_deletes() {
+        let (_dir, db, ids) = library_with(&["dune", "emma"]);
+        let search = |text: &str| -> Vec<String> {
+            let query = LibraryQuery { text: Some(text.into()), ..Default::default() };
+            db.query(&query).unwrap().into_iter().map(|i| i.metadata.title).collect()
+        };
+        let tag = db.create_tag("scifi", None).unwrap();
+        db.assign_tag(&ids[0], &tag.id).unwrap();
+        assert_eq!(search("scifi"), ["dune"]);
+        assert_eq!(search("tags:scifi"), ["dune"]);
+
+        db.rename_tag(&tag.id, "Space Opera").unwrap();
+        assert!(search("scifi").is_empty());
+        assert_eq!(search("tags:opera"), ["dune"]);
+
+        db.delete_tag(&tag.id).unwrap();
+        assert!(search("opera").is_empty());
+        assert_eq!(search("dune"), ["dune"]);
+    }
+}
@@ -0,0 +1,98 @@
+//! Items grouped into series and ordered by volume.
+
+use blinker_core_common::{BlinkerError, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{LibraryDatabase, LibraryItem, LibraryQuery, LibraryStore, SortKey};
+
+/// A series and how many items of it are in the library.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeriesSummary {
+    pub name: String,
+    pub items: usize,
+}
+
+/// Queries over series.
+pub trait SeriesStore {
+    /// Every series, ordered by name. Names differing only in case are one series.
+    fn list_series(&self) -> Result<Vec<SeriesSummary>>;
+    /// Items of a series by index; unnumbered ones come last, by title.
+    fn series_items(&self, name: &str) -> Result<Vec<LibraryItem>>;
+    /// The item following `item_id` in its series, if any.
+    fn next_in_series(&self, item_id: &str) -> Result<Option<LibraryItem>>;
+}
+
+impl SeriesStore for LibraryDatabase {
+    fn list_series(&self) -> Result<Vec<SeriesSummary>> {
+        let mut stmt = self.conn
+            .prepare(
+                "SELECT series, COUNT(*) FROM library_item WHERE series IS NOT NULL
+                 GROUP BY series COLLATE NOCASE ORDER BY series COLLATE NOCASE",
+            )
+            .map_err(|e| BlinkerError::Database(format!("prepare series: {}", e)))?;
+        let series = stmt
+            .query_map([], |row| {
+                let items: i64 = row.get(1)?;
+                Ok(SeriesSummary { name: row.get(0)?, items: items as usize })
+            })
+            .map_err(|e| BlinkerError::Database(format!("list series: {}", e)))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .map_err(|e| BlinkerError::Database(format!("row: {}", e)))?;
+        Ok(series)
+    }
+
+    fn series_items(&self, name: &str) -> Result<Vec<LibraryItem>> {
+        self.query(&LibraryQuery {
+            series: Some(name.to_string()),
+            sort: SortKey::Series,
+            ..Default::default()
+        })
+    }
+
+    fn next_in_series(&self, item_id: &str) -> Result<Option<LibraryItem>> {
+        let item = self.get_item(item_id)?
+            .ok_or_else(|| BlinkerError::NotFound(format!("item {}", item_id)))?;
+        let Some(series) = &item.metadata.series else {
+            return Ok(None);
+        };
+        let items = self.series_items(series)?;
+        Ok(items
+            .iter()
+            .position(|i| i.id == item.id)
+            .and_then(|pos| items.into_iter().nth(pos + 1)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::library_with;
+    use crate::MetadataField;
+
+    #[test]
+    fn series_are_ordered_by_index_with_unnumbered_last() {
+        let (_dir, db, ids) = library_with(&["two", "one", "extra", "ten", "loose", "other"]);
+        let series = [Some("Saga"), Some("saga"), Some("Saga"), Some("SAGA"), None, Some("Annals")];
+        let indexes = [Some("2"), Some("1"), None, Some("10"), None, Some("1")];
+        for ((id, series), index) in ids.iter().zip(series).zip(indexes) {
+            db.update_metadata(id, &[
+                (MetadataField::Series, series.map(Into::into)),
+                (MetadataField::SeriesIndex, index.map(Into::into)),
+            ])
+            .unwrap();
+        }
+
+        let summaries: Vec<(String, usize)> = db.list_series().unwrap().into_iter().map(|s| (s.name.to_lowercase(), s.items)).collect();
+        assert_eq!(summaries, [("annals".to_string(), 1), ("saga".to_string(), 4)]);
+        let titles: Vec<String> = db.series_items("Saga").unwrap().into_iter().map(|i| i.metadata.title).collect();
+        assert_eq!(titles, ["one", "two", "ten", "extra"]);
+
+        let next = |id: &str| db.next_in_series(id).unwrap().map(|i| i.metadata.title);
+        assert_eq!(next(&ids[1]).as_deref(), Some("two"));
+        assert_eq!(next(&ids[3]).as_deref(), Some("extra"));
+        assert_eq!(next(&ids[2]), None);
+        assert_eq!(next(&ids[5]), None);
+        assert_eq!(next(&ids[4]), None);
+        assert!(db.next_in_series("no-such-item").is_err());
+    }
+}
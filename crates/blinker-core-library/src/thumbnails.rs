@@ -0,0 +1,181 @@
+//! Cover thumbnails cached on disk by file hash.
+//!
+//! Thumbnails are JPEGs named `<file_hash>-<max_edge>.jpg`, so identical
+//! copies share one and changing the size renders fresh ones. Reading a
+//! thumbnail bumps its modification time; [`ThumbnailCache::evict`] removes
+//! the least recently used files once the cache outgrows its capacity.
+
+use blinker_core_common::{types::DocumentFormat, BlinkerError, Result};
+use blinker_core_render::{thumbnail, AnyRenderer};
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::LibraryItem;
+
+/// Longest edge of a thumbnail in pixels unless configured otherwise.
+pub const DEFAULT_MAX_EDGE: u32 = 320;
+/// Cache size above which eviction starts unless configured otherwise.
+pub const DEFAULT_CAPACITY: u64 = 256 * 1024 * 1024;
+
+/// A directory of cover thumbnails.
+#[derive(Debug, Clone)]
+pub struct ThumbnailCache {
+    dir: PathBuf,
+    max_edge: u32,
+    capacity: u64,
+}
+
+impl ThumbnailCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into(), max_edge: DEFAULT_MAX_EDGE, capacity: DEFAULT_CAPACITY }
+    }
+
+    /// Longest edge, in pixels, thumbnails are scaled to fit.
+    pub fn with_max_edge(mut self, max_edge: u32) -> Self {
+        self.max_edge = max_edge.max(1);
+        self
+    }
+
+    /// Total bytes the cache may hold before `evict` removes old thumbnails.
+    pub fn with_capacity(mut self, bytes: u64) -> Self {
+        self.capacity = bytes;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path_for(&self, file_hash: &str) -> PathBuf {
+        self.dir.join(format!("{}-{}.jpg", file_hash, self.max_edge))
+    }
+
+    /// Whether a thumbnail for `file_hash` is cached, without marking it used.
+    pub fn contains(&self, file_hash: &str) -> bool {
+        self.path_for(file_hash).is_file()
+    }
+
+    /// Whether items of `file_type` can have a cover at all; plain text and
+    /// RAR comics never get one.
+    pub fn has_covers(file_type: &str) -> bool {
+        matches!(
+            DocumentFormat::from_extension(file_type),
+            Some(DocumentFormat::Pdf | DocumentFormat::Epub | DocumentFormat::Cbz)
+        )
+    }
+
+    /// Path of the cached thumbnail for `file_hash`, if there is one.
+    pub fn get(&self, file_hash: &str) -> Option<PathBuf> {
+        let path = self.path_for(file_hash);
+        if !path.is_file() {
+            return None;
+        }
+        // Mark as recently used; failing to only makes it an earlier eviction candidate
+        if let Ok(file) = std::fs::File::options().append(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+        Some(path)
+    }
+
+    /// Render and store the thumbnail of a document unless it is cached.
+    /// Returns `None` for documents without a cover (plain text, EPUBs
+    /// declaring none). `format` skips detection when already known.
+    pub fn create(&self, path: &Path, format: Option<DocumentFormat>, file_hash: &str) -> Result<Option<PathBuf>> {
+        let target = self.path_for(file_hash);
+        if target.is_file() {
+            return Ok(Some(target));
+        }
+        let renderer = match format {
+            Some(format) => AnyRenderer::with_format(path, format),
+            None => AnyRenderer::open_for(path)?,
+        };
+        let Some(cover) = renderer.render_cover()? else {
+            return Ok(None);
+        };
+        let jpeg = thumbnail::encode_thumbnail(cover, self.max_edge)?;
+
+        // Written under a temporary name so readers never see a partial file
+        std::fs::create_dir_all(&self.dir).map_err(BlinkerError::Io)?;
+        let partial = self.dir.join(format!(".{}.tmp", uuid::Uuid::new_v4()));
+        std::fs::write(&partial, jpeg).map_err(BlinkerError::Io)?;
+        std::fs::rename(&partial, &target).map_err(BlinkerError::Io)?;
+        Ok(Some(target))
+    }
+
+    /// Cached thumbnail of an item, rendering it first if needed.
+    pub fn get_or_create(&self, item: &LibraryItem) -> Result<Option<PathBuf>> {
+        match self.get(&item.file_hash) {
+            Some(path) => Ok(Some(path)),
+            None => self.create(&item.file_path, None, &item.file_hash),
+        }
+    }
+
+    /// Delete least recently used thumbnails until the cache fits its
+    /// capacity. Returns the number of files removed.
+    pub fn evict(&self) -> Result<usize> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(0),
+            Err(e) => return Err(BlinkerError::Io(e)),
+        };
+        let mut files: Vec<(SystemTime, u64, PathBuf)> = entries
+            .flatten()
+            .filter(|e| e.path().extension().is_some_and(|ext| ext == "jpg"))
+            .filter_map(|e| {
+                let meta = e.metadata().ok()?;
+                Some((meta.modified().unwrap_or(SystemTime::UNIX_EPOCH), meta.len(), e.path()))
+            })
+            .collect();
+        let mut total: u64 = files.iter().map(|(_, len, _)| len).sum();
+        if total <= self.capacity {
+            return Ok(0);
+        }
+
+        files.sort();
+        let mut removed = 0;
+        for (_, len, path) in files {
+            if total <= self.capacity {
+                break;
+            }
+            match std::fs::remove_file(&path) {
+                Ok(()) => {
+                    total -= len;
+                    removed += 1;
+                }
+                Err(e) => tracing::warn!("Could not evict thumbnail {:?}: {}", path, e),
+            }
+        }
+        tracing::debug!("Evicted {} thumbnails from {:?}", removed, self.dir);
+        Ok(removed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn evict_removes_least_recently_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ThumbnailCache::new(dir.path()).with_capacity(250);
+        assert_eq!(cache.evict().unwrap(), 0);
+
+        let now = SystemTime::now();
+        for (hash, age) in [("old", 300), ("older", 400), ("new", 100)] {
+            let path = cache.path_for(hash);
+            std::fs::write(&path, [0u8; 100]).unwrap();
+            std::fs::File::options().write(true).open(&path).unwrap().set_modified(now - Duration::from_secs(age)).unwrap();
+        }
+        std::fs::write(dir.path().join("notes.txt"), [0u8; 1000]).unwrap();
+
+        // Reading the oldest makes it the most recently used
+        assert_eq!(cache.get("older"), Some(cache.path_for("older")));
+        assert_eq!(cache.evict().unwrap(), 1);
+        assert!(!cache.contains("old"));
+        assert!(cache.contains("older"));
+        assert!(cache.contains("new"));
+        assert!(dir.path().join("notes.txt").is_file());
+        assert_eq!(cache.evict().unwrap(), 0);
+    }
+}
@@ -0,0 +1,216 @@
+//! Live library updates from file-system events.
+//!
+//! A `LibraryWatcher` subscribes to a set of roots, debounces the raw events
+//! per path and applies them to a `LibraryStore` on a background thread:
+//! new or modified documents are (re)indexed, deleted or moved-away ones are
+//! flagged missing. Each applied change is reported through a callback.
+//!
+//! Changes below a stored `LibraryRoot` go through that root's rules, so a
+//! file appearing in an excluded folder is not indexed; paths watched
+//! without a stored root take every supported file, as `scan_paths` does.
+
+use blinker_core_common::{BlinkerError, Result};
+use blinker_core_render::format;
+use notify::{RecursiveMode, Watcher};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::scanner::CompiledRules;
+use crate::{AddOutcome, LibraryRoot, LibraryRootStore, LibraryScanner, LibraryStore};
+
+/// Default quiet period before a path's events are applied.
+pub const DEFAULT_DEBOUNCE: Duration = Duration::from_millis(750);
+
+/// A change applied to the library by the watcher.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum LibraryChange {
+    Added { id: String, path: PathBuf },
+    Updated { id: String, path: PathBuf },
+    /// Files at or under `path` disappeared; `count` items are now flagged missing.
+    Missing { path: PathBuf, count: usize },
+    /// A directory appeared and was scanned.
+    Scanned { path: PathBuf, new: usize, updated: usize },
+    Error { path: PathBuf, message: String },
+}
+
+enum Message {
+    Fs(notify::Result<notify::Event>),
+    Stop,
+}
+
+/// Background watcher keeping the library in sync with its roots.
+/// Watching stops when the value is dropped.
+pub struct LibraryWatcher {
+    watcher: Option<notify::RecommendedWatcher>,
+    tx: mpsc::Sender<Message>,
+    worker: Option<JoinHandle<()>>,
+}
+
+impl LibraryWatcher {
+    /// Watch `roots` recursively, applying changes to `store` after `debounce`
+    /// of quiet per path and reporting each through `on_change`.
+    pub fn spawn<S, F>(store: S, roots: &[PathBuf], debounce: Duration, on_change: F) -> Result<Self>
+    where
+        S: LibraryStore + LibraryRootStore + Send + 'static,
+        F: Fn(LibraryChange) + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+        let fs_tx = tx.clone();
+        let mut watcher = notify::recommended_watcher(move |ev| {
+            let _ = fs_tx.send(Message::Fs(ev));
+        })
+        .map_err(|e| BlinkerError::Io(std::io::Error::other(e)))?;
+
+        for root in roots {
+            // Events carry paths under the watched path as given; canonical roots
+            // keep them comparable with the canonical paths stored for items.
+            let root = std::fs::canonicalize(root).unwrap_or_else(|_| root.clone());
+            watcher
+                .watch(&root, RecursiveMode::Recursive)
+                .map_err(|e| BlinkerError::Io(std::io::Error::other(format!("watch {}: {}", root.display(), e))))?;
+            tracing::info!("Watching library root {:?}", root);
+        }
+
+        let worker = std::thread::Builder::new()
+            .name("library-watcher".into())
+            .spawn(move || Self::run(store, rx, debounce, on_change))?;
+
+        Ok(Self { watcher: Some(watcher), tx, worker: Some(worker) })
+    }
+
+    /// Stop watching and wait for pending changes to be applied.
+    pub fn stop(mut self) {
+        self.shutdown();
+    }
+
+    fn shutdown(&mut self) {
+        self.watcher.take();
+        let _ = self.tx.send(Message::Stop);
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+
+    fn run<S: LibraryStore + LibraryRootStore, F: Fn(LibraryChange)>(
+        store: S,
+        rx: mpsc::Receiver<Message>,
+        debounce: Duration,
+        on_change: F,
+    ) {
+        // Last event time per path; a path is applied once it has been quiet for `debounce`.
+        let mut pending: HashMap<PathBuf, Instant> = HashMap::new();
+        loop {
+            let msg = match pending.values().min() {
+                Some(oldest) => rx.recv_timeout((*oldest + debounce).saturating_duration_since(Instant::now())),
+                None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            match msg {
+                Ok(Message::Fs(Ok(event))) => {
+                    let now = Instant::now();
+                    for path in event.paths {
+                        pending.insert(path, now);
+                    }
+                }
+                Ok(Message::Fs(Err(e))) => tracing::warn!("Watch error: {}", e),
+                Ok(Message::Stop) | Err(RecvTimeoutError::Disconnected) => {
+                    Self::apply(&store, pending.drain().map(|(p, _)| p).collect(), &on_change);
+                    return;
+                }
+                Err(RecvTimeoutError::Timeout) => {}
+            }
+
+            let now = Instant::now();
+            let ready: Vec<PathBuf> = pending
+                .iter()
+                .filter(|(_, at)| now.duration_since(**at) >= debounce)
+                .map(|(p, _)| p.clone())
+                .collect();
+            for p in &ready {
+                pending.remove(p);
+            }
+            if !ready.is_empty() {
+                Self::apply(&store, ready, &on_change);
+            }
+        }
+    }
+
+    fn apply<S: LibraryStore + LibraryRootStore, F: Fn(LibraryChange)>(
+        store: &S,
+        mut paths: Vec<PathBuf>,
+        on_change: &F,
+    ) {
+        // Rules are read per batch so edits to a root apply without a restart
+        let roots = match Self::stored_rules(store) {
+            Ok(roots) => roots,
+            Err(e) => {
+                tracing::warn!("Could not read library roots: {}", e);
+                vec![]
+            }
+        };
+        // Index what exists before flagging what is gone, so a rename relinks
+        // the item to its new path before the old path is checked.
+        paths.sort_by_key(|p| !p.exists());
+        for path in paths {
+            let root = roots.iter().find(|(root, _)| path.starts_with(&root.path));
+            let change = if path.is_dir() {
+                let scanner = LibraryScanner::new();
+                match root {
+                    Some((root, _)) => scanner.scan_within(store, &root.path, &root.rules, &path),
+                    None => scanner.scan_paths(store, &[path.as_path()]),
+                }
+                .map(|r| (r.new + r.updated > 0).then_some(LibraryChange::Scanned { path: path.clone(), new: r.new, updated: r.updated }))
+            } else if path.is_file() {
+                if !Self::is_supported(&path) {
+                    continue;
+                }
+                if let Some((root, compiled)) = root {
+                    if !compiled.admits(&root.path, &path, false) {
+                        continue;
+                    }
+                }
+                store.add_or_update_path(&path).map(|outcome| match outcome {
+                    AddOutcome::Created { id } => Some(LibraryChange::Added { id, path: path.clone() }),
+                    AddOutcome::Updated { id } => Some(LibraryChange::Updated { id, path: path.clone() }),
+                    AddOutcome::Unchanged { .. } => None,
+                })
+            } else {
+                store
+                    .detect_missing(&path)
+                    .map(|count| (count > 0).then_some(LibraryChange::Missing { path: path.clone(), count }))
+            };
+
+            match change {
+                Ok(Some(change)) => on_change(change),
+                Ok(None) => {}
+                Err(e) => on_change(LibraryChange::Error { path, message: e.to_string() }),
+            }
+        }
+    }
+
+    fn is_supported(path: &Path) -> bool {
+        format::is_document(path)
+    }
+
+    /// Stored roots with their compiled rules, deepest first so the first
+    /// root containing a path is the one governing it.
+    fn stored_rules<S: LibraryRootStore>(store: &S) -> Result<Vec<(LibraryRoot, CompiledRules)>> {
+        let mut roots = store
+            .list_roots()?
+            .into_iter()
+            .map(|root| CompiledRules::new(&root.rules).map(|compiled| (root, compiled)))
+            .collect::<Result<Vec<_>>>()?;
+        roots.sort_by_key(|(root, _)| std::cmp::Reverse(root.path.components().count()));
+        Ok(roots)
+    }
+}
+
+impl Drop for LibraryWatcher {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
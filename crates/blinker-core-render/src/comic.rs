@@ -19,7 +19,7 @@ impl ComicRenderer {
     }
 
     /// Check if a filename is a supported image format
-    fn is_image_file(filename: &str) -> bool {
+    pub(crate) fn is_image_file(filename: &str) -> bool {
         let lower = filename.to_lowercase();
         lower.ends_with(".jpg") ||
         lower.ends_with(".jpeg") ||
@@ -43,7 +43,7 @@ impl DocumentRenderer for ComicRenderer {
         tracing::info!("Opening Comic archive: {:?}", path);
 
         let file = std::fs::File::open(path)
-            .map_err(|e| BlinkerError::Io(e))?;
+            .map_err(BlinkerError::Io)?;
 
         let mut archive = zip::ZipArchive::new(file)
             .map_err(|e| BlinkerError::Parsing(format!("Failed to open CBZ archive: {}", e)))?;
@@ -64,7 +64,7 @@ impl DocumentRenderer for ComicRenderer {
             if Self::is_image_file(&filename) && !file.is_dir() {
                 let mut buffer = Vec::new();
                 file.read_to_end(&mut buffer)
-                    .map_err(|e| BlinkerError::Io(e))?;
+                    .map_err(BlinkerError::Io)?;
 
                 images.push((filename, buffer));
             }
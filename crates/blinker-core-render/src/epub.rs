@@ -17,6 +17,19 @@ impl EpubRenderer {
             .to_string()
     }
 
+    /// The cover image an EPUB declares, decoded; `None` when it declares none.
+    pub fn cover(path: &Path) -> Result<Option<RenderedPage>> {
+        let mut doc = epub::doc::EpubDoc::new(path)
+            .map_err(|e| BlinkerError::Parsing(format!("Failed to load EPUB: {}", e)))?;
+        let Some((bytes, _mime)) = doc.get_cover() else {
+            return Ok(None);
+        };
+        let image = image::load_from_memory(&bytes)
+            .map_err(|e| BlinkerError::Rendering(format!("Failed to decode cover: {}", e)))?
+            .to_rgba8();
+        Ok(Some(RenderedPage { width: image.width(), height: image.height(), pixels: image.into_raw() }))
+    }
+
     /// Extract all text from HTML (simple implementation)
     fn extract_text_from_html(html: &str) -> String {
         // Very basic HTML text extraction
@@ -158,7 +171,7 @@ impl DocumentRenderer for EpubRenderer {
                         for col in 0..(metrics.width as i32) {
                             let dx = gx + col;
                             if dx < 0 || dx >= w_i { continue; }
-                            let src_alpha = bitmap[(row as usize) * (metrics.width as usize) + (col as usize)];
+                            let src_alpha = bitmap[(row as usize) * metrics.width + (col as usize)];
                             if src_alpha == 0 { continue; }
                             let idx = ((dy as u32) * width + (dx as u32)) as usize * 4;
                             let val = 255u8.saturating_sub(src_alpha);
@@ -219,4 +232,11 @@ impl DocumentRenderer for EpubRenderer {
 
         Ok(matches)
     }
+
+    fn page_text(&self, page: usize) -> Result<String> {
+        self.chapters
+            .get(page.saturating_sub(1))
+            .map(|html| Self::extract_text_from_html(html))
+            .ok_or_else(|| BlinkerError::Rendering(format!("Invalid page index: {}", page)))
+    }
 }
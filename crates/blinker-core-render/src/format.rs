@@ -0,0 +1,301 @@
+//! Format detection from file contents.
+//!
+//! Binary formats are recognised by their magic bytes (`%PDF-`, `Rar!`) and,
+//! for zip archives, by what they contain: an `application/epub+zip`
+//! `mimetype` entry or an OPF container makes an EPUB, nothing but images
+//! makes a comic. Text formats have no signature and are told apart by
+//! extension alone; a `.md` or `.txt` file is never re-read as a binary
+//! format, whatever its first bytes quote. Files with an extension of no
+//! known format are only matched by the unambiguous signatures: a PDF or RAR
+//! header, or a zip starting with an EPUB `mimetype` entry.
+
+use blinker_core_common::{types::DocumentFormat, BlinkerError, Result};
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::comic::ComicRenderer;
+
+/// Bytes read from the start of a file, enough for the longest signature.
+const HEADER_LEN: usize = 8;
+
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const RAR_MAGIC: &[u8] = b"Rar!\x1a\x07";
+const PDF_MAGIC: &[u8] = b"%PDF-";
+const EPUB_MIMETYPE: &str = "application/epub+zip";
+
+/// How far the contents of a file may decide its format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Sniffing {
+    /// Text formats are taken at their extension.
+    Never,
+    /// Only signatures no other kind of file starts with.
+    Signatures,
+    /// Signatures, and a zip's entries.
+    Full,
+}
+
+/// Format implied by the contents of `path`, if it has a recognised signature.
+pub fn sniff(path: &Path) -> Result<Option<DocumentFormat>> {
+    sniff_as(path, Sniffing::Full)
+}
+
+fn sniff_as(path: &Path, sniffing: Sniffing) -> Result<Option<DocumentFormat>> {
+    if sniffing == Sniffing::Never {
+        return Ok(None);
+    }
+    let mut header = Vec::with_capacity(HEADER_LEN);
+    File::open(path)?.take(HEADER_LEN as u64).read_to_end(&mut header)?;
+
+    if header.starts_with(RAR_MAGIC) {
+        return Ok(Some(DocumentFormat::Cbr));
+    }
+    if header.starts_with(PDF_MAGIC) {
+        return Ok(Some(DocumentFormat::Pdf));
+    }
+    if header.starts_with(ZIP_MAGIC) {
+        return match sniffing {
+            Sniffing::Full => inspect_zip(path),
+            _ => epub_signature(path),
+        };
+    }
+    Ok(None)
+}
+
+/// EPUBs must store their `mimetype` entry first, which makes it a
+/// signature; any other zip could be anything.
+fn epub_signature(path: &Path) -> Result<Option<DocumentFormat>> {
+    let mut archive = zip::ZipArchive::new(File::open(path)?)
+        .map_err(|e| BlinkerError::Parsing(format!("Failed to read zip archive: {}", e)))?;
+    let Ok(mut first) = archive.by_index(0) else {
+        return Ok(None);
+    };
+    let mut mimetype = String::new();
+    let is_epub = first.name() == "mimetype"
+        && first.by_ref().take(EPUB_MIMETYPE.len() as u64 + 2).read_to_string(&mut mimetype).is_ok()
+        && mimetype.trim() == EPUB_MIMETYPE;
+    Ok(is_epub.then_some(DocumentFormat::Epub))
+}
+
+/// Entries a comic archive may carry besides its pages: ComicInfo.xml and
+/// similar sidecars, and files left behind by the archiving OS.
+fn is_comic_extra(name: &str) -> bool {
+    let lower = name.to_lowercase();
+    let file = lower.rsplit('/').next().unwrap_or(&lower);
+    lower.ends_with('/')
+        || lower.starts_with("__macosx/")
+        || file == "comicinfo.xml"
+        || file == ".ds_store"
+        || file == "thumbs.db"
+        || file.ends_with(".nfo")
+        || file.ends_with(".sfv")
+}
+
+/// Tell EPUBs from comic archives by their entries. An archive is a comic
+/// only when every entry is a page image or a comic sidecar.
+fn inspect_zip(path: &Path) -> Result<Option<DocumentFormat>> {
+    let mut archive = zip::ZipArchive::new(File::open(path)?)
+        .map_err(|e| BlinkerError::Parsing(format!("Failed to read zip archive: {}", e)))?;
+
+    if let Ok(mut entry) = archive.by_name("mimetype") {
+        let mut mimetype = String::new();
+        if entry.read_to_string(&mut mimetype).is_ok() && mimetype.trim() == EPUB_MIMETYPE {
+            return Ok(Some(DocumentFormat::Epub));
+        }
+    }
+    if archive.by_name("META-INF/container.xml").is_ok() {
+        return Ok(Some(DocumentFormat::Epub));
+    }
+    let mut pages = 0;
+    for name in archive.file_names() {
+        if ComicRenderer::is_image_file(name) {
+            pages += 1;
+        } else if !is_comic_extra(name) {
+            return Ok(None);
+        }
+    }
+    if pages > 0 {
+        return Ok(Some(DocumentFormat::Cbz));
+    }
+    Ok(None)
+}
+
+fn extension_format(path: &Path) -> Option<DocumentFormat> {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .and_then(DocumentFormat::from_extension)
+}
+
+/// How far the extension of `path` leaves room for sniffing. Text
+/// extensions are trusted as they are; no extension, `.zip` and the binary
+/// formats are fully inspected; anything else must carry a signature.
+fn sniffing(path: &Path) -> Sniffing {
+    match path.extension().and_then(|e| e.to_str()) {
+        None => Sniffing::Full,
+        Some(ext) => match DocumentFormat::from_extension(ext) {
+            Some(DocumentFormat::Txt | DocumentFormat::Markdown) => Sniffing::Never,
+            Some(_) => Sniffing::Full,
+            None if ext.eq_ignore_ascii_case("zip") => Sniffing::Full,
+            None => Sniffing::Signatures,
+        },
+    }
+}
+
+/// Format of `path`: its contents when they carry a signature, otherwise its
+/// extension. Logs a warning when the two disagree. Text files are taken at
+/// their extension without being read.
+pub fn detect(path: &Path) -> Result<DocumentFormat> {
+    let by_ext = extension_format(path);
+    let sniffing = sniffing(path);
+    if sniffing == Sniffing::Never {
+        return by_ext.ok_or_else(|| BlinkerError::Parsing(format!("Unsupported format: {}", path.display())));
+    }
+    let by_content = match sniff_as(path, sniffing) {
+        Ok(format) => format,
+        Err(e) => {
+            tracing::debug!("Could not inspect {:?}: {}", path, e);
+            None
+        }
+    };
+
+    match (by_content, by_ext) {
+        (Some(content), Some(ext)) if content != ext => {
+            tracing::warn!(
+                "{} has a .{} extension but contains {}; treating it as {}",
+                path.display(),
+                ext.as_str(),
+                content.as_str(),
+                content.as_str()
+            );
+            Ok(content)
+        }
+        (Some(format), _) | (None, Some(format)) => Ok(format),
+        (None, None) => Err(BlinkerError::Parsing(format!("Unsupported format: {}", path.display()))),
+    }
+}
+
+/// Whether `path` is a document this crate can open: it has a known
+/// extension, or its contents are recognised. A `.zip` or extensionless file
+/// may be any supported archive; other extensions need a PDF, RAR or EPUB
+/// signature.
+pub fn is_document(path: &Path) -> bool {
+    if extension_format(path).is_some() {
+        return true;
+    }
+    matches!(sniff_as(path, sniffing(path)), Ok(Some(_)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    fn write_file(dir: &Path, name: &str, bytes: &[u8]) -> std::path::PathBuf {
+        let path = dir.join(name);
+        std::fs::write(&path, bytes).unwrap();
+        path
+    }
+
+    fn write_zip(dir: &Path, name: &str, entries: &[(&str, &[u8])]) -> std::path::PathBuf {
+        let path = dir.join(name);
+        let mut zip = zip::ZipWriter::new(File::create(&path).unwrap());
+        for (entry, bytes) in entries {
+            zip.start_file(*entry, SimpleFileOptions::default()).unwrap();
+            zip.write_all(bytes).unwrap();
+        }
+        zip.finish().unwrap();
+        path
+    }
+
+    #[test]
+    fn pdf_signature_only_at_start() {
+        let dir = tempfile::tempdir().unwrap();
+        let pdf = write_file(dir.path(), "doc", b"%PDF-1.7\n%\xe2\xe3\xcf\xd3\n");
+        assert_eq!(sniff(&pdf).unwrap(), Some(DocumentFormat::Pdf));
+        let quoted = write_file(dir.path(), "note", b"# Notes\nThe header is %PDF-1.7.\n");
+        assert_eq!(sniff(&quoted).unwrap(), None);
+    }
+
+    #[test]
+    fn text_extensions_are_not_overridden() {
+        let dir = tempfile::tempdir().unwrap();
+        let md = write_file(dir.path(), "note.md", b"%PDF-1.7 as a first line\n");
+        assert_eq!(detect(&md).unwrap(), DocumentFormat::Markdown);
+        let txt = write_file(dir.path(), "note.txt", b"Rar!\x1a\x07\x00");
+        assert_eq!(detect(&txt).unwrap(), DocumentFormat::Txt);
+    }
+
+    #[test]
+    fn misnamed_binary_is_detected_by_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let pdf = write_file(dir.path(), "book.epub", b"%PDF-1.4\n");
+        assert_eq!(detect(&pdf).unwrap(), DocumentFormat::Pdf);
+    }
+
+    #[test]
+    fn zip_with_epub_mimetype_is_epub() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_zip(dir.path(), "book", &[
+            ("mimetype", EPUB_MIMETYPE.as_bytes()),
+            ("OEBPS/cover.jpg", b"jpg"),
+            ("OEBPS/content.opf", b"<package/>"),
+        ]);
+        assert_eq!(inspect_zip(&path).unwrap(), Some(DocumentFormat::Epub));
+    }
+
+    #[test]
+    fn zip_of_images_is_comic() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = write_zip(dir.path(), "issue.zip", &[
+            ("ComicInfo.xml", b"<ComicInfo/>"),
+            ("01.jpg", b"jpg"),
+            ("02.png", b"png"),
+            ("__MACOSX/._01.jpg", b""),
+        ]);
+        assert_eq!(inspect_zip(&path).unwrap(), Some(DocumentFormat::Cbz));
+        assert!(is_document(&path));
+    }
+
+    #[test]
+    fn zip_with_other_files_is_not_comic() {
+        let dir = tempfile::tempdir().unwrap();
+        let docx = write_zip(dir.path(), "report.docx", &[
+            ("[Content_Types].xml", b"<Types/>"),
+            ("word/document.xml", b"<document/>"),
+            ("word/media/image1.png", b"png"),
+        ]);
+        assert_eq!(inspect_zip(&docx).unwrap(), None);
+        assert!(!is_document(&docx));
+        let backup = write_zip(dir.path(), "backup.zip", &[
+            ("photos/a.jpg", b"jpg"),
+            ("notes.md", b"# notes"),
+        ]);
+        assert_eq!(inspect_zip(&backup).unwrap(), None);
+        assert!(!is_document(&backup));
+    }
+
+    #[test]
+    fn unknown_extensions_need_a_signature() {
+        let dir = tempfile::tempdir().unwrap();
+        let pdf = write_file(dir.path(), "pdf-as.bin", b"%PDF-1.7\n");
+        assert!(is_document(&pdf));
+        assert_eq!(detect(&pdf).unwrap(), DocumentFormat::Pdf);
+        let rar = write_file(dir.path(), "comic.part", b"Rar!\x1a\x07\x01\x00");
+        assert_eq!(detect(&rar).unwrap(), DocumentFormat::Cbr);
+        let epub = write_zip(dir.path(), "book.download", &[
+            ("mimetype", EPUB_MIMETYPE.as_bytes()),
+            ("OEBPS/content.opf", b"<package/>"),
+        ]);
+        assert_eq!(detect(&epub).unwrap(), DocumentFormat::Epub);
+
+        let random = write_file(dir.path(), "random.dat", &[0x13, 0x37, 0x00, 0xff, 0x25, 0x50, 0x44, 0x46]);
+        assert!(!is_document(&random));
+        assert!(detect(&random).is_err());
+        // Images alone do not make a comic without a comic or zip extension
+        let pages = write_zip(dir.path(), "pages.dat", &[("01.jpg", b"jpg")]);
+        assert!(!is_document(&pages));
+        let bare = write_zip(dir.path(), "pages", &[("01.jpg", b"jpg")]);
+        assert!(is_document(&bare));
+    }
+}
@@ -1,15 +1,18 @@
-/// Document rendering: PDF via PDFium, EPUB via HTML flow, images for CBZ/CBR.
-///
-/// This crate handles:
-/// - PDF rendering with JavaScript disabled
-/// - EPUB layout and rendering
-/// - Comic book archive unpacking and rendering
-/// - Text and Markdown rendering
+//! Document rendering: PDF via PDFium, EPUB via HTML flow, images for CBZ/CBR.
+//!
+//! This crate handles:
+//! - PDF rendering with JavaScript disabled
+//! - EPUB layout and rendering
+//! - Comic book archive unpacking and rendering
+//! - Text and Markdown rendering
 
 pub mod pdf;
 pub mod epub;
 pub mod comic;
 pub mod text;
+pub mod format;
+pub mod thumbnail;
+pub mod rar;
 
 pub use pdf::PdfRenderer;
 pub use epub::EpubRenderer;
@@ -31,6 +34,12 @@ pub struct RenderSearchMatch {
     pub text: String,
 }
 
+/// Plain text of one page, used for content indexing.
+pub struct PageText {
+    pub page: usize,
+    pub text: String,
+}
+
 /// Common interface for document renderers.
 pub trait DocumentRenderer {
     /// Open a renderer for the given file path.
@@ -48,6 +57,11 @@ pub trait DocumentRenderer {
     fn search(&self, _query: &str, _limit: usize) -> Result<Vec<RenderSearchMatch>> {
         Ok(vec![])
     }
+
+    /// Plain text of a page (starts at 1); empty for image-only formats.
+    fn page_text(&self, _page: usize) -> Result<String> {
+        Ok(String::new())
+    }
 }
 
 /// Lightweight handle for rendering that avoids storing non-Send backends.
@@ -57,17 +71,19 @@ pub struct AnyRenderer {
 }
 
 impl AnyRenderer {
-    /// Create a handle based on file extension.
+    /// Create a handle for the format detected from the file's contents,
+    /// falling back to its extension.
     pub fn open_for(path: &Path) -> Result<Self> {
-        let ext = path
-            .extension()
-            .and_then(|s| s.to_str())
-            .ok_or_else(|| blinker_core_common::BlinkerError::Parsing("Missing file extension".into()))?;
-        if let Some(kind) = DocumentFormat::from_extension(ext) {
-            Ok(Self { path: path.to_path_buf(), kind })
-        } else {
-            Err(blinker_core_common::BlinkerError::Parsing(format!("Unsupported format: {}", ext)))
-        }
+        Ok(Self::with_format(path, format::detect(path)?))
+    }
+
+    /// Create a handle for a file whose format is already known.
+    pub fn with_format(path: &Path, kind: DocumentFormat) -> Self {
+        Self { path: path.to_path_buf(), kind }
+    }
+
+    pub fn format(&self) -> DocumentFormat {
+        self.kind
     }
 
     /// Get page count by opening the appropriate backend on-demand.
@@ -96,6 +112,37 @@ impl AnyRenderer {
         }
     }
 
+    /// Cover image: the first page of PDFs and comics, the declared cover of
+    /// EPUBs. `None` for documents without one.
+    pub fn render_cover(&self) -> Result<Option<RenderedPage>> {
+        match self.kind {
+            DocumentFormat::Pdf | DocumentFormat::Cbz => self.render_page(1).map(Some),
+            DocumentFormat::Epub => epub::EpubRenderer::cover(&self.path),
+            DocumentFormat::Cbr | DocumentFormat::Txt | DocumentFormat::Markdown => Ok(None),
+        }
+    }
+
+    /// Extract the text of every page, skipping pages without text.
+    pub fn extract_text(&self) -> Result<Vec<PageText>> {
+        fn collect<R: DocumentRenderer>(renderer: R) -> Result<Vec<PageText>> {
+            let mut pages = Vec::new();
+            for page in 1..=renderer.page_count()? {
+                let text = renderer.page_text(page)?;
+                if !text.trim().is_empty() {
+                    pages.push(PageText { page, text });
+                }
+            }
+            Ok(pages)
+        }
+
+        match self.kind {
+            DocumentFormat::Pdf => collect(PdfRenderer::open(&self.path)?),
+            DocumentFormat::Epub => collect(epub::EpubRenderer::open(&self.path)?),
+            DocumentFormat::Cbz | DocumentFormat::Cbr => Ok(vec![]),
+            DocumentFormat::Txt | DocumentFormat::Markdown => collect(text::TextRenderer::open(&self.path)?),
+        }
+    }
+
     /// Search within the document by opening the backend on-demand.
     pub fn search(&self, query: &str, limit: usize) -> Result<Vec<RenderSearchMatch>> {
         match self.kind {
@@ -135,4 +135,17 @@ impl DocumentRenderer for PdfRenderer {
 
         Ok(matches)
     }
+
+    fn page_text(&self, page: usize) -> Result<String> {
+        let page_idx_u16 = u16::try_from(page.saturating_sub(1))
+            .map_err(|_| BlinkerError::Rendering(format!("Invalid page index: {}", page)))?;
+        let pdf_page = self.document
+            .pages()
+            .get(page_idx_u16)
+            .map_err(|e| BlinkerError::Rendering(format!("Invalid page index {}: {:?}", page, e)))?;
+        let text = pdf_page
+            .text()
+            .map_err(|e| BlinkerError::Rendering(format!("Failed to extract text: {:?}", e)))?;
+        Ok(text.all())
+    }
 }
@@ -0,0 +1,368 @@
+//! Listing of RAR archives (versions 4 and 5) and reading of their stored
+//! entries.
+//!
+//! RAR's compression is proprietary, so only entries archived with the
+//! "store" method can be read. Comic archives are often made that way, since
+//! their pages are already compressed images; a compressed entry is listed
+//! but not readable.
+
+use blinker_core_common::{BlinkerError, Result};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+const RAR4_SIGNATURE: &[u8] = b"Rar!\x1a\x07\x00";
+const RAR5_SIGNATURE: &[u8] = b"Rar!\x1a\x07\x01\x00";
+
+/// One file or folder in a RAR archive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RarEntry {
+    /// Path inside the archive, with `/` separators.
+    pub name: String,
+    pub is_dir: bool,
+    /// Archived uncompressed, unencrypted and in one piece, so
+    /// [`read_entry`] can return it.
+    pub stored: bool,
+    /// Unpacked size in bytes.
+    pub size: u64,
+    offset: u64,
+    packed: u64,
+}
+
+fn truncated() -> BlinkerError {
+    BlinkerError::Parsing("truncated RAR header".into())
+}
+
+/// Little-endian fields and RAR5 variable-length integers of one header.
+struct Fields<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Fields<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8]> {
+        if n > self.bytes.len() {
+            return Err(truncated());
+        }
+        let (head, rest) = self.bytes.split_at(n);
+        self.bytes = rest;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16> {
+        Ok(u16::from_le_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn vint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for shift in (0..70).step_by(7) {
+            let byte = self.u8()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err(BlinkerError::Parsing("malformed RAR number".into()))
+    }
+}
+
+/// Read a RAR5 variable-length integer straight from the file, returning
+/// it with its length in bytes.
+fn read_vint(reader: &mut impl Read) -> Result<(u64, u64)> {
+    let mut value = 0u64;
+    for (i, shift) in (0..70).step_by(7).enumerate() {
+        let mut byte = [0u8];
+        reader.read_exact(&mut byte).map_err(|_| truncated())?;
+        value |= u64::from(byte[0] & 0x7f) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok((value, i as u64 + 1));
+        }
+    }
+    Err(BlinkerError::Parsing("malformed RAR number".into()))
+}
+
+/// Entries of the RAR archive at `path`, in archive order.
+pub fn entries(path: &Path) -> Result<Vec<RarEntry>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut signature = [0u8; 8];
+    reader.read_exact(&mut signature).map_err(|_| BlinkerError::Parsing("not a RAR archive".into()))?;
+    if signature.starts_with(RAR5_SIGNATURE) {
+        rar5_entries(&mut reader)
+    } else if signature.starts_with(RAR4_SIGNATURE) {
+        rar4_entries(&mut reader)
+    } else {
+        Err(BlinkerError::Parsing("not a RAR archive".into()))
+    }
+}
+
+fn rar4_entries(reader: &mut BufReader<File>) -> Result<Vec<RarEntry>> {
+    const MAIN: u8 = 0x73;
+    const FILE: u8 = 0x74;
+    const END: u8 = 0x7b;
+
+    let mut entries = vec![];
+    let mut pos = RAR4_SIGNATURE.len() as u64;
+    loop {
+        reader.seek(SeekFrom::Start(pos))?;
+        let mut base = [0u8; 7];
+        if reader.read_exact(&mut base).is_err() {
+            break;
+        }
+        let mut fields = Fields { bytes: &base[2..] };
+        let (kind, flags, size) = (fields.u8()?, fields.u16()?, u64::from(fields.u16()?));
+        if size < 7 {
+            return Err(truncated());
+        }
+        let mut header = vec![0u8; size as usize - 7];
+        reader.read_exact(&mut header).map_err(|_| truncated())?;
+        let mut fields = Fields { bytes: &header };
+        let next = match kind {
+            MAIN if flags & 0x80 != 0 => {
+                return Err(BlinkerError::Parsing("RAR archive headers are encrypted".into()));
+            }
+            FILE => {
+                let mut packed = u64::from(fields.u32()?);
+                let mut unpacked = u64::from(fields.u32()?);
+                let _host_crc_time_version = fields.take(10)?;
+                let method = fields.u8()?;
+                let name_len = fields.u16()? as usize;
+                let _attributes = fields.u32()?;
+                if flags & 0x100 != 0 {
+                    packed |= u64::from(fields.u32()?) << 32;
+                    unpacked |= u64::from(fields.u32()?) << 32;
+                }
+                let mut name = fields.take(name_len)?;
+                if flags & 0x200 != 0 {
+                    // Unicode names follow an ASCII form after a NUL
+                    name = name.split(|b| *b == 0).next().unwrap_or(name);
+                }
+                let offset = pos + size;
+                let split = flags & 0x03 != 0;
+                let encrypted = flags & 0x04 != 0;
+                entries.push(RarEntry {
+                    name: String::from_utf8_lossy(name).replace('\\', "/"),
+                    is_dir: flags & 0xe0 == 0xe0,
+                    stored: method == 0x30 && !split && !encrypted,
+                    size: unpacked,
+                    offset,
+                    packed,
+                });
+                offset + packed
+            }
+            END => break,
+            _ => {
+                let data = if flags & 0x8000 != 0 { u64::from(fields.u32()?) } else { 0 };
+                pos + size + data
+            }
+        };
+        pos = next;
+    }
+    Ok(entries)
+}
+
+fn rar5_entries(reader: &mut BufReader<File>) -> Result<Vec<RarEntry>> {
+    const FILE: u64 = 2;
+    const ENCRYPTION: u64 = 4;
+    const END: u64 = 5;
+    const EXTRA_ENCRYPTION: u64 = 1;
+
+    let mut entries = vec![];
+    let mut pos = RAR5_SIGNATURE.len() as u64;
+    loop {
+        reader.seek(SeekFrom::Start(pos))?;
+        let mut crc = [0u8; 4];
+        if reader.read_exact(&mut crc).is_err() {
+            break;
+        }
+        let (size, size_len) = read_vint(reader)?;
+        let start = pos + 4 + size_len;
+        let mut header = vec![0u8; usize::try_from(size).map_err(|_| truncated())?];
+        reader.read_exact(&mut header).map_err(|_| truncated())?;
+        let mut fields = Fields { bytes: &header };
+        let kind = fields.vint()?;
+        let flags = fields.vint()?;
+        let extra = if flags & 0x01 != 0 { fields.vint()? } else { 0 };
+        let data = if flags & 0x02 != 0 { fields.vint()? } else { 0 };
+        match kind {
+            FILE => {
+                let file_flags = fields.vint()?;
+                let unpacked = fields.vint()?;
+                let _attributes = fields.vint()?;
+                if file_flags & 0x02 != 0 {
+                    fields.u32()?;
+                }
+                if file_flags & 0x04 != 0 {
+                    fields.u32()?;
+                }
+                let compression = fields.vint()?;
+                let _host = fields.vint()?;
+                let name_len = usize::try_from(fields.vint()?).map_err(|_| truncated())?;
+                let name = fields.take(name_len)?;
+
+                let extra_start = header.len().checked_sub(extra as usize).ok_or_else(truncated)?;
+                let mut records = Fields { bytes: &header[extra_start..] };
+                let mut encrypted = false;
+                while !records.bytes.is_empty() {
+                    let record_len = usize::try_from(records.vint()?).map_err(|_| truncated())?;
+                    let mut record = Fields { bytes: records.take(record_len)? };
+                    encrypted |= record.vint()? == EXTRA_ENCRYPTION;
+                }
+                let split = flags & 0x18 != 0;
+                let method = (compression >> 7) & 0x07;
+                entries.push(RarEntry {
+                    name: String::from_utf8_lossy(name).into_owned(),
+                    is_dir: file_flags & 0x01 != 0,
+                    stored: method == 0 && !split && !encrypted,
+                    size: unpacked,
+                    offset: start + size,
+                    packed: data,
+                });
+            }
+            ENCRYPTION => return Err(BlinkerError::Parsing("RAR archive headers are encrypted".into())),
+            END => break,
+            _ => {}
+        }
+        pos = start + size + data;
+    }
+    Ok(entries)
+}
+
+/// Contents of a stored entry listed by [`entries`]. Fails for compressed
+/// entries and ones larger than `limit` bytes.
+pub fn read_entry(path: &Path, entry: &RarEntry, limit: u64) -> Result<Vec<u8>> {
+    if !entry.stored || entry.is_dir {
+        return Err(BlinkerError::Parsing(format!("RAR entry {} is compressed and cannot be read", entry.name)));
+    }
+    if entry.packed > limit {
+        return Err(BlinkerError::Parsing(format!("RAR entry {} is larger than {} bytes", entry.name, limit)));
+    }
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(entry.offset))?;
+    let mut bytes = vec![0u8; entry.packed as usize];
+    file.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn vint(mut value: u64) -> Vec<u8> {
+        let mut out = vec![];
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                out.push(byte);
+                return out;
+            }
+            out.push(byte | 0x80);
+        }
+    }
+
+    /// A RAR4 archive of `(name, data, method)` entries; checksums are left
+    /// zero since they are not verified.
+    fn rar4(entries: &[(&str, &[u8], u8)]) -> Vec<u8> {
+        let mut out = RAR4_SIGNATURE.to_vec();
+        out.extend([0, 0, 0x73, 0, 0, 13, 0, 0, 0, 0, 0, 0, 0]);
+        for (name, data, method) in entries {
+            let size = 7 + 25 + name.len();
+            out.extend([0, 0, 0x74, 0x00, 0x80]);
+            out.extend((size as u16).to_le_bytes());
+            out.extend((data.len() as u32).to_le_bytes());
+            out.extend((data.len() as u32).to_le_bytes());
+            out.extend([0; 10]);
+            out.push(*method);
+            out.extend((name.len() as u16).to_le_bytes());
+            out.extend([0; 4]);
+            out.extend(name.as_bytes());
+            out.extend(*data);
+        }
+        out.extend([0, 0, 0x7b, 0, 0x40, 7, 0]);
+        out
+    }
+
+    /// A RAR5 archive of `(name, data, method)` entries.
+    fn rar5(entries: &[(&str, &[u8], u64)]) -> Vec<u8> {
+        let block = |out: &mut Vec<u8>, fields: Vec<u8>| {
+            out.extend([0; 4]);
+            out.extend(vint(fields.len() as u64));
+            out.extend(fields);
+        };
+        let mut out = RAR5_SIGNATURE.to_vec();
+        block(&mut out, [vint(1), vint(0), vint(0)].concat());
+        for (name, data, method) in entries {
+            let fields = [
+                vint(2),
+                vint(0x02),
+                vint(data.len() as u64),
+                vint(0),
+                vint(data.len() as u64),
+                vint(0),
+                vint(method << 7),
+                vint(0),
+                vint(name.len() as u64),
+                name.as_bytes().to_vec(),
+            ]
+            .concat();
+            block(&mut out, fields);
+            out.extend(*data);
+        }
+        block(&mut out, [vint(5), vint(0), vint(0)].concat());
+        out
+    }
+
+    fn check(path: &Path) {
+        let listed = entries(path).unwrap();
+        let names: Vec<&str> = listed.iter().map(|e| e.name.as_str()).collect();
+        assert_eq!(names, ["001.jpg", "ComicInfo.xml", "packed.xml"]);
+        assert_eq!(read_entry(path, &listed[0], 1024).unwrap(), b"jpeg bytes");
+        assert_eq!(read_entry(path, &listed[1], 1024).unwrap(), b"<ComicInfo/>");
+        assert!(!listed[2].stored);
+        assert!(read_entry(path, &listed[2], 1024).is_err());
+        assert!(read_entry(path, &listed[1], 4).is_err());
+    }
+
+    #[test]
+    fn reads_stored_rar4_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("issue.cbr");
+        std::fs::write(&path, rar4(&[
+            ("001.jpg", b"jpeg bytes", 0x30),
+            ("ComicInfo.xml", b"<ComicInfo/>", 0x30),
+            ("packed.xml", b"\x01\x02", 0x33),
+        ]))
+        .unwrap();
+        check(&path);
+    }
+
+    #[test]
+    fn reads_stored_rar5_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("issue.cbr");
+        std::fs::write(&path, rar5(&[
+            ("001.jpg", b"jpeg bytes", 0),
+            ("ComicInfo.xml", b"<ComicInfo/>", 0),
+            ("packed.xml", b"\x01\x02", 3),
+        ]))
+        .unwrap();
+        check(&path);
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("issue.cbr");
+        std::fs::write(&path, b"PK\x03\x04 not a rar").unwrap();
+        assert!(entries(&path).is_err());
+        std::fs::write(&path, &rar5(&[("a.jpg", b"data", 0)])[..20]).unwrap();
+        assert!(entries(&path).is_err());
+    }
+}
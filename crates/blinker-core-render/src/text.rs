@@ -123,7 +123,7 @@ impl TextRenderer {
                 for col in 0..(metrics.width as i32) {
                     let dx = gx + col;
                     if dx < 0 || dx >= w_i { continue; }
-                    let src_alpha = bitmap[(row as usize) * (metrics.width as usize) + (col as usize)];
+                    let src_alpha = bitmap[(row as usize) * metrics.width + (col as usize)];
                     if src_alpha == 0 { continue; }
                     let idx = ((dy as u32) * width + (dx as u32)) as usize * 4;
                     // simple alpha-over on white: new = 255 - alpha
@@ -148,7 +148,7 @@ impl DocumentRenderer for TextRenderer {
 
         // Read the entire file
         let content = std::fs::read_to_string(path)
-            .map_err(|e| BlinkerError::Io(e))?;
+            .map_err(BlinkerError::Io)?;
 
         // Check if it's markdown based on extension
         let is_markdown = path.extension()
@@ -212,4 +212,11 @@ impl DocumentRenderer for TextRenderer {
 
         Ok(matches)
     }
+
+    fn page_text(&self, page: usize) -> Result<String> {
+        if page != 1 {
+            return Err(BlinkerError::Rendering(format!("Invalid page index: {}", page)));
+        }
+        Ok(self.get_text_content())
+    }
 }
@@ -0,0 +1,26 @@
+//! Downscaled previews of rendered pages, used for library covers.
+
+use blinker_core_common::{BlinkerError, Result};
+use image::{DynamicImage, RgbaImage};
+use std::io::Cursor;
+
+use crate::RenderedPage;
+
+/// Scale `page` to fit within `max_edge` pixels on its longer side,
+/// preserving aspect ratio, and encode it as JPEG. Smaller pages are not
+/// enlarged.
+pub fn encode_thumbnail(page: RenderedPage, max_edge: u32) -> Result<Vec<u8>> {
+    let image = RgbaImage::from_raw(page.width, page.height, page.pixels)
+        .ok_or_else(|| BlinkerError::Rendering("Page buffer does not match its dimensions".into()))?;
+    let mut image = DynamicImage::ImageRgba8(image);
+    if image.width() > max_edge || image.height() > max_edge {
+        image = image.thumbnail(max_edge, max_edge);
+    }
+
+    // JPEG has no alpha channel
+    let mut out = Cursor::new(Vec::new());
+    DynamicImage::ImageRgb8(image.to_rgb8())
+        .write_to(&mut out, image::ImageFormat::Jpeg)
+        .map_err(|e| BlinkerError::Rendering(format!("Failed to encode thumbnail: {}", e)))?;
+    Ok(out.into_inner())
+}
@@ -1,10 +1,10 @@
-/// Security layer: OS sandboxing, content sanitization, path validation.
-///
-/// This crate handles:
-/// - OS-level sandboxing (AppContainer on Windows, App Sandbox on macOS, seccomp-bpf on Linux)
-/// - Content sanitization for EPUB HTML
-/// - Path traversal prevention for archives
-/// - Network blocking
+//! Security layer: OS sandboxing, content sanitization, path validation.
+//!
+//! This crate handles:
+//! - OS-level sandboxing (AppContainer on Windows, App Sandbox on macOS, seccomp-bpf on Linux)
+//! - Content sanitization for EPUB HTML
+//! - Path traversal prevention for archives
+//! - Network blocking
 
 use blinker_core_common::Result;
 
@@ -47,7 +47,7 @@ pub mod sanitizer {
         Ok(html.to_string())
     }
 
-    pub fn validate_path(path: &str) -> Result<()> {
+    pub fn validate_path(_path: &str) -> Result<()> {
         // TODO: Validate path for traversal attacks
         // - No ".." components
         // - No absolute paths in archives
@@ -19,9 +19,9 @@ CREATE TABLE IF NOT EXISTS library_item (
     indexed_at INTEGER NOT NULL
 );
 
-CREATE INDEX idx_library_item_hash ON library_item(file_hash);
-CREATE INDEX idx_library_item_type ON library_item(file_type);
-CREATE INDEX idx_library_item_title ON library_item(title);
+CREATE INDEX IF NOT EXISTS idx_library_item_hash ON library_item(file_hash);
+CREATE INDEX IF NOT EXISTS idx_library_item_type ON library_item(file_type);
+CREATE INDEX IF NOT EXISTS idx_library_item_title ON library_item(title);
 
 -- Tags table
 CREATE TABLE IF NOT EXISTS tag (
@@ -31,7 +31,7 @@ CREATE TABLE IF NOT EXISTS tag (
     created_at INTEGER NOT NULL
 );
 
-CREATE INDEX idx_tag_name ON tag(name);
+CREATE INDEX IF NOT EXISTS idx_tag_name ON tag(name);
 
 -- Item-tag relationship (many-to-many)
 CREATE TABLE IF NOT EXISTS item_tag (
@@ -55,8 +55,8 @@ CREATE TABLE IF NOT EXISTS reading_state (
     FOREIGN KEY (item_id) REFERENCES library_item(id) ON DELETE CASCADE
 );
 
-CREATE INDEX idx_reading_state_item ON reading_state(item_id);
-CREATE INDEX idx_reading_state_last_opened ON reading_state(last_opened);
+CREATE INDEX IF NOT EXISTS idx_reading_state_item ON reading_state(item_id);
+CREATE INDEX IF NOT EXISTS idx_reading_state_last_opened ON reading_state(last_opened);
 
 -- Annotations table
 CREATE TABLE IF NOT EXISTS annotation (
@@ -75,9 +75,9 @@ CREATE TABLE IF NOT EXISTS annotation (
     FOREIGN KEY (item_id) REFERENCES library_item(id) ON DELETE CASCADE
 );
 
-CREATE INDEX idx_annotation_item ON annotation(item_id);
-CREATE INDEX idx_annotation_page ON annotation(item_id, page);
-CREATE INDEX idx_annotation_kind ON annotation(kind);
+CREATE INDEX IF NOT EXISTS idx_annotation_item ON annotation(item_id);
+CREATE INDEX IF NOT EXISTS idx_annotation_page ON annotation(item_id, page);
+CREATE INDEX IF NOT EXISTS idx_annotation_kind ON annotation(kind);
 
 -- Full-text search virtual table
 CREATE VIRTUAL TABLE IF NOT EXISTS library_fts USING fts5(
@@ -113,5 +113,3 @@ CREATE TABLE IF NOT EXISTS schema_version (
     version INTEGER PRIMARY KEY,
     applied_at INTEGER NOT NULL
 );
-
-INSERT INTO schema_version (version, applied_at) VALUES (1, strftime('%s', 'now'));
@@ -0,0 +1,64 @@
+-- Keep the `tags` column of library_fts in sync with item_tag.
+--
+-- The original library_fts was an external-content table over library_item,
+-- which has no tags column, so its triggers always indexed ''. Rebuild it as a
+-- standalone FTS5 table and maintain tags from item_tag and tag triggers.
+
+DROP TRIGGER IF EXISTS library_item_ai;
+DROP TRIGGER IF EXISTS library_item_ad;
+DROP TRIGGER IF EXISTS library_item_au;
+DROP TABLE IF EXISTS library_fts;
+
+CREATE VIRTUAL TABLE library_fts USING fts5(
+    item_id UNINDEXED,
+    title,
+    author,
+    subject,
+    tags
+);
+
+INSERT INTO library_fts(rowid, item_id, title, author, subject, tags)
+SELECT li.rowid, li.id, li.title, li.author, li.subject,
+       ifnull((SELECT group_concat(t.name, ' ')
+               FROM item_tag it JOIN tag t ON t.id = it.tag_id
+               WHERE it.item_id = li.id), '')
+FROM library_item li;
+
+CREATE TRIGGER library_item_ai AFTER INSERT ON library_item BEGIN
+    INSERT INTO library_fts(rowid, item_id, title, author, subject, tags)
+    VALUES (NEW.rowid, NEW.id, NEW.title, NEW.author, NEW.subject, '');
+END;
+
+CREATE TRIGGER library_item_ad AFTER DELETE ON library_item BEGIN
+    DELETE FROM library_fts WHERE rowid = OLD.rowid;
+END;
+
+CREATE TRIGGER library_item_au AFTER UPDATE OF id, title, author, subject ON library_item BEGIN
+    UPDATE library_fts
+    SET item_id = NEW.id, title = NEW.title, author = NEW.author, subject = NEW.subject
+    WHERE rowid = OLD.rowid;
+END;
+
+CREATE TRIGGER item_tag_ai AFTER INSERT ON item_tag BEGIN
+    UPDATE library_fts
+    SET tags = ifnull((SELECT group_concat(t.name, ' ')
+                       FROM item_tag it JOIN tag t ON t.id = it.tag_id
+                       WHERE it.item_id = NEW.item_id), '')
+    WHERE rowid = (SELECT rowid FROM library_item WHERE id = NEW.item_id);
+END;
+
+CREATE TRIGGER item_tag_ad AFTER DELETE ON item_tag BEGIN
+    UPDATE library_fts
+    SET tags = ifnull((SELECT group_concat(t.name, ' ')
+                       FROM item_tag it JOIN tag t ON t.id = it.tag_id
+                       WHERE it.item_id = OLD.item_id), '')
+    WHERE rowid = (SELECT rowid FROM library_item WHERE id = OLD.item_id);
+END;
+
+CREATE TRIGGER tag_au AFTER UPDATE OF name ON tag BEGIN
+    UPDATE library_fts
+    SET tags = ifnull((SELECT group_concat(t.name, ' ')
+                       FROM item_tag it JOIN tag t ON t.id = it.tag_id
+                       WHERE it.item_id = library_fts.item_id), '')
+    WHERE item_id IN (SELECT item_id FROM item_tag WHERE tag_id = NEW.id);
+END;
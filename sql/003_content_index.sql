@@ -0,0 +1,39 @@
+-- Full-text index of document contents, one row per page.
+
+CREATE TABLE IF NOT EXISTS content_page (
+    id INTEGER PRIMARY KEY,
+    item_id TEXT NOT NULL,
+    page INTEGER NOT NULL,
+    body TEXT NOT NULL,
+    FOREIGN KEY (item_id) REFERENCES library_item(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_content_page_item ON content_page(item_id, page);
+
+CREATE VIRTUAL TABLE IF NOT EXISTS content_fts USING fts5(
+    body,
+    content='content_page',
+    content_rowid='id',
+    tokenize='unicode61 remove_diacritics 2'
+);
+
+CREATE TRIGGER IF NOT EXISTS content_page_ai AFTER INSERT ON content_page BEGIN
+    INSERT INTO content_fts(rowid, body) VALUES (NEW.id, NEW.body);
+END;
+
+CREATE TRIGGER IF NOT EXISTS content_page_ad AFTER DELETE ON content_page BEGIN
+    INSERT INTO content_fts(content_fts, rowid, body) VALUES ('delete', OLD.id, OLD.body);
+END;
+
+CREATE TRIGGER IF NOT EXISTS content_page_au AFTER UPDATE ON content_page BEGIN
+    INSERT INTO content_fts(content_fts, rowid, body) VALUES ('delete', OLD.id, OLD.body);
+    INSERT INTO content_fts(rowid, body) VALUES (NEW.id, NEW.body);
+END;
+
+-- Hash of the file contents that content_page currently reflects.
+CREATE TABLE IF NOT EXISTS content_index_state (
+    item_id TEXT PRIMARY KEY,
+    file_hash TEXT NOT NULL,
+    indexed_at INTEGER NOT NULL,
+    FOREIGN KEY (item_id) REFERENCES library_item(id) ON DELETE CASCADE
+);
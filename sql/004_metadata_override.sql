@@ -0,0 +1,11 @@
+-- User edits to item metadata. A row here wins over extracted metadata
+-- whenever the item is re-indexed; `value` may be NULL to blank a field.
+
+CREATE TABLE IF NOT EXISTS metadata_override (
+    item_id TEXT NOT NULL,
+    field TEXT NOT NULL,
+    value TEXT,
+    updated_at INTEGER NOT NULL,
+    PRIMARY KEY (item_id, field),
+    FOREIGN KEY (item_id) REFERENCES library_item(id) ON DELETE CASCADE
+);
@@ -0,0 +1,8 @@
+-- Document-level timestamps from the file's own metadata (e.g. PDF CreationDate).
+-- created_at/modified_at on library_item track the row itself.
+
+ALTER TABLE library_item ADD COLUMN doc_created_at INTEGER;
+ALTER TABLE library_item ADD COLUMN doc_modified_at INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_library_item_language ON library_item(language);
+CREATE INDEX IF NOT EXISTS idx_library_item_page_count ON library_item(page_count);
@@ -0,0 +1,6 @@
+-- When set, the item's file was not found during the last scan of its root.
+-- Missing items are kept (with their annotations) until explicitly pruned.
+
+ALTER TABLE library_item ADD COLUMN missing_since INTEGER;
+
+CREATE INDEX IF NOT EXISTS idx_library_item_missing ON library_item(missing_since);
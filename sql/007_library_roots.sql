@@ -0,0 +1,14 @@
+-- Directories the library is built from, each scanned with its own rules.
+-- Pattern lists are JSON arrays of glob strings.
+
+CREATE TABLE IF NOT EXISTS library_root (
+    id TEXT PRIMARY KEY,
+    path TEXT NOT NULL UNIQUE,
+    include_patterns TEXT NOT NULL DEFAULT '[]',
+    exclude_patterns TEXT NOT NULL DEFAULT '[]',
+    max_depth INTEGER,
+    follow_symlinks INTEGER NOT NULL DEFAULT 0,
+    enabled INTEGER NOT NULL DEFAULT 1,
+    created_at INTEGER NOT NULL,
+    last_scanned_at INTEGER
+);
@@ -0,0 +1,5 @@
+-- File modification time (nanoseconds since the Unix epoch) recorded at
+-- indexing. Together with file_size it lets rescans skip re-hashing files
+-- that have not changed. NULL until the file is next indexed.
+
+ALTER TABLE library_item ADD COLUMN file_mtime INTEGER;
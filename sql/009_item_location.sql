@@ -0,0 +1,23 @@
+-- Every path a document is found at. `library_item.file_path` remains the
+-- item's primary location: a present one when there is any. An item is
+-- missing only once all of its locations are.
+
+CREATE TABLE IF NOT EXISTS item_location (
+    id INTEGER PRIMARY KEY,
+    item_id TEXT NOT NULL,
+    file_path TEXT NOT NULL UNIQUE,
+    file_size INTEGER NOT NULL,
+    file_mtime INTEGER,
+    missing_since INTEGER,
+    added_at INTEGER NOT NULL,
+    seen_at INTEGER NOT NULL,
+    FOREIGN KEY (item_id) REFERENCES library_item(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_item_location_item ON item_location(item_id);
+
+INSERT INTO item_location (item_id, file_path, file_size, file_mtime, missing_since, added_at, seen_at)
+SELECT id, file_path, file_size, file_mtime, missing_since, created_at, indexed_at FROM library_item;
+
+-- Modification times are tracked per location now
+ALTER TABLE library_item DROP COLUMN file_mtime;
@@ -0,0 +1,25 @@
+-- User collections. Manual collections hold an ordered list of items; smart
+-- collections store a serialized LibraryQuery and are evaluated when read.
+
+CREATE TABLE IF NOT EXISTS collection (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    -- JSON-encoded LibraryQuery; NULL for manual collections
+    query TEXT,
+    created_at INTEGER NOT NULL,
+    modified_at INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS collection_item (
+    collection_id TEXT NOT NULL,
+    item_id TEXT NOT NULL,
+    -- 0-based, contiguous within a collection
+    position INTEGER NOT NULL,
+    added_at INTEGER NOT NULL,
+    PRIMARY KEY (collection_id, item_id),
+    FOREIGN KEY (collection_id) REFERENCES collection(id) ON DELETE CASCADE,
+    FOREIGN KEY (item_id) REFERENCES library_item(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_collection_item_position ON collection_item(collection_id, position);
+CREATE INDEX IF NOT EXISTS idx_collection_item_item ON collection_item(item_id);
@@ -0,0 +1,7 @@
+-- Series a document belongs to and its position in it. Existing items are
+-- filled in by re-extracting metadata (`blinker-cli backfill`).
+
+ALTER TABLE library_item ADD COLUMN series TEXT;
+ALTER TABLE library_item ADD COLUMN series_index REAL;
+
+CREATE INDEX IF NOT EXISTS idx_library_item_series ON library_item(series COLLATE NOCASE, series_index);
@@ -0,0 +1,14 @@
+-- Standard identifiers of a document (ISBN, DOI, UUID, ...). `scheme` is
+-- lowercase; `source` records where a value came from so re-extraction can
+-- replace its own values without touching imported ones.
+
+CREATE TABLE IF NOT EXISTS item_identifier (
+    item_id TEXT NOT NULL,
+    scheme TEXT NOT NULL,
+    value TEXT NOT NULL,
+    source TEXT NOT NULL,
+    PRIMARY KEY (item_id, scheme, value),
+    FOREIGN KEY (item_id) REFERENCES library_item(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_item_identifier_value ON item_identifier(scheme, value);
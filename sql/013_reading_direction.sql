@@ -0,0 +1,4 @@
+-- Documents read right to left (manga). Existing comics are filled in by
+-- re-extracting metadata (`blinker-cli backfill`).
+
+ALTER TABLE library_item ADD COLUMN right_to_left INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,19 @@
+-- ISBNs are stored as ISBN-13 under the `isbn13` scheme so that ISBN-10 and
+-- ISBN-13 printings of the same edition match. Rewrites rows recorded as
+-- `isbn`, converting ten-digit values (978 prefix, recomputed check digit).
+
+UPDATE OR IGNORE item_identifier
+SET scheme = 'isbn13',
+    value = CASE length(value)
+        WHEN 13 THEN value
+        ELSE '978' || substr(value, 1, 9) || ((10 - (38
+            + 3 * (substr(value, 1, 1) + substr(value, 3, 1) + substr(value, 5, 1)
+                 + substr(value, 7, 1) + substr(value, 9, 1))
+            + substr(value, 2, 1) + substr(value, 4, 1) + substr(value, 6, 1) + substr(value, 8, 1)) % 10) % 10)
+    END
+WHERE scheme = 'isbn'
+  AND length(value) IN (10, 13)
+  AND substr(value, 1, 9) NOT GLOB '*[^0-9]*';
+
+-- Left over: duplicates of a row rewritten above, and malformed values
+DELETE FROM item_identifier WHERE scheme = 'isbn';
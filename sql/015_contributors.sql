@@ -0,0 +1,25 @@
+-- People credited on documents. One `contributor` row per person, found by
+-- `match_key` (the name folded to lowercase ASCII words, surname last) so
+-- the same name written differently across files maps to one row.
+-- `library_item.author` stays as the display string of the authors.
+-- Existing items get their contributors on the next metadata backfill.
+
+CREATE TABLE IF NOT EXISTS contributor (
+    id TEXT PRIMARY KEY,
+    name TEXT NOT NULL,
+    sort_name TEXT NOT NULL,
+    match_key TEXT NOT NULL UNIQUE,
+    created_at INTEGER NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS item_contributor (
+    item_id TEXT NOT NULL,
+    contributor_id TEXT NOT NULL,
+    role TEXT NOT NULL,
+    position INTEGER NOT NULL,
+    PRIMARY KEY (item_id, contributor_id, role),
+    FOREIGN KEY (item_id) REFERENCES library_item(id) ON DELETE CASCADE,
+    FOREIGN KEY (contributor_id) REFERENCES contributor(id) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_item_contributor_contributor ON item_contributor(contributor_id);
@@ -0,0 +1,5 @@
+-- Opening a single-page or flow document used to record it as fully read.
+-- Nothing could mark one finished explicitly before this migration, so every
+-- such state came from merely opening it: restart those at 0.
+
+UPDATE reading_state SET progress = 0.0 WHERE total_pages <= 1 AND progress >= 1.0;
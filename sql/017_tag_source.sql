@@ -0,0 +1,6 @@
+-- Record where an item's tag came from. NULL means the user assigned it;
+-- `file` marks tags read from the document (e.g. Markdown front matter), which
+-- re-extraction replaces without touching the user's own. Tags assigned before
+-- this migration cannot be told apart and are kept as the user's.
+
+ALTER TABLE item_tag ADD COLUMN source TEXT;